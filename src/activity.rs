@@ -0,0 +1,104 @@
+use crate::{
+    assignment::Assignment,
+    error::Error,
+    util::{load_json, save_json},
+    work_directory::WorkDirectory,
+};
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+// Where a cached assignment came from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WorkSource {
+    Primenet,
+    Gpu72,
+}
+
+impl WorkSource {
+    // How it's named in --output json events.
+    pub fn name(&self) -> &'static str {
+        match self {
+            WorkSource::Primenet => "primenet",
+            WorkSource::Gpu72 => "gpu72",
+        }
+    }
+}
+
+impl fmt::Display for WorkSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WorkSource::Primenet => write!(f, "Primenet"),
+            WorkSource::Gpu72 => write!(f, "GPU to 72"),
+        }
+    }
+}
+
+// What we've done in a work directory, for the status subcommand. It lives in the work directory
+// as activity.json.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Activity {
+    pub last_fetch: Option<DateTime<Utc>>,
+    // The last time Primenet accepted a result.
+    pub last_submit: Option<DateTime<Utc>>,
+    // Where each cached assignment we fetched came from, by its worktodo line. Lines added by hand
+    // aren't in here.
+    pub sources: BTreeMap<String, WorkSource>,
+}
+
+impl Activity {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        load_json(path, "activity file")
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        save_json(path, self)
+    }
+
+    pub fn source(&self, assignment: &Assignment) -> Option<WorkSource> {
+        self.sources.get(&assignment.line).copied()
+    }
+}
+
+// Load, change and save the activity file. It's only bookkeeping, so failing to is a warning
+// rather than a reason to stop.
+fn update(work_directory: &WorkDirectory, change: impl FnOnce(&mut Activity)) {
+    let updated = Activity::load(&work_directory.activity).and_then(|mut activity| {
+        change(&mut activity);
+        activity.save(&work_directory.activity)
+    });
+    if let Err(e) = updated {
+        warn!(
+            "Failed to update {}. {}",
+            work_directory.activity.display(),
+            e
+        );
+    }
+}
+
+// Note newly cached assignments. `cached` is what was in the worktodo beforehand, and the sources
+// of anything no longer in it are forgotten. Only called with the worktodo locked.
+pub fn record_fetch(
+    work_directory: &WorkDirectory,
+    source: WorkSource,
+    cached: &[String],
+    fetched: &[String],
+    now: DateTime<Utc>,
+) {
+    update(work_directory, |activity| {
+        activity.last_fetch = Some(now);
+        activity
+            .sources
+            .retain(|line, _| cached.iter().any(|cached| cached.trim() == line));
+        for line in fetched {
+            activity.sources.insert(line.trim().to_string(), source);
+        }
+    });
+}
+
+pub fn record_submit(work_directory: &WorkDirectory, now: DateTime<Utc>) {
+    update(work_directory, |activity| activity.last_submit = Some(now));
+}
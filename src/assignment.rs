@@ -0,0 +1,212 @@
+use crate::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AssignmentKind {
+    Test,
+    DoubleCheck,
+    Prp,
+    PrpDoubleCheck,
+    Factor,
+    Pfactor,
+    Pminus1,
+    Ecm,
+    // Checking a PRP proof someone else uploaded, which takes a small fraction of a full test.
+    Cert,
+}
+
+impl AssignmentKind {
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "Test" => Some(AssignmentKind::Test),
+            "DoubleCheck" => Some(AssignmentKind::DoubleCheck),
+            "PRP" => Some(AssignmentKind::Prp),
+            "PRPDC" => Some(AssignmentKind::PrpDoubleCheck),
+            "Factor" => Some(AssignmentKind::Factor),
+            // gpuowl spells it PFactor.
+            "Pfactor" | "PFactor" => Some(AssignmentKind::Pfactor),
+            "Pminus1" => Some(AssignmentKind::Pminus1),
+            "ECM2" => Some(AssignmentKind::Ecm),
+            "Cert" => Some(AssignmentKind::Cert),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AssignmentKind::Test => "Test",
+            AssignmentKind::DoubleCheck => "DoubleCheck",
+            AssignmentKind::Prp => "PRP",
+            AssignmentKind::PrpDoubleCheck => "PRPDC",
+            AssignmentKind::Factor => "Factor",
+            AssignmentKind::Pfactor => "Pfactor",
+            AssignmentKind::Pminus1 => "Pminus1",
+            AssignmentKind::Ecm => "ECM2",
+            AssignmentKind::Cert => "Cert",
+        }
+    }
+
+    // Whether the line describes the number as k,b,n,c (k*b^n+c) instead of just the exponent.
+    fn uses_kbnc(&self) -> bool {
+        !matches!(
+            self,
+            AssignmentKind::Test | AssignmentKind::DoubleCheck | AssignmentKind::Factor
+        )
+    }
+}
+
+impl fmt::Display for AssignmentKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+// A single line of a worktodo file, e.g.
+//     Test=7A30B8B6C0FC79C534A271D9561F7DCC,89459323,76,1
+//     PRP=BC914675C81023F252E92CF034BEFF6C,1,2,96364649,-1,76,0
+//     Factor=N/A,332194529,74,75
+//     Cert=B2EE67DC0A514E85D0F8A82544CF9B17,1,2,110000117,-1,430000
+#[derive(Clone, Debug, PartialEq)]
+pub struct Assignment {
+    pub kind: AssignmentKind,
+    // Assignment ID. Lines without one (or with N/A, like GPU to 72 hands out) have none.
+    pub aid: Option<String>,
+    pub exponent: u64,
+    // Numeric fields after the AID, in the order they appear in the line.
+    pub fields: Vec<String>,
+    pub line: String,
+}
+
+fn is_aid(field: &str) -> bool {
+    field.len() == 32 && field.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+impl Assignment {
+    pub fn parse(line: &str) -> Result<Self, Error> {
+        let line = line.trim();
+        // gpuowl lets a line start with its own settings, e.g. "B1=1000000;PFactor=...".
+        let assignment = line.rsplit(';').next().unwrap_or(line);
+        let mut key_value = assignment.splitn(2, '=');
+        let key = key_value.next().unwrap_or("").trim();
+        let kind = AssignmentKind::from_key(key)
+            .ok_or_else(|| Error::Parse(format!("Unknown assignment type in line '{}'.", line)))?;
+        let mut fields = key_value
+            .next()
+            .ok_or_else(|| Error::Parse(format!("Missing '=' in assignment line '{}'.", line)))?
+            .split(',')
+            .map(|field| field.trim().to_string())
+            .collect::<Vec<_>>();
+        let aid = match fields.first().map(String::as_str) {
+            Some("N/A") => {
+                fields.remove(0);
+                None
+            }
+            Some(field) if is_aid(field) => Some(fields.remove(0)),
+            _ => None,
+        };
+        let exponent_index = if kind.uses_kbnc() { 2 } else { 0 };
+        let exponent = fields
+            .get(exponent_index)
+            .and_then(|field| field.parse::<u64>().ok())
+            .ok_or_else(|| {
+                Error::Parse(format!(
+                    "Couldn't find the exponent in assignment line '{}'.",
+                    line
+                ))
+            })?;
+        Ok(Assignment {
+            kind,
+            aid,
+            exponent,
+            fields,
+            line: line.to_string(),
+        })
+    }
+}
+
+impl FromStr for Assignment {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Assignment::parse(s)
+    }
+}
+
+impl fmt::Display for Assignment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.line)
+    }
+}
+
+// Deterministic Miller-Rabin. Testing against the first 12 primes as bases is enough for every
+// number that fits in 64 bits.
+pub fn is_prime(n: u64) -> bool {
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if n < 2 {
+        return false;
+    }
+    for &p in &BASES {
+        if n % p == 0 {
+            return n == p;
+        }
+    }
+    let mul_mod = |a: u64, b: u64| ((a as u128 * b as u128) % n as u128) as u64;
+    let pow_mod = |mut base: u64, mut exponent: u64| {
+        let mut result = 1;
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = mul_mod(result, base);
+            }
+            base = mul_mod(base, base);
+            exponent >>= 1;
+        }
+        result
+    };
+    let shift = (n - 1).trailing_zeros();
+    let d = (n - 1) >> shift;
+    BASES.iter().all(|&a| {
+        let mut x = pow_mod(a, d);
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+        for _ in 1..shift {
+            x = mul_mod(x, x);
+            if x == n - 1 {
+                return true;
+            }
+        }
+        false
+    })
+}
+
+// Lines that are allowed in a worktodo file but aren't assignments: blank lines, comments and
+// Prime95-style [Worker #1] section headers.
+pub fn is_worktodo_filler(line: &str) -> bool {
+    let line = line.trim();
+    line.is_empty() || line.starts_with('#') || line.starts_with(';') || line.starts_with('[')
+}
+
+// Lowest and highest exponents to accept work for, inclusive. Either end can be left open.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExponentRange {
+    pub min: Option<u64>,
+    pub max: Option<u64>,
+}
+
+impl ExponentRange {
+    pub fn contains(&self, exponent: u64) -> bool {
+        self.min.map_or(true, |min| exponent >= min) && self.max.map_or(true, |max| exponent <= max)
+    }
+}
+
+impl fmt::Display for ExponentRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.min, self.max) {
+            (None, None) => write!(f, "any exponent"),
+            (Some(min), None) => write!(f, "M{} and up", min),
+            (None, Some(max)) => write!(f, "up to M{}", max),
+            (Some(min), Some(max)) => write!(f, "M{} to M{}", min, max),
+        }
+    }
+}
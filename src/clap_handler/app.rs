@@ -1,40 +1,22 @@
-use super::gpu72_work::*;
 use super::lists::*;
-use super::p95_work::*;
 use super::validators::*;
-use clap::{App, Arg, ArgGroup};
+use clap::{App, Arg, ArgGroup, ArgMatches};
+use log::LevelFilter;
+use primenet_rs::{
+    assignment::{AssignmentKind, ExponentRange},
+    client::Client,
+    endpoints::{Endpoints, DEFAULT_GPU72_URL, DEFAULT_PRIMENET_URL},
+    gpu72_work::*,
+    logging::{level_from_verbosity, parse_target_filters},
+    options::*,
+    p95_work::*,
+    retry::{RetryPolicy, DEFAULT_RETRIES, DEFAULT_RETRY_DELAY, DEFAULT_RETRY_MAX_DELAY},
+};
 use std::env::current_dir;
 use std::fs::File;
 use std::io::{BufReader, Read};
-
-#[derive(Clone, Debug)]
-pub struct GeneralOptions {
-    pub work_directory: String,
-    pub num_cache: usize,
-    pub timeout: usize,
-}
-
-#[derive(Clone, Debug)]
-pub struct PrimenetOptions {
-    pub credentials: (String, String),
-    pub work_type: PrimenetWorkType,
-    pub general_options: GeneralOptions,
-}
-
-#[derive(Clone, Debug)]
-pub struct Gpu72Options {
-    pub primenet_credentials: Option<(String, String)>,
-    pub gpu72_credentials: (String, String),
-    pub work_type: Gpu72WorkType,
-    pub max_exp: u8,
-    pub general_options: GeneralOptions,
-}
-
-#[derive(Clone, Debug)]
-pub enum Options {
-    Primenet(PrimenetOptions),
-    Gpu72(Gpu72Options),
-}
+use std::path::Path;
+use std::time::Duration;
 
 macro_rules! map_matches {
     (
@@ -112,7 +94,7 @@ macro_rules! map_matches_simple {
     }}
 }
 
-const GPU72_TYPES_AND_OPTS_HELP: &'static str = r"GPU to 72 work types and options:
+const GPU72_TYPES_AND_OPTS_HELP: &str = r"GPU to 72 work types and options:
     - Lucas-Lehmer trial factoring             --gpu72-lucas-lehmer-trial-factor
         - What makes sense                         --gpu72-what-makes-sense
         - Lowest trial factor level                --gpu72-lowest-trial-factor-level
@@ -135,12 +117,375 @@ const GPU72_TYPES_AND_OPTS_HELP: &'static str = r"GPU to 72 work types and optio
         - Lowest exponent                          --gpu72-lowest-exponent
         - Oldest exponent                          --gpu72-oldest-exponent";
 
-pub fn request_from_args() -> Result<Options, String> {
+const DEFAULT_LOG_FILE_LEVEL: &str = "debug";
+const DEFAULT_LOG_MAX_SIZE: u64 = 10 * 1024 * 1024;
+const DEFAULT_LOG_MAX_FILES: usize = 5;
+const DEFAULT_COOKIE_JAR: &str = "cookies.json";
+
+// Global arguments are propagated down to the subcommand matches, so this is given those.
+fn logging_options_from_matches(matches: &ArgMatches) -> Result<LoggingOptions, String> {
+    let level = level_from_verbosity(
+        matches.occurrences_of("verbose"),
+        matches.occurrences_of("quiet"),
+        matches.is_present("debug"),
+    );
+    let log_file = matches.value_of("log-file").map(|path| LogFileOptions {
+        path: path.to_string(),
+        level: matches
+            .value_of("log-file-level")
+            .unwrap_or(DEFAULT_LOG_FILE_LEVEL)
+            .parse::<LevelFilter>()
+            .unwrap(),
+        max_size: matches
+            .value_of("log-max-size")
+            .map(|s| s.parse::<u64>().unwrap())
+            .unwrap_or(DEFAULT_LOG_MAX_SIZE),
+        max_files: matches
+            .value_of("log-max-files")
+            .map(|s| s.parse::<usize>().unwrap())
+            .unwrap_or(DEFAULT_LOG_MAX_FILES),
+    });
+    let target_filters = match matches.value_of("log-filter") {
+        Some(filters) => parse_target_filters(filters)?,
+        None => Vec::new(),
+    };
+    Ok(LoggingOptions {
+        level,
+        log_file,
+        target_filters,
+    })
+}
+
+fn traffic_options_from_matches(matches: &ArgMatches) -> TrafficOptions {
+    if let Some(dir) = matches.value_of("record") {
+        TrafficOptions::Record(dir.to_string())
+    } else if let Some(dir) = matches.value_of("replay") {
+        TrafficOptions::Replay(dir.to_string())
+    } else {
+        TrafficOptions::Live
+    }
+}
+
+fn output_from_matches(matches: &ArgMatches) -> OutputFormat {
+    matches
+        .value_of("output")
+        .and_then(OutputFormat::from_name)
+        .unwrap_or(OutputFormat::Text)
+}
+
+fn retry_policy_from_matches(matches: &ArgMatches) -> RetryPolicy {
+    let seconds = |name, default| {
+        Duration::from_secs(
+            matches
+                .value_of(name)
+                .map(|s| s.parse::<u64>().unwrap())
+                .unwrap_or(default),
+        )
+    };
+    RetryPolicy {
+        retries: matches
+            .value_of("retries")
+            .map(|s| s.parse::<u32>().unwrap())
+            .unwrap_or(DEFAULT_RETRIES),
+        initial_delay: seconds("retry-delay", DEFAULT_RETRY_DELAY),
+        max_delay: seconds("retry-max-delay", DEFAULT_RETRY_MAX_DELAY),
+    }
+}
+
+fn exponent_range_from_matches(matches: &ArgMatches) -> Result<ExponentRange, String> {
+    let bound = |name| matches.value_of(name).map(|s| s.parse::<u64>().unwrap());
+    let exponent_range = ExponentRange {
+        min: bound("exp-min"),
+        max: bound("exp-max"),
+    };
+    match (exponent_range.min, exponent_range.max) {
+        (Some(min), Some(max)) if min > max => Err(format!(
+            "--exp-min ({}) is greater than --exp-max ({}).",
+            min, max
+        )),
+        _ => Ok(exponent_range),
+    }
+}
+
+// Refuse work types that give out assignments the client can't run.
+fn check_client_can_run(
+    client: Option<Client>,
+    kinds: &[AssignmentKind],
+    work_type: &str,
+) -> Result<(), String> {
+    match client {
+        Some(client) if !kinds.iter().any(|kind| client.can_run(*kind)) => {
+            Err(format!("{} can't run {} work.", client, work_type))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn number_from_matches(matches: &ArgMatches, name: &str) -> Option<f64> {
+    matches.value_of(name).map(|s| s.parse::<f64>().unwrap())
+}
+
+fn cookie_jar_from_matches(matches: &ArgMatches, work_directory: &str) -> Option<String> {
+    if matches.is_present("no-cookie-jar") {
+        None
+    } else {
+        Some(matches.value_of("cookie-jar").map_or_else(
+            || {
+                Path::new(work_directory)
+                    .join(DEFAULT_COOKIE_JAR)
+                    .display()
+                    .to_string()
+            },
+            str::to_string,
+        ))
+    }
+}
+
+fn endpoints_from_matches(matches: &ArgMatches) -> Endpoints {
+    let endpoints = Endpoints::new(
+        matches
+            .value_of("primenet-url")
+            .unwrap_or(DEFAULT_PRIMENET_URL),
+        matches.value_of("gpu72-url").unwrap_or(DEFAULT_GPU72_URL),
+    );
+    match matches.value_of("proof-upload-url") {
+        Some(url) => endpoints.with_proof_upload(url),
+        None => endpoints,
+    }
+}
+
+pub fn request_from_args() -> Result<(GlobalOptions, Options), String> {
     let current_dir = format!("{}", current_dir().unwrap().display());
     let matches = App::new("primenet-rs")
         .version("1.0.0")
         .about("Interface to request from and report to Primenet (GIMPS) and GPU to 72.")
         .author("Aurorans Solis")
+        .arg(
+            Arg::with_name("verbose")
+                .short('v')
+                .long("verbose")
+                .multiple(true)
+                .global(true)
+                .help("Show more output. Use twice for trace output.")
+        )
+        .arg(
+            Arg::with_name("quiet")
+                .short('q')
+                .long("quiet")
+                .multiple(true)
+                .global(true)
+                .help("Show less output. Use twice to only show errors.")
+        )
+        .arg(
+            Arg::with_name("debug")
+                .long("debug")
+                .global(true)
+                .help("Show debugging output. Same as -v.")
+        )
+        .arg(
+            Arg::with_name("log-file")
+                .long("log-file")
+                .takes_value(true)
+                .number_of_values(1)
+                .value_name("FILE_PATH")
+                .global(true)
+                .help("Also write log output to this file")
+        )
+        .arg(
+            Arg::with_name("log-file-level")
+                .long("log-file-level")
+                .takes_value(true)
+                .number_of_values(1)
+                .value_name("LEVEL")
+                .validator(log_level_validator)
+                .global(true)
+                .help(
+                    "Level of output written to the log file: off, error, warn, info, debug or \
+                    trace. Defaults to debug."
+                )
+        )
+        .arg(
+            Arg::with_name("log-max-size")
+                .long("log-max-size")
+                .takes_value(true)
+                .number_of_values(1)
+                .value_name("BYTES")
+                .validator(numeric_validator)
+                .global(true)
+                .help(
+                    "Size at which the log file is rotated to FILE_PATH.1, FILE_PATH.2 and so on. \
+                    Defaults to 10 MiB."
+                )
+        )
+        .arg(
+            Arg::with_name("log-max-files")
+                .long("log-max-files")
+                .takes_value(true)
+                .number_of_values(1)
+                .value_name("NUM")
+                .validator(numeric_validator)
+                .global(true)
+                .help("Number of rotated log files to keep. Defaults to 5.")
+        )
+        .arg(
+            Arg::with_name("log-filter")
+                .long("log-filter")
+                .takes_value(true)
+                .number_of_values(1)
+                .value_name("FILTERS")
+                .validator(log_filter_validator)
+                .global(true)
+                .help(
+                    "Per-subsystem log levels, overriding -v/-q and --log-file-level. For \
+                    example 'network=debug,locking=off'. Subsystems are network, locking and \
+                    parsing."
+                )
+        )
+        .arg(
+            Arg::with_name("primenet-url")
+                .long("primenet-url")
+                .takes_value(true)
+                .number_of_values(1)
+                .value_name("URL")
+                .validator(url_validator)
+                .global(true)
+                .help(
+                    "Base URL of the Primenet server, for a mirror or local stand-in. Defaults to \
+                    https://www.mersenne.org/."
+                )
+        )
+        .arg(
+            Arg::with_name("proof-upload-url")
+                .long("proof-upload-url")
+                .takes_value(true)
+                .number_of_values(1)
+                .value_name("URL")
+                .validator(url_validator)
+                .global(true)
+                .help(
+                    "Where to upload PRP proof files. Defaults to proof_upload/ on the Primenet \
+                    server."
+                )
+        )
+        .arg(
+            Arg::with_name("gpu72-url")
+                .long("gpu72-url")
+                .takes_value(true)
+                .number_of_values(1)
+                .value_name("URL")
+                .validator(url_validator)
+                .global(true)
+                .help(
+                    "Base URL of the GPU to 72 server, for a mirror or local stand-in. Defaults \
+                    to https://www.gpu72.com/."
+                )
+        )
+        .arg(
+            Arg::with_name("retries")
+                .long("retries")
+                .takes_value(true)
+                .number_of_values(1)
+                .value_name("NUM")
+                .validator(u32_validator)
+                .global(true)
+                .help(
+                    "How many times to retry a request that failed to connect, timed out or got \
+                    a 5xx or 429 response. Submissions are only retried if they failed to \
+                    connect, since Primenet may already have them. Defaults to 3. Use 0 to never \
+                    retry."
+                )
+        )
+        .arg(
+            Arg::with_name("retry-delay")
+                .long("retry-delay")
+                .takes_value(true)
+                .number_of_values(1)
+                .value_name("SECONDS")
+                .validator(numeric_validator)
+                .global(true)
+                .help(
+                    "Delay before the first retry. It doubles for each retry after that, with \
+                    some random jitter. Defaults to 5."
+                )
+        )
+        .arg(
+            Arg::with_name("retry-max-delay")
+                .long("retry-max-delay")
+                .takes_value(true)
+                .number_of_values(1)
+                .value_name("SECONDS")
+                .validator(numeric_validator)
+                .global(true)
+                .help("Longest delay between retries. Defaults to 300.")
+        )
+        .arg(
+            Arg::with_name("cookie-jar")
+                .long("cookie-jar")
+                .takes_value(true)
+                .number_of_values(1)
+                .value_name("FILE_PATH")
+                .global(true)
+                .help(
+                    "File to keep the Primenet login session in between runs, readable only by \
+                    you. Defaults to cookies.json in the work directory."
+                )
+        )
+        .arg(
+            Arg::with_name("no-cookie-jar")
+                .long("no-cookie-jar")
+                .conflicts_with("cookie-jar")
+                .global(true)
+                .help("Don't keep the login session between runs, and log in every time")
+        )
+        .arg(
+            Arg::with_name("record")
+                .long("record")
+                .takes_value(true)
+                .number_of_values(1)
+                .value_name("DIR")
+                .conflicts_with("replay")
+                .global(true)
+                .help(
+                    "Save every request and response to this directory, with passwords redacted. \
+                    The directory is created if needed."
+                )
+        )
+        .arg(
+            Arg::with_name("replay")
+                .long("replay")
+                .takes_value(true)
+                .number_of_values(1)
+                .value_name("DIR")
+                .validator(recording_validator)
+                .global(true)
+                .help(
+                    "Serve the requests and responses saved with --record from this directory \
+                    instead of using the network"
+                )
+        )
+        .arg(
+            Arg::with_name("dry-run")
+                .long("dry-run")
+                .global(true)
+                .help(
+                    "Log in and print the work that would be requested and the results that \
+                    would be submitted, without requesting or submitting anything or changing \
+                    any file"
+                )
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .takes_value(true)
+                .number_of_values(1)
+                .value_name("FORMAT")
+                .validator(output_validator)
+                .global(true)
+                .help(
+                    "text (the default), or json to print fetches, submissions, errors and \
+                    status as one versioned JSON event per line. Logs still go to stderr."
+                )
+        )
         .subcommand(
             App::new("p95")
                 .author("Aurorans Solis")
@@ -181,9 +526,89 @@ pub fn request_from_args() -> Result<Options, String> {
                                 without looping."
                         ),
                 )
+                .arg(
+                    Arg::with_name("days-of-work")
+                        .long("days-of-work")
+                        .takes_value(true)
+                        .number_of_values(1)
+                        .value_name("DAYS")
+                        .validator(positive_number_validator)
+                        .help(
+                            "Cache enough work to last this many days instead of --num-cache \
+                            assignments, going by a rough estimate of how many GHz-days each \
+                            assignment is and --throughput, or the throughput measured in the \
+                            work directory without it"
+                        )
+                )
+                .arg(
+                    Arg::with_name("throughput")
+                        .long("throughput")
+                        .takes_value(true)
+                        .number_of_values(1)
+                        .value_name("GHZ_DAYS")
+                        .validator(positive_number_validator)
+                        .help(
+                            "GHz-days of work done per day, for sizing --days-of-work caches. \
+                            Overrides the measured throughput."
+                        )
+                )
+                .arg(
+                    Arg::with_name("exp-min")
+                        .long("exp-min")
+                        .takes_value(true)
+                        .number_of_values(1)
+                        .value_name("EXPONENT")
+                        .validator(u64_validator)
+                        .help("Lowest exponent to accept assignments for")
+                )
+                .arg(
+                    Arg::with_name("exp-max")
+                        .long("exp-max")
+                        .takes_value(true)
+                        .number_of_values(1)
+                        .value_name("EXPONENT")
+                        .validator(u64_validator)
+                        .help(
+                            "Highest exponent to accept assignments for. Assignments outside the \
+                            range are never cached, even if the server hands them out."
+                        )
+                )
+                .arg(
+                    Arg::with_name("cores")
+                        .long("cores")
+                        .takes_value(true)
+                        .number_of_values(1)
+                        .value_name("NUM")
+                        .default_value("1")
+                        .validator(cores_validator)
+                        .help("Number of cores the assignments will be run on")
+                )
+                .arg(
+                    Arg::with_name("client")
+                        .long("client")
+                        .takes_value(true)
+                        .number_of_values(1)
+                        .value_name("CLIENT")
+                        .validator(client_validator)
+                        .help(
+                            "The program working out of the work directory: mfaktc, mfakto, \
+                            mlucas, gpuowl, cudalucas or mprime. Decides the worktodo and results \
+                            file names, and work it can't run is never cached. Without it the \
+                            file names are guessed."
+                        )
+                )
                 .group(
                     ArgGroup::with_name("general options")
-                        .args(&["work-directory", "num-cache", "timeout"])
+                        .args(&[
+                            "work-directory",
+                            "num-cache",
+                            "days-of-work",
+                            "throughput",
+                            "timeout",
+                            "exp-min",
+                            "exp-max",
+                            "client",
+                        ])
                         .multiple(true)
                 )
                 .arg(
@@ -314,6 +739,11 @@ pub fn request_from_args() -> Result<Options, String> {
                             "Request double-checks of PRP tests on Mersenne cofactors from Primenet"
                         )
                 )
+                .arg(
+                    Arg::with_name("cert")
+                        .long("cert")
+                        .help("Request certification of uploaded PRP proofs from Primenet")
+                )
                 .group(
                     ArgGroup::with_name("worktype")
                         .args(&[
@@ -331,10 +761,40 @@ pub fn request_from_args() -> Result<Options, String> {
                             "100m-digits-prp",
                             "first-prp-on-mersenne-cofactors",
                             "double-check-prp-on-mersenne-cofactors",
+                            "cert",
                         ])
                         .required(true)
                         .multiple(false)
                 )
+                .arg(
+                    Arg::with_name("fallback-work-type")
+                        .long("fallback-work-type")
+                        .takes_value(true)
+                        .number_of_values(1)
+                        .value_name("WORK_TYPE")
+                        .validator(primenet_work_type_validator)
+                        .help(
+                            "Work type to request instead when Primenet has none of the chosen \
+                            type left, named like its option, e.g. double-check-ll"
+                        )
+                )
+                .arg(
+                    Arg::with_name("no-proof-upload")
+                        .long("no-proof-upload")
+                        .help(
+                            "Leave PRP proof files alone instead of uploading them once their \
+                            results are submitted"
+                        )
+                )
+                .arg(
+                    Arg::with_name("delete-uploaded-proofs")
+                        .long("delete-uploaded-proofs")
+                        .conflicts_with("no-proof-upload")
+                        .help(
+                            "Delete PRP proof files once they're uploaded, instead of moving them \
+                            to $WORKDIR/proofs-uploaded/"
+                        )
+                )
         )
         .subcommand(
             App::new("gpu72")
@@ -377,9 +837,79 @@ pub fn request_from_args() -> Result<Options, String> {
                                 without looping."
                         )
                 )
+                .arg(
+                    Arg::with_name("days-of-work")
+                        .long("days-of-work")
+                        .takes_value(true)
+                        .number_of_values(1)
+                        .value_name("DAYS")
+                        .validator(positive_number_validator)
+                        .help(
+                            "Cache enough work to last this many days instead of --num-cache \
+                            assignments, going by a rough estimate of how many GHz-days each \
+                            assignment is and --throughput, or the throughput measured in the \
+                            work directory without it"
+                        )
+                )
+                .arg(
+                    Arg::with_name("throughput")
+                        .long("throughput")
+                        .takes_value(true)
+                        .number_of_values(1)
+                        .value_name("GHZ_DAYS")
+                        .validator(positive_number_validator)
+                        .help(
+                            "GHz-days of work done per day, for sizing --days-of-work caches. \
+                            Overrides the measured throughput."
+                        )
+                )
+                .arg(
+                    Arg::with_name("exp-min")
+                        .long("exp-min")
+                        .takes_value(true)
+                        .number_of_values(1)
+                        .value_name("EXPONENT")
+                        .validator(u64_validator)
+                        .help("Lowest exponent to accept assignments for")
+                )
+                .arg(
+                    Arg::with_name("exp-max")
+                        .long("exp-max")
+                        .takes_value(true)
+                        .number_of_values(1)
+                        .value_name("EXPONENT")
+                        .validator(u64_validator)
+                        .help(
+                            "Highest exponent to accept assignments for. Assignments outside the \
+                            range are never cached, even if the server hands them out."
+                        )
+                )
+                .arg(
+                    Arg::with_name("client")
+                        .long("client")
+                        .takes_value(true)
+                        .number_of_values(1)
+                        .value_name("CLIENT")
+                        .validator(client_validator)
+                        .help(
+                            "The program working out of the work directory: mfaktc, mfakto, \
+                            mlucas, gpuowl, cudalucas or mprime. Decides the worktodo and results \
+                            file names, and work it can't run is never cached. Without it the \
+                            file names are guessed."
+                        )
+                )
                 .group(
                     ArgGroup::with_name("general options")
-                        .args(&["work-directory", "num-cache", "timeout"])
+                        .args(&[
+                            "work-directory",
+                            "num-cache",
+                            "days-of-work",
+                            "throughput",
+                            "timeout",
+                            "exp-min",
+                            "exp-max",
+                            "client",
+                        ])
                         .multiple(true)
                 )
                 .arg(
@@ -598,6 +1128,78 @@ pub fn request_from_args() -> Result<Options, String> {
                         .required_unless_one(&GPU72LGPU72D_LIST)
                         .conflicts_with_all(&GPU72LGPU72D_LIST)
                 )
+        )
+        .subcommand(
+            App::new("queue")
+                .author("Aurorans Solis")
+                .version("1.0.0")
+                .about(
+                    "Show results waiting to be submitted to Primenet, and those quarantined \
+                    instead"
+                )
+                .arg(
+                    Arg::with_name("work-directory")
+                        .short('w')
+                        .long("work-directory")
+                        .takes_value(true)
+                        .number_of_values(1)
+                        .value_name("WORKDIR")
+                        .default_value(&current_dir)
+                        .validator(directory_validator)
+                        .help("Working directory with the submission queue")
+                )
+        )
+        .subcommand(
+            App::new("factors")
+                .author("Aurorans Solis")
+                .version("1.0.0")
+                .about(
+                    "Show the factors in results.sent, with their bit size, k and class, and the \
+                    trial factoring yield by bit level"
+                )
+                .arg(
+                    Arg::with_name("work-directory")
+                        .short('w')
+                        .long("work-directory")
+                        .takes_value(true)
+                        .number_of_values(1)
+                        .value_name("WORKDIR")
+                        .default_value(&current_dir)
+                        .validator(directory_validator)
+                        .help("Working directory with results.sent")
+                )
+        )
+        .subcommand(
+            App::new("status")
+                .author("Aurorans Solis")
+                .version("1.0.0")
+                .about(
+                    "Show the assignments cached in a work directory, progress on the current \
+                    one, results waiting to be submitted, locks held and measured throughput"
+                )
+                .arg(
+                    Arg::with_name("work-directory")
+                        .short('w')
+                        .long("work-directory")
+                        .takes_value(true)
+                        .number_of_values(1)
+                        .value_name("WORKDIR")
+                        .default_value(&current_dir)
+                        .validator(directory_validator)
+                        .help("Working directory to show the status of")
+                )
+                .arg(
+                    Arg::with_name("client")
+                        .long("client")
+                        .takes_value(true)
+                        .number_of_values(1)
+                        .value_name("CLIENT")
+                        .validator(client_validator)
+                        .help(
+                            "The program working out of the work directory, which decides its \
+                            file names. Without it the file names are guessed."
+                        )
+                )
         ).try_get_matches().map_err(|e| format!("{}", e))?;
     if let Some(matches) = matches.subcommand_matches("gpu72") {
         let gpu72_credentials = if matches.is_present("gpu72-userpass") {
@@ -667,7 +1269,20 @@ pub fn request_from_args() -> Result<Options, String> {
         let general_options = GeneralOptions {
             work_directory,
             num_cache,
+            days_of_work: number_from_matches(matches, "days-of-work"),
+            throughput: number_from_matches(matches, "throughput"),
             timeout,
+            endpoints: endpoints_from_matches(matches),
+            exponent_range: exponent_range_from_matches(matches)?,
+            client: matches.value_of("client").and_then(Client::from_name),
+        };
+        let global_options = GlobalOptions {
+            logging_options: logging_options_from_matches(matches)?,
+            output: output_from_matches(matches),
+            dry_run: matches.is_present("dry-run"),
+            traffic: traffic_options_from_matches(matches),
+            retry_policy: retry_policy_from_matches(matches),
+            cookie_jar: cookie_jar_from_matches(matches, &general_options.work_directory),
         };
         let work_type = map_matches!(
             matches,
@@ -696,13 +1311,18 @@ pub fn request_from_args() -> Result<Options, String> {
                 _ -> Gpu72LLP1WorkOption::WhatMakesSense;
             }
         );
-        Ok(Options::Gpu72(Gpu72Options {
-            primenet_credentials,
-            gpu72_credentials,
-            work_type,
-            max_exp,
-            general_options,
-        }))
+        let kind = work_type.assignment_kind();
+        check_client_can_run(general_options.client, &[kind], kind.as_str())?;
+        Ok((
+            global_options,
+            Options::Gpu72(Gpu72Options {
+                primenet_credentials,
+                gpu72_credentials,
+                work_type,
+                max_exp,
+                general_options,
+            }),
+        ))
     } else if let Some(matches) = matches.subcommand_matches("p95") {
         let username = if matches.is_present("username") {
             matches.value_of("username").unwrap().to_string()
@@ -741,7 +1361,20 @@ pub fn request_from_args() -> Result<Options, String> {
         let general_options = GeneralOptions {
             work_directory,
             num_cache,
+            days_of_work: number_from_matches(matches, "days-of-work"),
+            throughput: number_from_matches(matches, "throughput"),
             timeout,
+            endpoints: endpoints_from_matches(matches),
+            exponent_range: exponent_range_from_matches(matches)?,
+            client: matches.value_of("client").and_then(Client::from_name),
+        };
+        let global_options = GlobalOptions {
+            logging_options: logging_options_from_matches(matches)?,
+            output: output_from_matches(matches),
+            dry_run: matches.is_present("dry-run"),
+            traffic: traffic_options_from_matches(matches),
+            retry_policy: retry_policy_from_matches(matches),
+            cookie_jar: cookie_jar_from_matches(matches, &general_options.work_directory),
         };
         let work_type = map_matches_simple!(
             matches,
@@ -761,13 +1394,84 @@ pub fn request_from_args() -> Result<Options, String> {
             "world-record-prp" => PrimenetWorkType::WorldRecordPrpTests;
             "100m-digits-prp" => PrimenetWorkType::HundredMillionDigitsPrpTests;
             "first-prp-on-mersenne-cofactors" => PrimenetWorkType::FirstPrpTestsOnMersenneCofactors;
+            "cert" => PrimenetWorkType::Certification;
             _ => PrimenetWorkType::DoubleCheckPrpTestsOnMersenneCofactors;
         );
-        Ok(Options::Primenet(PrimenetOptions {
-            credentials,
-            work_type,
-            general_options,
-        }))
+        let cores = matches.value_of("cores").unwrap().parse::<usize>().unwrap();
+        let proofs = if matches.is_present("no-proof-upload") {
+            ProofOptions::NoUpload
+        } else if matches.is_present("delete-uploaded-proofs") {
+            ProofOptions::Delete
+        } else {
+            ProofOptions::Archive
+        };
+        let fallback_work_type = matches
+            .value_of("fallback-work-type")
+            .and_then(PrimenetWorkType::from_name);
+        for work_type in Some(work_type).iter().chain(fallback_work_type.iter()) {
+            check_client_can_run(
+                general_options.client,
+                work_type.assignment_kinds(),
+                work_type.name(),
+            )?;
+        }
+        Ok((
+            global_options,
+            Options::Primenet(PrimenetOptions {
+                credentials,
+                work_type,
+                fallback_work_type,
+                cores,
+                proofs,
+                general_options,
+            }),
+        ))
+    } else if let Some(matches) = matches.subcommand_matches("queue") {
+        let global_options = GlobalOptions {
+            logging_options: logging_options_from_matches(matches)?,
+            output: output_from_matches(matches),
+            dry_run: matches.is_present("dry-run"),
+            traffic: traffic_options_from_matches(matches),
+            retry_policy: retry_policy_from_matches(matches),
+            cookie_jar: None,
+        };
+        Ok((
+            global_options,
+            Options::Queue(QueueOptions {
+                work_directory: matches.value_of("work-directory").unwrap().to_string(),
+            }),
+        ))
+    } else if let Some(matches) = matches.subcommand_matches("factors") {
+        let global_options = GlobalOptions {
+            logging_options: logging_options_from_matches(matches)?,
+            output: output_from_matches(matches),
+            dry_run: matches.is_present("dry-run"),
+            traffic: traffic_options_from_matches(matches),
+            retry_policy: retry_policy_from_matches(matches),
+            cookie_jar: None,
+        };
+        Ok((
+            global_options,
+            Options::Factors(FactorsOptions {
+                work_directory: matches.value_of("work-directory").unwrap().to_string(),
+            }),
+        ))
+    } else if let Some(matches) = matches.subcommand_matches("status") {
+        let global_options = GlobalOptions {
+            logging_options: logging_options_from_matches(matches)?,
+            output: output_from_matches(matches),
+            dry_run: matches.is_present("dry-run"),
+            traffic: traffic_options_from_matches(matches),
+            retry_policy: retry_policy_from_matches(matches),
+            cookie_jar: None,
+        };
+        Ok((
+            global_options,
+            Options::Status(StatusOptions {
+                work_directory: matches.value_of("work-directory").unwrap().to_string(),
+                client: matches.value_of("client").and_then(Client::from_name),
+            }),
+        ))
     } else {
         Err("No subcommand specified.".to_string())
     }
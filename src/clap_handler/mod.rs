@@ -1,5 +1,3 @@
 pub mod app;
-pub mod gpu72_work;
 mod lists;
-pub mod p95_work;
 pub mod validators;
@@ -1,3 +1,9 @@
+use log::LevelFilter;
+use primenet_rs::client::{Client, CLIENTS};
+use primenet_rs::logging::parse_target_filters;
+use primenet_rs::options::OutputFormat;
+use primenet_rs::p95_work::{PrimenetWorkType, PRIMENET_WORK_TYPES};
+use reqwest::Url;
 use std::fs::{read_dir, File};
 use std::path::Path;
 
@@ -73,6 +79,36 @@ pub fn numeric_validator(s: String) -> Result<(), String> {
     }
 }
 
+pub fn u32_validator(s: String) -> Result<(), String> {
+    numeric_validator(s.clone())?;
+    s.parse::<u32>()
+        .map(|_| ())
+        .map_err(|e| format!("Invalid number: '{}'. Details: {}", s, e))
+}
+
+pub fn u64_validator(s: String) -> Result<(), String> {
+    numeric_validator(s.clone())?;
+    s.parse::<u64>()
+        .map(|_| ())
+        .map_err(|e| format!("Invalid number: '{}'. Details: {}", s, e))
+}
+
+pub fn positive_number_validator(s: String) -> Result<(), String> {
+    match s.parse::<f64>() {
+        Ok(n) if n > 0.0 && n.is_finite() => Ok(()),
+        Ok(_) => Err(format!("'{}' isn't a number greater than 0.", s)),
+        Err(e) => Err(format!("Invalid number: '{}'. Details: {}", s, e)),
+    }
+}
+
+pub fn cores_validator(s: String) -> Result<(), String> {
+    numeric_validator(s.clone())?;
+    match s.parse::<usize>() {
+        Ok(0) => Err("The number of cores must be at least 1".to_string()),
+        _ => Ok(()),
+    }
+}
+
 pub fn p95_username_validator(s: String) -> Result<(), String> {
     if s.is_ascii() {
         if s.chars()
@@ -94,7 +130,7 @@ pub fn file_validator(s: String) -> Result<(), String> {
     let path = Path::new(&s);
     if path.exists() {
         if path.is_file() {
-            match File::open(&path) {
+            match File::open(path) {
                 Ok(_) => Ok(()),
                 Err(e) => Err(format!("Error opening file '{}': {}", s, e)),
             }
@@ -113,3 +149,79 @@ pub fn gpu72_username_validator(s: String) -> Result<(), String> {
         Err(format!("Username '{}' is not ASCII.", s))
     }
 }
+
+pub fn log_level_validator(s: String) -> Result<(), String> {
+    s.parse::<LevelFilter>().map(|_| ()).map_err(|_| {
+        format!(
+            "Invalid log level '{}'. Expected one of off, error, warn, info, debug or trace.",
+            s
+        )
+    })
+}
+
+pub fn log_filter_validator(s: String) -> Result<(), String> {
+    parse_target_filters(&s).map(|_| ())
+}
+
+pub fn url_validator(s: String) -> Result<(), String> {
+    match Url::parse(&s) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => Ok(()),
+        Ok(url) => Err(format!(
+            "URL '{}' has scheme '{}', expected http or https.",
+            s,
+            url.scheme()
+        )),
+        Err(e) => Err(format!("Invalid URL '{}'. Error: {}", s, e)),
+    }
+}
+
+pub fn recording_validator(s: String) -> Result<(), String> {
+    let path = Path::new(&s);
+    if path.is_dir() {
+        read_dir(path)
+            .map(|_| ())
+            .map_err(|e| format!("Failed to open directory '{}': {}", s, e))
+    } else {
+        Err(format!("Path '{}' does not point to a directory.", s))
+    }
+}
+
+pub fn client_validator(s: String) -> Result<(), String> {
+    match Client::from_name(&s) {
+        Some(_) => Ok(()),
+        None => Err(format!(
+            "Unknown client '{}'. Expected one of: {}.",
+            s,
+            CLIENTS
+                .iter()
+                .map(Client::name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+pub fn primenet_work_type_validator(s: String) -> Result<(), String> {
+    match PrimenetWorkType::from_name(&s) {
+        Some(_) => Ok(()),
+        None => Err(format!(
+            "Unknown work type '{}'. Expected one of: {}.",
+            s,
+            PRIMENET_WORK_TYPES
+                .iter()
+                .map(PrimenetWorkType::name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+pub fn output_validator(s: String) -> Result<(), String> {
+    match OutputFormat::from_name(&s) {
+        Some(_) => Ok(()),
+        None => Err(format!(
+            "Unknown output format '{}'. Expected text or json.",
+            s
+        )),
+    }
+}
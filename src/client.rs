@@ -0,0 +1,197 @@
+use crate::assignment::AssignmentKind;
+use std::fmt;
+
+// The programs that can work out of a work directory. Each one has its own file names, its own
+// idea of what work it can do, and its own way of writing results and checkpoints.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Client {
+    Mfaktc,
+    Mfakto,
+    Mlucas,
+    Gpuowl,
+    CudaLucas,
+    Mprime,
+}
+
+pub const CLIENTS: [Client; 6] = [
+    Client::Mfaktc,
+    Client::Mfakto,
+    Client::Mlucas,
+    Client::Gpuowl,
+    Client::CudaLucas,
+    Client::Mprime,
+];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ResultsFormat {
+    // One line of text per result, e.g. "M89459323 has a factor: ...".
+    Text,
+    // One JSON object per line.
+    Json,
+}
+
+impl ResultsFormat {
+    // Whether a line of the results file could be a result in this format. Anything else is the
+    // client talking to itself, and stays where it is.
+    pub fn accepts(&self, line: &str) -> bool {
+        match self {
+            ResultsFormat::Text => true,
+            ResultsFormat::Json => line.trim_start().starts_with('{'),
+        }
+    }
+}
+
+// How a client names the files it saves its progress in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CheckpointFormat {
+    // M<exponent>.ckp
+    Mfaktc,
+    // p<exponent> and q<exponent>
+    Mlucas,
+    // <exponent>/<exponent>.prp, or <exponent>/<exponent>.owl for older versions
+    Gpuowl,
+    // c<exponent> and t<exponent>
+    CudaLucas,
+    // p<exponent> for LL and PRP, m<exponent> for P-1 and e<exponent> for ECM, each with a .bu
+    // backup
+    Mprime,
+}
+
+impl CheckpointFormat {
+    // Whether we can tell from the client's files how far it is through an assignment. mfaktc and
+    // mfakto checkpoint a class count rather than iterations, and CUDALucas and mprime checkpoints
+    // are binary formats we don't read.
+    pub fn has_readable_progress(&self) -> bool {
+        matches!(self, CheckpointFormat::Gpuowl | CheckpointFormat::Mlucas)
+    }
+
+    // Checkpoint files for an exponent, relative to the work directory.
+    pub fn file_names(&self, exponent: u64) -> Vec<String> {
+        match self {
+            CheckpointFormat::Mfaktc => vec![format!("M{}.ckp", exponent)],
+            CheckpointFormat::Mlucas => vec![format!("p{}", exponent), format!("q{}", exponent)],
+            CheckpointFormat::Gpuowl => {
+                vec![
+                    format!("{0}/{0}.prp", exponent),
+                    format!("{0}/{0}.owl", exponent),
+                ]
+            }
+            CheckpointFormat::CudaLucas => {
+                vec![format!("c{}", exponent), format!("t{}", exponent)]
+            }
+            CheckpointFormat::Mprime => ["p", "m", "e"]
+                .iter()
+                .flat_map(|prefix| {
+                    vec![
+                        format!("{}{}", prefix, exponent),
+                        format!("{}{}.bu", prefix, exponent),
+                    ]
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Client {
+    // The name used for --client.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Client::Mfaktc => "mfaktc",
+            Client::Mfakto => "mfakto",
+            Client::Mlucas => "mlucas",
+            Client::Gpuowl => "gpuowl",
+            Client::CudaLucas => "cudalucas",
+            Client::Mprime => "mprime",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        CLIENTS
+            .iter()
+            .find(|client| client.name().eq_ignore_ascii_case(name))
+            .copied()
+    }
+
+    pub fn worktodo_file(&self) -> &'static str {
+        match self {
+            Client::Mlucas => "worktodo.ini",
+            _ => "worktodo.txt",
+        }
+    }
+
+    pub fn results_file(&self) -> &'static str {
+        "results.txt"
+    }
+
+    pub fn assignment_kinds(&self) -> &'static [AssignmentKind] {
+        match self {
+            Client::Mfaktc | Client::Mfakto => &[AssignmentKind::Factor],
+            Client::Mlucas => &[
+                AssignmentKind::Test,
+                AssignmentKind::DoubleCheck,
+                AssignmentKind::Prp,
+                AssignmentKind::PrpDoubleCheck,
+                AssignmentKind::Pfactor,
+                AssignmentKind::Pminus1,
+                AssignmentKind::Cert,
+            ],
+            Client::Gpuowl => &[
+                AssignmentKind::Prp,
+                AssignmentKind::PrpDoubleCheck,
+                AssignmentKind::Pfactor,
+                AssignmentKind::Pminus1,
+                AssignmentKind::Cert,
+            ],
+            Client::CudaLucas => &[AssignmentKind::Test, AssignmentKind::DoubleCheck],
+            Client::Mprime => &[
+                AssignmentKind::Test,
+                AssignmentKind::DoubleCheck,
+                AssignmentKind::Prp,
+                AssignmentKind::PrpDoubleCheck,
+                AssignmentKind::Factor,
+                AssignmentKind::Pfactor,
+                AssignmentKind::Pminus1,
+                AssignmentKind::Ecm,
+                AssignmentKind::Cert,
+            ],
+        }
+    }
+
+    pub fn can_run(&self, kind: AssignmentKind) -> bool {
+        self.assignment_kinds().contains(&kind)
+    }
+
+    // The largest exponent the client can work on, where it has a hard limit.
+    pub fn max_exponent(&self) -> Option<u64> {
+        match self {
+            // Exponents are 32 bit integers.
+            Client::Mfaktc | Client::Mfakto => Some(u32::MAX as u64),
+            // ...and signed ones here.
+            Client::CudaLucas => Some(i32::MAX as u64),
+            _ => None,
+        }
+    }
+
+    pub fn results_format(&self) -> ResultsFormat {
+        match self {
+            Client::Gpuowl => ResultsFormat::Json,
+            _ => ResultsFormat::Text,
+        }
+    }
+
+    pub fn checkpoint_format(&self) -> CheckpointFormat {
+        match self {
+            Client::Mfaktc | Client::Mfakto => CheckpointFormat::Mfaktc,
+            Client::Mlucas => CheckpointFormat::Mlucas,
+            Client::Gpuowl => CheckpointFormat::Gpuowl,
+            Client::CudaLucas => CheckpointFormat::CudaLucas,
+            Client::Mprime => CheckpointFormat::Mprime,
+        }
+    }
+}
+
+impl fmt::Display for Client {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
@@ -0,0 +1,221 @@
+use crate::error::Error;
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::fs::{read_to_string, remove_file, rename, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    pub domain: String,
+    // Cookies set without a Domain attribute only go back to the exact host that set them.
+    pub host_only: bool,
+    pub path: String,
+    pub secure: bool,
+    // None for session cookies, which are kept until the server replaces them.
+    pub expires: Option<DateTime<Utc>>,
+}
+
+impl Cookie {
+    fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        let host = url.host_str().unwrap_or("").to_ascii_lowercase();
+        let domain_matches = if self.host_only {
+            host == self.domain
+        } else {
+            domain_match(&host, &self.domain)
+        };
+        domain_matches
+            && path_match(url.path(), &self.path)
+            && (!self.secure || url.scheme() == "https")
+    }
+}
+
+fn domain_match(host: &str, domain: &str) -> bool {
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+fn path_match(request_path: &str, cookie_path: &str) -> bool {
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/')))
+}
+
+// Everything up to the last slash of the path, the way browsers pick a path for cookies that don't
+// set one.
+fn default_path(url: &Url) -> String {
+    let path = url.path();
+    match path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(i) => path[..i].to_string(),
+    }
+}
+
+// Servers aren't consistent about the date format in Expires, so try the usual ones.
+fn parse_expires(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(value)
+        .map(|time| time.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(value, "%a, %d-%b-%Y %H:%M:%S GMT")
+                .map(|time| time.and_utc())
+                .ok()
+        })
+}
+
+// Parse a Set-Cookie header sent with the response from `url`. Returns None for anything
+// malformed, or for a Domain the server isn't allowed to set.
+fn parse_set_cookie(url: &Url, header: &str, now: DateTime<Utc>) -> Option<Cookie> {
+    let mut parts = header.split(';');
+    let (name, value) = parts.next()?.split_once('=')?;
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+    let host = url.host_str()?.to_ascii_lowercase();
+    let mut cookie = Cookie {
+        name: name.to_string(),
+        value: value.trim().to_string(),
+        domain: host.clone(),
+        host_only: true,
+        path: default_path(url),
+        secure: false,
+        expires: None,
+    };
+    let mut max_age = None;
+    for attribute in parts {
+        let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+        let value = value.trim();
+        match key.trim().to_ascii_lowercase().as_str() {
+            "domain" if !value.is_empty() => {
+                let domain = value.trim_start_matches('.').to_ascii_lowercase();
+                if !domain_match(&host, &domain) {
+                    return None;
+                }
+                cookie.domain = domain;
+                cookie.host_only = false;
+            }
+            "path" if value.starts_with('/') => cookie.path = value.to_string(),
+            "secure" => cookie.secure = true,
+            "max-age" => max_age = value.parse::<i64>().ok(),
+            "expires" => cookie.expires = cookie.expires.or_else(|| parse_expires(value)),
+            _ => {}
+        }
+    }
+    // Max-Age wins over Expires when a server sends both.
+    if let Some(seconds) = max_age {
+        cookie.expires = Some(now + Duration::seconds(seconds));
+    }
+    Some(cookie)
+}
+
+// The cookies a transport has been given, which is what keeps it logged in. It can be saved to a
+// file so the session outlives the process.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CookieJar {
+    cookies: Vec<Cookie>,
+}
+
+impl CookieJar {
+    // A missing file is just an empty jar. Cookies that expired since the last save are dropped.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let mut jar: CookieJar = match read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json).map_err(|e| {
+                Error::Parse(format!(
+                    "Failed to parse cookie jar {}. Error: {}",
+                    path.display(),
+                    e
+                ))
+            })?,
+            Err(e) if e.kind() == ErrorKind::NotFound => CookieJar::default(),
+            Err(e) => return Err(Error::io(format!("Failed to read {}.", path.display()), e)),
+        };
+        let now = Utc::now();
+        jar.cookies.retain(|cookie| !cookie.is_expired(now));
+        Ok(jar)
+    }
+
+    // The jar is as good as a password while the session lasts, so only the owner gets to read it.
+    // It's written to a temporary file and renamed into place so a crash can't leave half a jar.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let json = serde_json::to_string_pretty(self).expect("Failed to serialize cookie jar");
+        let mut temp_path = path.as_os_str().to_owned();
+        temp_path.push(".tmp");
+        let temp_path = PathBuf::from(temp_path);
+        // A leftover temporary file could have looser permissions, which opening it wouldn't fix.
+        let _ = remove_file(&temp_path);
+        create_private(&temp_path)
+            .and_then(|mut file| file.write_all(json.as_bytes()))
+            .and_then(|_| rename(&temp_path, path))
+            .map_err(|e| Error::io(format!("Failed to write {}.", path.display()), e))
+    }
+
+    pub fn len(&self) -> usize {
+        self.cookies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cookies.is_empty()
+    }
+
+    pub fn cookies(&self) -> &[Cookie] {
+        &self.cookies
+    }
+
+    // Take in a Set-Cookie header from the response to `url`, replacing any cookie with the same
+    // name, domain and path. Returns whether the jar changed.
+    pub fn store(&mut self, url: &Url, header: &str, now: DateTime<Utc>) -> bool {
+        let cookie = match parse_set_cookie(url, header, now) {
+            Some(cookie) => cookie,
+            None => return false,
+        };
+        let before = self.cookies.clone();
+        self.cookies.retain(|existing| {
+            !(existing.name == cookie.name
+                && existing.domain == cookie.domain
+                && existing.path == cookie.path)
+        });
+        // An expiry in the past is how servers delete cookies.
+        if !cookie.is_expired(now) {
+            self.cookies.push(cookie);
+        }
+        self.cookies != before
+    }
+
+    // The Cookie header to send with a request to `url`, if any cookies apply.
+    pub fn header(&self, url: &Url, now: DateTime<Utc>) -> Option<String> {
+        let cookies = self
+            .cookies
+            .iter()
+            .filter(|cookie| !cookie.is_expired(now) && cookie.matches(url))
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect::<Vec<_>>();
+        if cookies.is_empty() {
+            None
+        } else {
+            Some(cookies.join("; "))
+        }
+    }
+}
+
+#[cfg(unix)]
+fn create_private(path: &Path) -> std::io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+}
+
+#[cfg(not(unix))]
+fn create_private(path: &Path) -> std::io::Result<File> {
+    OpenOptions::new().write(true).create_new(true).open(path)
+}
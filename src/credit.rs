@@ -0,0 +1,188 @@
+use crate::{
+    assignment::{Assignment, AssignmentKind},
+    work_result::{ResultKind, WorkResult},
+};
+use regex::Regex;
+
+// Estimates of how much work an assignment is, in GHz-days: days of work for one core of a 1 GHz
+// Core 2 Duo, the unit Primenet gives credit in. They're shaped like Primenet's credit formulas
+// (see reference/cpu_credit.txt), but anything that squares mod M<exponent> uses an approximate
+// time per squaring, so they're only good for sizing caches and guessing how long work will take.
+// Primenet's own figure is whatever it reports when a result is submitted.
+
+// Seconds per trial factoring class per bit level, from Primenet's hardwired table.
+fn tf_timing(bits: u64) -> f64 {
+    2.4 * match bits {
+        0..=61 => 0.00465,
+        62 | 63 => 0.00743,
+        64 => 0.00711,
+        _ => 0.00707,
+    }
+}
+
+// Trial factoring M<exponent> from 2^from_bits to 2^to_bits.
+pub fn tf_credit(exponent: u64, from_bits: u64, to_bits: u64) -> f64 {
+    if exponent == 0 {
+        return 0.0;
+    }
+    (from_bits + 1..=to_bits)
+        .filter(|bits| *bits >= 48)
+        .map(|bits| tf_timing(bits) * 2f64.powi((bits - 48) as i32) * 1680.0 / exponent as f64)
+        .sum()
+}
+
+// An approximation, not Primenet's formula. Primenet looks the time per squaring up in a table of
+// FFT lengths that isn't published (credit_get_FFT_timing reads it from its database). FFT lengths
+// (and so the time per squaring) grow a little faster than the exponent, so p * log2(p) fitted to
+// what Primenet credits for exponents from 60M to 110M stands in for it, and can be well off
+// outside that range.
+const SQUARING_TIMING_SCALE: f64 = 1.4e-15 * 86400.0;
+
+// Seconds for one squaring mod M<exponent> on the benchmark machine.
+fn squaring_timing(exponent: u64) -> f64 {
+    let p = exponent as f64;
+    SQUARING_TIMING_SCALE * p * p.max(2.0).log2()
+}
+
+// One iteration (squaring) of a test of M<exponent>.
+pub fn iteration_credit(exponent: u64) -> f64 {
+    squaring_timing(exponent) / 86400.0
+}
+
+// A Lucas-Lehmer or PRP test of M<exponent>, which takes about as many squarings as the exponent.
+pub fn ll_credit(exponent: u64) -> f64 {
+    iteration_credit(exponent) * exponent as f64
+}
+
+pub fn pm1_credit(exponent: u64, b1: u64, b2: u64) -> f64 {
+    squaring_timing(exponent) * (1.5 * b1 as f64 + 0.05 * b2 as f64) / 86400.0
+}
+
+pub fn ecm_credit(exponent: u64, curves: u64, b1: u64, b2: u64) -> f64 {
+    squaring_timing(exponent) * curves as f64 * (13.0 * b1 as f64 + 0.06 * b2 as f64) / 86400.0
+}
+
+// Pfactor lines leave the bounds to the client. These are about what Prime95 and gpuowl pick for
+// wavefront exponents.
+const PFACTOR_B1: u64 = 1_000_000;
+const PFACTOR_B2: u64 = 30 * PFACTOR_B1;
+// ECM2 lines with a B2 of 0 leave it to Prime95, which uses 100 * B1.
+const ECM_DEFAULT_B2_MULTIPLE: u64 = 100;
+
+// Estimated GHz-days for an assignment, going by its worktodo line.
+pub fn assignment_credit(assignment: &Assignment) -> f64 {
+    let field = |index: usize| {
+        assignment
+            .fields
+            .get(index)
+            .and_then(|field| field.parse::<u64>().ok())
+    };
+    let exponent = assignment.exponent;
+    match assignment.kind {
+        AssignmentKind::Test
+        | AssignmentKind::DoubleCheck
+        | AssignmentKind::Prp
+        | AssignmentKind::PrpDoubleCheck => ll_credit(exponent),
+        // Factor=<exponent>,<from bits>,<to bits>
+        AssignmentKind::Factor => match (field(1), field(2)) {
+            (Some(from_bits), Some(to_bits)) => tf_credit(exponent, from_bits, to_bits),
+            _ => 0.0,
+        },
+        AssignmentKind::Pfactor => pm1_credit(exponent, PFACTOR_B1, PFACTOR_B2),
+        // Pminus1=<k>,<b>,<n>,<c>,<B1>,<B2>
+        AssignmentKind::Pminus1 => {
+            let b1 = field(4).unwrap_or(PFACTOR_B1);
+            pm1_credit(exponent, b1, field(5).unwrap_or(0))
+        }
+        // ECM2=<k>,<b>,<n>,<c>,<B1>,<B2>,<curves>
+        AssignmentKind::Ecm => {
+            let b1 = field(4).unwrap_or(0);
+            let b2 = match field(5) {
+                Some(0) | None => b1 * ECM_DEFAULT_B2_MULTIPLE,
+                Some(b2) => b2,
+            };
+            ecm_credit(exponent, field(6).unwrap_or(1), b1, b2)
+        }
+        // Cert=<k>,<b>,<n>,<c>,<squarings>
+        AssignmentKind::Cert => iteration_credit(exponent) * field(4).unwrap_or(0) as f64,
+    }
+}
+
+// Estimated GHz-days for a finished piece of work, or None if the result doesn't say enough to
+// tell, e.g. ECM results or a factor found without the bit range it was found in.
+pub fn result_credit(result: &WorkResult) -> Option<f64> {
+    let number = |pattern: &str| {
+        Regex::new(pattern)
+            .expect("Failed to build regex for result credit")
+            .captures(&result.line)
+            .and_then(|captures| captures[1].parse::<u64>().ok())
+    };
+    // "from 2^74 to 2^75" in text results, "[TF:74:75:mfaktc ...]" after a factor and
+    // "bitlo":74, "bithi":75 in JSON.
+    let tf_range = || {
+        let text = Regex::new(r"from 2\^([0-9]+) to 2\^([0-9]+)|\[TF:([0-9]+):([0-9]+)")
+            .expect("Failed to build regex for result credit")
+            .captures(&result.line)
+            .map(|captures| {
+                let bits = |index| captures.get(index).and_then(|m| m.as_str().parse().ok());
+                (bits(1).or_else(|| bits(3)), bits(2).or_else(|| bits(4)))
+            });
+        match text {
+            Some((Some(from), Some(to))) => Some((from, to)),
+            _ => Some((
+                number(r#""bitlo"\s*:\s*([0-9]+)"#)?,
+                number(r#""bithi"\s*:\s*([0-9]+)"#)?,
+            )),
+        }
+    };
+    // "B1=1000000, B2=30000000" in text results and "B1":1000000, "B2":30000000 in JSON.
+    let pm1 = || {
+        let b1 = number(r#"B1"?\s*[=:]\s*([0-9]+)"#)?;
+        Some(pm1_credit(
+            result.exponent,
+            b1,
+            number(r#"B2"?\s*[=:]\s*([0-9]+)"#).unwrap_or(0),
+        ))
+    };
+    let is_pm1 = result.line.contains("P-1") || result.line.contains("PM1");
+    match result.kind {
+        ResultKind::Composite | ResultKind::Prime => Some(ll_credit(result.exponent)),
+        ResultKind::NoFactor | ResultKind::Factor(_) if !is_pm1 => {
+            tf_range().map(|(from, to)| tf_credit(result.exponent, from, to))
+        }
+        ResultKind::NoFactor | ResultKind::Factor(_) | ResultKind::Unknown if is_pm1 => pm1(),
+        _ => None,
+    }
+}
+
+// How much work to keep cached, as a number of days at a given speed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DaysOfWork {
+    pub days: f64,
+    // GHz-days of work the rig gets through per day.
+    pub throughput: f64,
+}
+
+impl DaysOfWork {
+    pub fn days_cached(&self, cached: &[Assignment]) -> f64 {
+        cached.iter().map(assignment_credit).sum::<f64>() / self.throughput
+    }
+
+    // How many assignments it should take to cover the days, counting the cached ones, or None if
+    // they already do. New assignments are taken to be the size of the average cached one, and
+    // with nothing cached to go by they're asked for one at a time.
+    pub fn assignments_to_cache(&self, cached: &[Assignment]) -> Option<usize> {
+        let wanted = self.days * self.throughput;
+        let have = cached.iter().map(assignment_credit).sum::<f64>();
+        if have >= wanted {
+            return None;
+        }
+        let more = if cached.is_empty() || have <= 0.0 {
+            1
+        } else {
+            let average = have / cached.len() as f64;
+            ((wanted - have) / average).ceil().max(1.0) as usize
+        };
+        Some(cached.len() + more)
+    }
+}
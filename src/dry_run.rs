@@ -0,0 +1,208 @@
+use crate::{
+    assignment::Assignment,
+    error::Error,
+    options::GeneralOptions,
+    primenet_runtime::sort_results,
+    submission_queue::SubmissionQueue,
+    throughput::{days_of_work_with, Throughput},
+    transport::Request,
+    util::read_nolock,
+    work_directory::{parse_assignments, WorkDirectory},
+    work_result::WorkResult,
+};
+use chrono::{DateTime, Utc};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+// What a run would request and submit, worked out from the work directory as it is without
+// locking, changing or sending anything.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DryRun {
+    pub work_directory: PathBuf,
+    pub cached: Vec<Assignment>,
+    // Assignments in the worktodo that would be quarantined, with why.
+    pub invalid_assignments: Vec<(Assignment, String)>,
+    // How many assignments would be asked for and the request that would ask for them, or None
+    // when enough work is cached already.
+    pub work_request: Option<(usize, Request)>,
+    // The request that would be made instead if the server had none of the chosen type left, for
+    // the same number of assignments.
+    pub fallback_request: Option<Request>,
+    // Where results would be POSTed, or None when they wouldn't be submitted at all.
+    pub report_url: Option<String>,
+    // The results that would be submitted, in order.
+    pub submissions: Vec<String>,
+    // Results that would be quarantined instead, with why.
+    pub invalid_results: Vec<(WorkResult, String)>,
+    // Results in the queue that aren't due to be tried again yet.
+    pub waiting: usize,
+}
+
+// A file's lines, or none if it doesn't exist. Somebody else's lock is an error rather than
+// something to wait for, since a dry run would rather see the files at rest.
+fn read_lines(path: &Path, lock_path: &Path) -> Result<Vec<String>, Error> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(read_nolock(path, lock_path)?
+        .lines()
+        .map(|line| line.trim().to_string())
+        .collect())
+}
+
+impl DryRun {
+    // `work_request` builds the request for a number of assignments. Results are only looked at
+    // if `submit` is set.
+    pub fn plan<F: FnOnce(usize) -> Request>(
+        general_options: &GeneralOptions,
+        work_request: F,
+        submit: bool,
+        now: DateTime<Utc>,
+    ) -> Result<Self, Error> {
+        let work_directory =
+            WorkDirectory::from_options(&general_options.work_directory, general_options.client);
+        let worktodo = read_lines(&work_directory.worktodo, &work_directory.worktodo_lock)?;
+        let (kept, invalid_assignments) = work_directory.sort_worktodo(worktodo.clone());
+        let cached = parse_assignments(&kept);
+        // The throughput that's already been measured, since measuring it again saves it.
+        let measured = Throughput::load(&work_directory.throughput)?.ghz_days_per_day();
+        let wanted = match days_of_work_with(
+            general_options.days_of_work,
+            general_options.throughput,
+            measured,
+        ) {
+            Some(days_of_work) => days_of_work
+                .assignments_to_cache(&cached)
+                .unwrap_or(cached.len()),
+            None => general_options.num_cache,
+        };
+        let work_request = wanted
+            .checked_sub(cached.len())
+            .filter(|&num_to_get| num_to_get > 0)
+            .map(|num_to_get| (num_to_get, work_request(num_to_get)));
+        let mut plan = DryRun {
+            work_directory: work_directory.path.clone(),
+            cached,
+            invalid_assignments,
+            work_request,
+            fallback_request: None,
+            report_url: None,
+            submissions: Vec::new(),
+            invalid_results: Vec::new(),
+            waiting: 0,
+        };
+        if submit {
+            let results = read_lines(&work_directory.results, &work_directory.results_lock)?;
+            let results_sent = read_lines(
+                &work_directory.results_sent,
+                &work_directory.results_sent_lock,
+            )?;
+            if work_directory.queue_lock.exists() {
+                return Err(Error::LockContention(work_directory.queue_lock));
+            }
+            let mut queue = SubmissionQueue::load(&work_directory.queue)?;
+            let sorted = sort_results(
+                &work_directory,
+                &worktodo,
+                results,
+                &results_sent,
+                &mut queue,
+                now,
+            );
+            plan.submissions = queue.due(now);
+            plan.waiting = queue.len() - plan.submissions.len();
+            plan.invalid_results = sorted.quarantined;
+            plan.report_url = Some(general_options.endpoints.primenet_report());
+        }
+        Ok(plan)
+    }
+
+    // Add the fallback request, if any work would be requested. `fallback_request` builds it for a
+    // number of assignments, like plan's `work_request`.
+    pub fn with_fallback<F: FnOnce(usize) -> Request>(mut self, fallback_request: F) -> Self {
+        self.fallback_request = self
+            .work_request
+            .as_ref()
+            .map(|(num_to_get, _)| fallback_request(*num_to_get));
+        self
+    }
+}
+
+impl fmt::Display for DryRun {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Dry run in {}. Nothing was requested or submitted, and no files were changed.",
+            self.work_directory.display()
+        )?;
+        write!(f, "\n\n{} assignment(s) cached.", self.cached.len())?;
+        if !self.invalid_assignments.is_empty() {
+            write!(
+                f,
+                "\nWould quarantine {} assignment(s) from the worktodo:",
+                self.invalid_assignments.len()
+            )?;
+            for (assignment, reason) in &self.invalid_assignments {
+                write!(f, "\n    {}  ({})", assignment, reason)?;
+            }
+        }
+        match &self.work_request {
+            Some((num_to_get, request)) => write!(
+                f,
+                "\nWould request {} more assignment(s):\n    {} {}",
+                num_to_get,
+                request.method,
+                request.full_url()
+            )?,
+            None => write!(f, "\nEnough work is cached. Would not request any.")?,
+        }
+        if let Some(request) = &self.fallback_request {
+            write!(
+                f,
+                "\nIf none of that type is left, would request instead:\n    {} {}",
+                request.method,
+                request.full_url()
+            )?;
+        }
+        let report_url = match &self.report_url {
+            Some(report_url) => report_url,
+            None => {
+                return write!(
+                    f,
+                    "\n\nWould not submit results without Primenet credentials."
+                )
+            }
+        };
+        if self.submissions.is_empty() {
+            write!(f, "\n\nNo results to submit.")?;
+        } else {
+            write!(
+                f,
+                "\n\nWould submit {} result(s) to {}:",
+                self.submissions.len(),
+                report_url
+            )?;
+            for result in &self.submissions {
+                write!(f, "\n    {}", result)?;
+            }
+        }
+        if !self.invalid_results.is_empty() {
+            write!(
+                f,
+                "\nWould quarantine {} result(s):",
+                self.invalid_results.len()
+            )?;
+            for (result, problem) in &self.invalid_results {
+                write!(f, "\n    {}  ({})", result.line, problem)?;
+            }
+        }
+        if self.waiting > 0 {
+            write!(
+                f,
+                "\n{} more result(s) queued, waiting to be retried.",
+                self.waiting
+            )?;
+        }
+        Ok(())
+    }
+}
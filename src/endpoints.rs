@@ -0,0 +1,88 @@
+use crate::gpu72_work::Gpu72WorkType;
+
+pub const DEFAULT_PRIMENET_URL: &str = "https://www.mersenne.org/";
+pub const DEFAULT_GPU72_URL: &str = "https://www.gpu72.com/";
+
+const PRIMENET_REQUEST_PATH: &str = "manual_assignment/";
+const PRIMENET_REPORT_PATH: &str = "manual_result/";
+const PRIMENET_PROOF_UPLOAD_PATH: &str = "proof_upload/";
+const GPU72_ASSIGNMENTS_PATH: &str = "account/getassignments/";
+
+// Base URLs for each server. These default to the live sites, but can point at a mirror, a caching
+// proxy or a local stand-in as long as it serves the same paths.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Endpoints {
+    primenet: String,
+    gpu72: String,
+    // Proof uploads go to Primenet unless they're pointed somewhere else.
+    proof_upload: Option<String>,
+}
+
+// Paths are joined onto the base URL, so it always needs to end with a slash.
+fn with_trailing_slash(base_url: &str) -> String {
+    if base_url.ends_with('/') {
+        base_url.to_string()
+    } else {
+        format!("{}/", base_url)
+    }
+}
+
+impl Endpoints {
+    pub fn new(primenet: &str, gpu72: &str) -> Self {
+        Endpoints {
+            primenet: with_trailing_slash(primenet),
+            gpu72: with_trailing_slash(gpu72),
+            proof_upload: None,
+        }
+    }
+
+    pub fn with_proof_upload(mut self, proof_upload: &str) -> Self {
+        self.proof_upload = Some(proof_upload.to_string());
+        self
+    }
+
+    pub fn primenet_base(&self) -> &str {
+        &self.primenet
+    }
+
+    pub fn gpu72_base(&self) -> &str {
+        &self.gpu72
+    }
+
+    pub fn primenet_login(&self) -> String {
+        self.primenet.clone()
+    }
+
+    pub fn primenet_request(&self) -> String {
+        format!("{}{}", self.primenet, PRIMENET_REQUEST_PATH)
+    }
+
+    pub fn primenet_report(&self) -> String {
+        format!("{}{}", self.primenet, PRIMENET_REPORT_PATH)
+    }
+
+    pub fn proof_upload(&self) -> String {
+        self.proof_upload
+            .clone()
+            .unwrap_or_else(|| format!("{}{}", self.primenet, PRIMENET_PROOF_UPLOAD_PATH))
+    }
+
+    pub fn gpu72_login_check(&self) -> String {
+        format!("{}{}", self.gpu72, GPU72_ASSIGNMENTS_PATH)
+    }
+
+    pub fn gpu72_request(&self, work_type: Gpu72WorkType) -> String {
+        format!(
+            "{}{}{}",
+            self.gpu72,
+            GPU72_ASSIGNMENTS_PATH,
+            work_type.as_str().0
+        )
+    }
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Endpoints::new(DEFAULT_PRIMENET_URL, DEFAULT_GPU72_URL)
+    }
+}
@@ -0,0 +1,111 @@
+use crate::transport::TransportError;
+use std::error::Error as StdError;
+use std::fmt;
+use std::io::Error as IoError;
+use std::path::PathBuf;
+
+// Exit codes from sysexits.h, so that shell scripts and service managers can tell failures apart.
+pub const EX_USAGE: i32 = 64;
+pub const EX_DATAERR: i32 = 65;
+pub const EX_UNAVAILABLE: i32 = 69;
+pub const EX_IOERR: i32 = 74;
+pub const EX_TEMPFAIL: i32 = 75;
+pub const EX_PROTOCOL: i32 = 76;
+pub const EX_NOPERM: i32 = 77;
+
+#[derive(Debug)]
+pub enum Error {
+    // Bad command line arguments or configuration.
+    Usage(String),
+    // The request never got a response (DNS, connect, timeout, TLS, reading the body...).
+    Network {
+        context: String,
+        source: TransportError,
+    },
+    // The server answered, but not with a success status.
+    HttpStatus {
+        url: String,
+        status: u16,
+        body: String,
+    },
+    // The server answered, but isn't taking requests right now (e.g. down for maintenance).
+    Unavailable(String),
+    // The server understood what we sent and turned it down.
+    Rejected(String),
+    // The server rejected our credentials or the session is no longer logged in.
+    Authentication(String),
+    // Another process is holding a lockfile we need.
+    LockContention(PathBuf),
+    // A response or a file didn't have the contents we expected.
+    Parse(String),
+    Io {
+        context: String,
+        source: IoError,
+    },
+}
+
+impl Error {
+    pub fn network<S: Into<String>, E: Into<TransportError>>(context: S, source: E) -> Self {
+        Error::Network {
+            context: context.into(),
+            source: source.into(),
+        }
+    }
+
+    pub fn io<S: Into<String>>(context: S, source: IoError) -> Self {
+        Error::Io {
+            context: context.into(),
+            source,
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Usage(_) => EX_USAGE,
+            Error::Network { .. } => EX_UNAVAILABLE,
+            Error::HttpStatus { .. } => EX_PROTOCOL,
+            Error::Unavailable(_) => EX_UNAVAILABLE,
+            Error::Rejected(_) => EX_DATAERR,
+            Error::Authentication(_) => EX_NOPERM,
+            Error::LockContention(_) => EX_TEMPFAIL,
+            Error::Parse(_) => EX_DATAERR,
+            Error::Io { .. } => EX_IOERR,
+        }
+    }
+
+    // Lockfiles found while failing with a lock contention error belong to somebody else, so
+    // cleaning up after one of those would pull the lock out from under the other process.
+    pub fn should_clean_up(&self) -> bool {
+        !matches!(self, Error::LockContention(_) | Error::Usage(_))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Usage(msg) => write!(f, "{}", msg),
+            Error::Network { context, source } => write!(f, "{} Error: {}", context, source),
+            Error::HttpStatus { url, status, body } => write!(
+                f,
+                "Request to '{}' returned bad status: {}\nResponse text: {}",
+                url, status, body
+            ),
+            Error::Unavailable(msg) => write!(f, "{}", msg),
+            Error::Rejected(msg) => write!(f, "{}", msg),
+            Error::Authentication(msg) => write!(f, "Authentication failed: {}", msg),
+            Error::LockContention(path) => write!(f, "Found lockfile: {}", path.display()),
+            Error::Parse(msg) => write!(f, "{}", msg),
+            Error::Io { context, source } => write!(f, "{} Error: {}", context, source),
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Error::Network { source, .. } => Some(source.as_ref()),
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
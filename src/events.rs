@@ -0,0 +1,420 @@
+use crate::{
+    activity::WorkSource,
+    assignment::Assignment,
+    dry_run::DryRun,
+    error::Error,
+    factor_report::FactorReport,
+    options::OutputFormat,
+    status::Status,
+    submission_queue::{Quarantine, SubmissionQueue},
+    work_directory::WorkDirectory,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+use std::sync::OnceLock;
+
+// With --output json everything worth knowing about a run goes to stdout as one JSON object per
+// line, and the logs stay on stderr. Every object has
+//     "version": EVENT_SCHEMA_VERSION,
+//     "time":    when it happened, in RFC 3339 UTC,
+//     "event":   one of "fetch", "submission", "error", "status", "queue", "factors" or
+//                "dry_run",
+// and the rest of its fields depend on the event, as laid out in the structs below. Fields are
+// only ever added within a version. Renaming or removing one, or changing what it means, bumps
+// the version.
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+static OUTPUT: OnceLock<OutputFormat> = OnceLock::new();
+
+// Set once at startup, like the logger. Until it's set, output is text.
+pub fn init_output(format: OutputFormat) {
+    let _ = OUTPUT.set(format);
+}
+
+pub fn json_output() -> bool {
+    OUTPUT.get() == Some(&OutputFormat::Json)
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AssignmentEvent {
+    // The worktodo key, e.g. "Factor" or "PRP".
+    pub kind: String,
+    pub exponent: u64,
+    pub aid: Option<String>,
+    pub line: String,
+}
+
+impl From<&Assignment> for AssignmentEvent {
+    fn from(assignment: &Assignment) -> Self {
+        AssignmentEvent {
+            kind: assignment.kind.as_str().to_string(),
+            exponent: assignment.exponent,
+            aid: assignment.aid.clone(),
+            line: assignment.line.clone(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubmissionOutcome {
+    Accepted,
+    // Primenet turned it down. It's been moved to results.quarantine.
+    Rejected,
+    // Primenet answered with something we didn't recognise. It stays queued.
+    Unknown,
+    // It never got an answer from Primenet. It stays queued.
+    Failed,
+    // It was never sent, since it doesn't check out. It's in results.quarantine.
+    Quarantined,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CachedEvent {
+    #[serde(flatten)]
+    pub assignment: AssignmentEvent,
+    // "primenet" or "gpu72", or null for assignments we didn't fetch.
+    pub source: Option<&'static str>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ProgressEvent {
+    pub exponent: u64,
+    pub iteration: u64,
+    pub iterations: u64,
+    pub percent: f64,
+    pub eta: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ThroughputEvent {
+    pub ghz_days_per_day: Option<f64>,
+    pub measurements: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct StatusEvent {
+    pub work_directory: String,
+    pub cached: Vec<CachedEvent>,
+    pub current: Option<ProgressEvent>,
+    pub results_waiting: usize,
+    pub last_fetch: Option<DateTime<Utc>>,
+    pub last_submit: Option<DateTime<Utc>>,
+    pub locks: Vec<String>,
+    pub throughput: ThroughputEvent,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct QueuedEvent {
+    pub result: String,
+    pub queued_at: DateTime<Utc>,
+    pub attempts: u32,
+    pub last_attempt: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub next_attempt: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct QueueEvent {
+    pub work_directory: String,
+    pub results: Vec<QueuedEvent>,
+    // Results in results.quarantine, with the note saying when and why.
+    pub quarantined: Vec<InvalidEvent>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FactorEvent {
+    pub exponent: u64,
+    // As a string, since it can be bigger than JSON numbers go.
+    pub factor: String,
+    pub bits: f64,
+    pub k: String,
+    pub class: u32,
+    pub pass: Option<usize>,
+    pub line: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct YieldEvent {
+    // Trial factoring from 2^bits to 2^(bits+1).
+    pub bits: u64,
+    pub tested: usize,
+    pub found: usize,
+    pub expected: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FactorsEvent {
+    pub work_directory: String,
+    pub factors: Vec<FactorEvent>,
+    pub yields: Vec<YieldEvent>,
+}
+
+// A line that would be quarantined, and why.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct InvalidEvent {
+    pub line: String,
+    pub reason: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct WorkRequestEvent {
+    pub count: usize,
+    pub method: String,
+    // With the query string, but never the credentials.
+    pub url: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DryRunEvent {
+    pub work_directory: String,
+    pub cached: Vec<AssignmentEvent>,
+    pub invalid_assignments: Vec<InvalidEvent>,
+    // Null when enough work is cached.
+    pub work_request: Option<WorkRequestEvent>,
+    // Null without a fallback work type, or when no work would be requested.
+    pub fallback_request: Option<WorkRequestEvent>,
+    // Null when results wouldn't be submitted at all.
+    pub report_url: Option<String>,
+    pub submissions: Vec<String>,
+    pub invalid_results: Vec<InvalidEvent>,
+    pub waiting: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    // Assignments newly cached in a work directory.
+    Fetch {
+        source: &'static str,
+        work_directory: String,
+        assignments: Vec<AssignmentEvent>,
+    },
+    // What became of one result.
+    Submission {
+        work_directory: String,
+        result: String,
+        outcome: SubmissionOutcome,
+        message: Option<String>,
+        ghz_days: Option<f64>,
+    },
+    // Fatal errors end the run with `exit_code`. The rest are retried on the next update.
+    Error {
+        message: String,
+        exit_code: i32,
+        fatal: bool,
+    },
+    Status(StatusEvent),
+    Queue(QueueEvent),
+    Factors(FactorsEvent),
+    // What --dry-run would have requested and submitted.
+    DryRun(DryRunEvent),
+}
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    version: u32,
+    time: DateTime<Utc>,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+fn path_string(path: &Path) -> String {
+    path.display().to_string()
+}
+
+impl Event {
+    pub fn fetch(
+        source: WorkSource,
+        work_directory: &WorkDirectory,
+        assignments: &[Assignment],
+    ) -> Self {
+        Event::Fetch {
+            source: source.name(),
+            work_directory: path_string(&work_directory.path),
+            assignments: assignments.iter().map(AssignmentEvent::from).collect(),
+        }
+    }
+
+    pub fn submission(
+        work_directory: &WorkDirectory,
+        result: &str,
+        outcome: SubmissionOutcome,
+        message: Option<String>,
+        ghz_days: Option<f64>,
+    ) -> Self {
+        Event::Submission {
+            work_directory: path_string(&work_directory.path),
+            result: result.to_string(),
+            outcome,
+            message,
+            ghz_days,
+        }
+    }
+
+    pub fn error(e: &Error, fatal: bool) -> Self {
+        Event::Error {
+            message: e.to_string(),
+            exit_code: e.exit_code(),
+            fatal,
+        }
+    }
+
+    pub fn status(status: &Status) -> Self {
+        Event::Status(StatusEvent {
+            work_directory: path_string(&status.work_directory),
+            cached: status
+                .cached
+                .iter()
+                .map(|cached| CachedEvent {
+                    assignment: AssignmentEvent::from(&cached.assignment),
+                    source: cached.source.map(|source| source.name()),
+                })
+                .collect(),
+            current: status.current.map(|current| ProgressEvent {
+                exponent: current.progress.exponent,
+                iteration: current.progress.iteration,
+                iterations: current.progress.iterations,
+                percent: current.progress.percent(),
+                eta: current.eta,
+            }),
+            results_waiting: status.results_waiting,
+            last_fetch: status.activity.last_fetch,
+            last_submit: status.activity.last_submit,
+            locks: status.locks.iter().map(|lock| path_string(lock)).collect(),
+            throughput: ThroughputEvent {
+                ghz_days_per_day: status.throughput.ghz_days_per_day(),
+                measurements: status.throughput.samples.len(),
+            },
+        })
+    }
+
+    pub fn queue(
+        work_directory: &WorkDirectory,
+        queue: &SubmissionQueue,
+        quarantine: &Quarantine,
+    ) -> Self {
+        Event::Queue(QueueEvent {
+            work_directory: path_string(&work_directory.path),
+            results: queue
+                .entries
+                .iter()
+                .map(|entry| QueuedEvent {
+                    result: entry.result.clone(),
+                    queued_at: entry.queued_at,
+                    attempts: entry.attempts,
+                    last_attempt: entry.last_attempt,
+                    last_error: entry.last_error.clone(),
+                    next_attempt: entry.next_attempt,
+                })
+                .collect(),
+            quarantined: quarantine
+                .entries
+                .iter()
+                .map(|(line, reason)| InvalidEvent {
+                    line: line.clone(),
+                    reason: reason.clone(),
+                })
+                .collect(),
+        })
+    }
+
+    pub fn factors(work_directory: &WorkDirectory, report: &FactorReport) -> Self {
+        Event::Factors(FactorsEvent {
+            work_directory: path_string(&work_directory.path),
+            factors: report
+                .factors
+                .iter()
+                .map(|found| FactorEvent {
+                    exponent: found.exponent,
+                    factor: found.info.factor.to_string(),
+                    bits: found.info.bits,
+                    k: found.info.k.to_string(),
+                    class: found.info.class,
+                    pass: found.info.pass,
+                    line: found.line.clone(),
+                })
+                .collect(),
+            yields: report
+                .yields
+                .iter()
+                .map(|(&bits, level)| YieldEvent {
+                    bits,
+                    tested: level.tested,
+                    found: level.found,
+                    expected: level.expected(bits),
+                })
+                .collect(),
+        })
+    }
+
+    pub fn dry_run(plan: &DryRun) -> Self {
+        Event::DryRun(DryRunEvent {
+            work_directory: path_string(&plan.work_directory),
+            cached: plan.cached.iter().map(AssignmentEvent::from).collect(),
+            invalid_assignments: plan
+                .invalid_assignments
+                .iter()
+                .map(|(assignment, reason)| InvalidEvent {
+                    line: assignment.line.clone(),
+                    reason: reason.clone(),
+                })
+                .collect(),
+            work_request: plan
+                .work_request
+                .as_ref()
+                .map(|(count, request)| WorkRequestEvent {
+                    count: *count,
+                    method: request.method.to_string(),
+                    url: request.full_url(),
+                }),
+            fallback_request: match (&plan.work_request, &plan.fallback_request) {
+                (Some((count, _)), Some(request)) => Some(WorkRequestEvent {
+                    count: *count,
+                    method: request.method.to_string(),
+                    url: request.full_url(),
+                }),
+                _ => None,
+            },
+            report_url: plan.report_url.clone(),
+            submissions: plan.submissions.clone(),
+            invalid_results: plan
+                .invalid_results
+                .iter()
+                .map(|(result, problem)| InvalidEvent {
+                    line: result.line.clone(),
+                    reason: problem.clone(),
+                })
+                .collect(),
+            waiting: plan.waiting,
+        })
+    }
+
+    // The line printed for the event.
+    pub fn to_json(&self, time: DateTime<Utc>) -> String {
+        serde_json::to_string(&Envelope {
+            version: EVENT_SCHEMA_VERSION,
+            time,
+            event: self,
+        })
+        .expect("Failed to serialize event")
+    }
+}
+
+// Print the event, if events are being printed at all.
+pub fn emit(event: Event) {
+    if json_output() {
+        println!("{}", event.to_json(Utc::now()));
+    }
+}
+
+// What the read-only subcommands print: the event with --output json, and the text otherwise.
+pub fn output(text: impl fmt::Display, event: impl FnOnce() -> Event) {
+    if json_output() {
+        emit(event());
+    } else {
+        println!("{}", text);
+    }
+}
@@ -0,0 +1,104 @@
+use num_bigint::BigUint;
+use std::fmt;
+
+// Why a reported factor of M<exponent> can't be right.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FactorProblem {
+    NotANumber,
+    // Every factor of M<p> is 2kp+1 for some k.
+    NotOneMod2p,
+    // ...and is 1 or 7 mod 8.
+    NotPlusMinusOneMod8,
+    // The factor has the right form but 2^p mod q isn't 1.
+    DoesNotDivide,
+}
+
+impl fmt::Display for FactorProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FactorProblem::NotANumber => write!(f, "the factor isn't a number greater than 1"),
+            FactorProblem::NotOneMod2p => write!(f, "the factor isn't of the form 2kp+1"),
+            FactorProblem::NotPlusMinusOneMod8 => write!(f, "the factor isn't 1 or 7 mod 8"),
+            FactorProblem::DoesNotDivide => write!(f, "the factor doesn't divide 2^p-1"),
+        }
+    }
+}
+
+// Check that `factor` (in decimal) really divides 2^exponent-1. Cheap next to finding it: one
+// modular exponentiation with about as many steps as the exponent has bits.
+pub fn check_factor(exponent: u64, factor: &str) -> Result<(), FactorProblem> {
+    let one = BigUint::from(1u32);
+    let q = BigUint::parse_bytes(factor.trim().as_bytes(), 10)
+        .filter(|q| *q > one)
+        .ok_or(FactorProblem::NotANumber)?;
+    let two_p = BigUint::from(exponent) * 2u32;
+    if (&q - &one) % &two_p != BigUint::from(0u32) {
+        return Err(FactorProblem::NotOneMod2p);
+    }
+    let mod_8 = &q % 8u32;
+    if mod_8 != one && mod_8 != BigUint::from(7u32) {
+        return Err(FactorProblem::NotPlusMinusOneMod8);
+    }
+    if BigUint::from(2u32).modpow(&BigUint::from(exponent), &q) != one {
+        return Err(FactorProblem::DoesNotDivide);
+    }
+    Ok(())
+}
+
+// Factors of Mersenne numbers fall in 16 of the classes mod 120, and Prime95 trial factors one
+// class per pass in this order.
+pub const TF_PASS_CLASSES: [u32; 16] = [
+    1, 7, 17, 23, 31, 41, 47, 49, 71, 73, 79, 89, 97, 103, 113, 119,
+];
+
+// Approximate log2 of a decimal factor, worked out the same way Primenet does for credit. Only
+// the leading six digits are looked at, so it can be a little off the exact value.
+pub fn credit_log2_factor(factor: &str) -> f64 {
+    let factor = factor.trim();
+    let digits = factor.len();
+    if digits > 6 {
+        let leading = factor[..6].parse::<f64>().unwrap_or(0.0);
+        leading.log2() + 3.322 * (digits - 6) as f64
+    } else {
+        factor.parse::<f64>().unwrap_or(0.0).log2()
+    }
+}
+
+// What can be worked out about a factor q = 2kp+1 of M<p> from the numbers alone.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FactorInfo {
+    pub factor: BigUint,
+    // Size as Primenet credits it, see credit_log2_factor.
+    pub bits: f64,
+    pub k: BigUint,
+    // q mod 120. Real factors are always in one of TF_PASS_CLASSES.
+    pub class: u32,
+    // Which of Prime95's 16 trial factoring passes finds the factor, counting from 0 like
+    // Primenet does.
+    pub pass: Option<usize>,
+}
+
+impl FactorInfo {
+    // The bit level the factor was found at, as in "from 2^74 to 2^75" for a 74.x bit factor.
+    pub fn bit_level(&self) -> u64 {
+        self.factor.bits().saturating_sub(1)
+    }
+}
+
+// None unless the factor is a number of the form 2kp+1. Whether it actually divides M<p> is
+// check_factor's job.
+pub fn factor_info(exponent: u64, factor: &str) -> Option<FactorInfo> {
+    let q = BigUint::parse_bytes(factor.trim().as_bytes(), 10)?;
+    let two_p = BigUint::from(exponent) * 2u32;
+    if q <= BigUint::from(1u32) || (&q - 1u32) % &two_p != BigUint::from(0u32) {
+        return None;
+    }
+    let class = (&q % 120u32).to_u32_digits().first().copied().unwrap_or(0);
+    Some(FactorInfo {
+        k: (&q - 1u32) / &two_p,
+        bits: credit_log2_factor(factor),
+        class,
+        pass: TF_PASS_CLASSES.iter().position(|c| *c == class),
+        factor: q,
+    })
+}
@@ -0,0 +1,110 @@
+use crate::{factor::FactorInfo, work_result::WorkResult};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FoundFactor {
+    pub exponent: u64,
+    pub info: FactorInfo,
+    pub line: String,
+}
+
+// Trial factoring done at one bit level, i.e. from 2^bits to 2^(bits+1).
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct BitLevelYield {
+    // Exponents taken through the bit level.
+    pub tested: usize,
+    pub found: usize,
+}
+
+impl BitLevelYield {
+    // About 1 in <bits> exponents have a factor between 2^bits and 2^(bits+1).
+    pub fn expected(&self, bits: u64) -> f64 {
+        self.tested as f64 / bits.max(1) as f64
+    }
+}
+
+// Factors found in a set of results, along with how much trial factoring it took to find them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FactorReport {
+    pub factors: Vec<FoundFactor>,
+    pub yields: BTreeMap<u64, BitLevelYield>,
+}
+
+impl FactorReport {
+    pub fn from_results(lines: &[String]) -> Self {
+        // mfaktc and mfakto write one of these per exponent and bit range, whether or not they
+        // found anything, e.g. "no factor for M332194529 from 2^74 to 2^75 [...]" or "found 1
+        // factor for M332194679 from 2^39 to 2^40 [...]".
+        let range_regex = Regex::new(r"factors? for M[0-9]+ from 2\^([0-9]+) to 2\^([0-9]+)")
+            .expect("Failed to build regex for trial factoring ranges");
+        let mut report = FactorReport::default();
+        for line in lines {
+            if let Some(captures) = range_regex.captures(line) {
+                let from = captures[1].parse::<u64>().unwrap_or(0);
+                let to = captures[2].parse::<u64>().unwrap_or(0);
+                for bits in from..to {
+                    report.yields.entry(bits).or_default().tested += 1;
+                }
+            }
+            let result = match WorkResult::parse(line) {
+                Ok(result) => result,
+                Err(_) => continue,
+            };
+            if let Some(info) = result.factor_info() {
+                report.yields.entry(info.bit_level()).or_default().found += 1;
+                report.factors.push(FoundFactor {
+                    exponent: result.exponent,
+                    info,
+                    line: result.line,
+                });
+            }
+        }
+        report
+    }
+}
+
+impl fmt::Display for FactorReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.factors.is_empty() {
+            write!(f, "No factors found.")?;
+        } else {
+            write!(f, "{} factor(s) found:", self.factors.len())?;
+        }
+        for found in &self.factors {
+            let info = &found.info;
+            write!(f, "\n\nM{} has a factor: {}", found.exponent, info.factor)?;
+            write!(f, "\n    Bits:  {:.2}", info.bits)?;
+            write!(f, "\n    k:     {}", info.k)?;
+            match info.pass {
+                Some(pass) => write!(
+                    f,
+                    "\n    Class: {} mod 120, pass {} of 16",
+                    info.class,
+                    pass + 1
+                )?,
+                None => write!(f, "\n    Class: {} mod 120", info.class)?,
+            }
+        }
+        if !self.yields.is_empty() {
+            write!(f, "\n\nTrial factoring yield by bit level:")?;
+            write!(
+                f,
+                "\n    {:<9} {:>7} {:>6} {:>9}",
+                "Bits", "Tested", "Found", "Expected"
+            )?;
+            for (bits, level) in &self.yields {
+                write!(
+                    f,
+                    "\n    {:<9} {:>7} {:>6} {:>9.2}",
+                    format!("{}-{}", bits, bits + 1),
+                    level.tested,
+                    level.found,
+                    level.expected(*bits)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
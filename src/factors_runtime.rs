@@ -0,0 +1,31 @@
+use crate::{
+    error::Error,
+    events::{output, Event},
+    factor_report::FactorReport,
+    options::FactorsOptions,
+    util::read_nolock,
+    work_directory::WorkDirectory,
+};
+use log::debug;
+
+// Print the factors in a work directory's results.sent, and how the trial factoring yield compares
+// with what's expected. Read without locking, the same as the queue subcommand.
+pub fn factors_runtime(factors_options: FactorsOptions) -> Result<(), Error> {
+    let work_directory = WorkDirectory::new(&factors_options.work_directory);
+    debug!(
+        "Using results.sent path: {}",
+        work_directory.results_sent.display()
+    );
+    let results_sent = if work_directory.results_sent.exists() {
+        read_nolock(
+            &work_directory.results_sent,
+            &work_directory.results_sent_lock,
+        )?
+    } else {
+        String::new()
+    };
+    let lines = results_sent.lines().map(str::to_string).collect::<Vec<_>>();
+    let report = FactorReport::from_results(&lines);
+    output(&report, || Event::factors(&work_directory, &report));
+    Ok(())
+}
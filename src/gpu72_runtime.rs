@@ -1,100 +1,201 @@
 use crate::{
-    clap_handler::{
-        app::{GeneralOptions, Gpu72Options, PrimenetOptions},
-        gpu72_work::Gpu72WorkType,
-        p95_work::PrimenetWorkType,
+    activity::{record_fetch, WorkSource},
+    assignment::{Assignment, ExponentRange},
+    dry_run::DryRun,
+    endpoints::Endpoints,
+    error::Error,
+    events::{emit, output, Event},
+    gpu72_work::Gpu72WorkType,
+    logging::{LOCKING, NETWORK, PARSING},
+    options::{GeneralOptions, Gpu72Options},
+    primenet_runtime::{
+        primenet_ensure_login, primenet_submit, top_up, update_throughput, validate_fetched,
+        with_primenet_session,
     },
-    primenet_runtime::{primenet_login, primenet_request},
+    retry::log_cycle_summary,
+    throughput::days_of_work_with,
+    transport::{Request, ReqwestTransport, Transport},
     util::*,
+    work_directory::{parse_assignments, WorkDirectory},
 };
+use chrono::Utc;
+use log::{debug, error, info, trace, warn};
 use regex::RegexBuilder;
-use reqwest::blocking::{Client, ClientBuilder};
-use std::fs::{remove_file, OpenOptions};
-use std::io::{BufWriter, Write};
-use std::path::Path;
-use std::str::from_utf8;
 use std::thread::sleep;
 use std::time::{Duration, Instant};
 
+// What GPU to 72 takes as the top of the range when there isn't one.
+const GPU72_MAX_EXPONENT: u64 = 10_000_000_000;
 const WVR: &str = r"(Factor=N\/A(,[0-9]+){3})$";
 
-fn gpu72_check_login(client: &Client, username: &str, password: &str) -> Result<(), String> {
-    let result = client
-        .get("https://www.gpu72.com/account/getassignments/")
-        .basic_auth(username, Some(password))
-        .send()
-        .map_err(|e| {
-            format!(
-                "Failed to send login check request to GPU to 72. Error: {}",
-                e
-            )
-        })?;
-    let status = result.status();
-    let response = result.text().map_err(|e| {
-        format!(
-            "Failed to read login check response from GPU to 72. Error: {}",
-            e
-        )
-    })?;
-    if status == 200 {
-        // Unsure of what a good response looks like, so just print it out and say it's good
-        println!("response: '{}'", response);
-        Ok(())
-    } else {
-        Err(format!(
-            "Bad response status: {}Error:\n{}",
-            status, response
-        ))
+pub fn gpu72_check_login(
+    transport: &dyn Transport,
+    endpoints: &Endpoints,
+    username: &str,
+    password: &str,
+) -> Result<(), Error> {
+    let login_check_addr = endpoints.gpu72_login_check();
+    debug!(target: NETWORK, "GET {} (login check)", login_check_addr);
+    let request = Request::get(&login_check_addr).basic_auth(username, password);
+    let result = transport
+        .send(&request)
+        .map_err(|e| Error::network("Failed to send login check request to GPU to 72.", e))?;
+    let status = result.status;
+    let url = result.url;
+    let response = result.body;
+    debug!(target: NETWORK, "Login check response from {}: status {}", url, status);
+    match status {
+        200 => {
+            // Unsure of what a good response looks like, so just log it and say it's good
+            trace!(target: NETWORK, "Login check response body: '{}'", response);
+            Ok(())
+        }
+        401 | 403 => Err(Error::Authentication(format!(
+            "GPU to 72 rejected the credentials for user '{}'.",
+            username
+        ))),
+        _ => Err(Error::HttpStatus {
+            url,
+            status,
+            body: response,
+        }),
     }
 }
 
-fn gpu72_request(
-    client: &Client,
+// The request for `num_to_get` assignments of `work_info`, without the credentials.
+pub fn gpu72_work_request(
+    endpoints: &Endpoints,
+    num_to_get: usize,
+    max_exp: u8,
+    work_info: Gpu72WorkType,
+    exponent_range: ExponentRange,
+) -> Request {
+    let (_, workopt) = work_info.as_str();
+    Request::get(&endpoints.gpu72_request(work_info)).query(&[
+        ("Number", &num_to_get.to_string()),
+        ("GHzDays", ""),
+        ("Low", &exponent_range.min.unwrap_or(0).to_string()),
+        (
+            "High",
+            &exponent_range.max.unwrap_or(GPU72_MAX_EXPONENT).to_string(),
+        ),
+        ("Pledge", &max_exp.to_string()),
+        ("Option", workopt),
+    ])
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn gpu72_request(
+    transport: &dyn Transport,
+    endpoints: &Endpoints,
     num_to_cache: usize,
     max_exp: u8,
-    worktodo_path: &Path,
-    worktodo_lock_path: &Path,
+    work_directory: &WorkDirectory,
     work_info: Gpu72WorkType,
+    exponent_range: ExponentRange,
     username: &str,
     password: &str,
-) -> Result<(), String> {
+) -> Result<(), Error> {
+    let worktodo_path = &work_directory.worktodo;
+    let worktodo_lock_path = &work_directory.worktodo_lock;
     while worktodo_lock_path.exists() {
+        debug!(target: LOCKING, "Waiting for {} to be unlocked.", worktodo_path.display());
         sleep(Duration::from_secs(1));
     }
-    let workfile_contents = read_list_lock(worktodo_path, worktodo_lock_path)
-        .map_err(|e| format!("Failed to read worktodo file. Error: {}", e))?;
-    if num_to_cache <= workfile_contents.len() {
-        println!(
+    let unlock_on_err = |e: Error| {
+        let _ = unlock_file(worktodo_lock_path);
+        e
+    };
+    let worktodo = read_list_lock(worktodo_path, worktodo_lock_path)?;
+    let worktodo = work_directory
+        .check_worktodo(worktodo)
+        .map_err(unlock_on_err)?;
+    let cached = parse_assignments(&worktodo).len();
+    if num_to_cache <= cached {
+        info!(
             "Already have {} assignment(s) cached of the requested {}. Not requesting more.",
-            workfile_contents.len(),
-            num_to_cache
+            cached, num_to_cache
         );
         unlock_file(worktodo_lock_path)
-            .map_err(|e| format!("Failed to unlock worktodo file. Error: {}", e))
     } else {
-        let (worktype_request_addr, workopt) = work_info.as_str();
-        let num_to_get = format!("{}", num_to_cache - workfile_contents.len());
-        let pledge = format!("{}", max_exp);
-        let response = client
-            .get(worktype_request_addr)
-            .basic_auth(username, Some(password))
-            .query(&[
-                // Force deref to &str since otherwise &String is expected
-                ("Number", &*num_to_get),
-                ("GHzDays", ""),
-                ("Low", "0"),
-                ("High", "10000000000"),
-                ("Pledge", &pledge),
-                ("Option", workopt),
-            ])
-            .send()
-            .map_err(|e| format!("Failed to make work request to GPU to 72. Error: {}", e))?;
-        println!("{:?}", response);
-        Ok(())
+        let num_to_get = num_to_cache - cached;
+        let request = gpu72_work_request(endpoints, num_to_get, max_exp, work_info, exponent_range)
+            .basic_auth(username, password);
+        debug!(
+            target: NETWORK,
+            "GET {} (Number={}, Option={}, exponents={})",
+            request.url,
+            num_to_get,
+            work_info.as_str().1,
+            exponent_range
+        );
+        let response = transport
+            .send(&request)
+            .map_err(|e| Error::network("Failed to make work request to GPU to 72.", e))
+            .map_err(unlock_on_err)?;
+        let status = response.status;
+        let url = response.url;
+        let response_text = response.body;
+        debug!(target: NETWORK, "Work request response from {}: status {}", url, status);
+        trace!(target: NETWORK, "Work request response body: {}", response_text);
+        if status == 200 {
+            let work_validation_regex = RegexBuilder::new(WVR)
+                .multi_line(true)
+                .build()
+                .expect("Failed to build regex for task validation");
+            let fetched = work_validation_regex
+                .captures_iter(&response_text)
+                .filter_map(|captures| Assignment::parse(&captures[0]).ok())
+                .collect::<Vec<_>>();
+            if fetched.is_empty() {
+                warn!(
+                    "Received work request response but failed to find any valid jobs in it. You \
+                    may want to check your GPU to 72 account to see if any work has been \
+                    reserved, and if so, add it to your worktodo file manually."
+                );
+                return unlock_file(worktodo_lock_path);
+            }
+            let validated_jobs =
+                validate_fetched(work_directory, fetched, exponent_range).map_err(unlock_on_err)?;
+            debug!(target: PARSING, "Validated jobs: {:?}", validated_jobs);
+            append_jobs(worktodo_path, &validated_jobs).map_err(unlock_on_err)?;
+            info!(
+                "Cached {} new assignment(s) in {}.",
+                validated_jobs.len(),
+                worktodo_path.display()
+            );
+            record_fetch(
+                work_directory,
+                WorkSource::Gpu72,
+                &worktodo,
+                &validated_jobs,
+                Utc::now(),
+            );
+            emit(Event::fetch(
+                WorkSource::Gpu72,
+                work_directory,
+                &parse_assignments(&validated_jobs),
+            ));
+            unlock_file(worktodo_lock_path)
+        } else {
+            unlock_file(worktodo_lock_path)?;
+            Err(Error::HttpStatus {
+                url,
+                status,
+                body: response_text,
+            })
+        }
     }
 }
 
-pub fn gpu72_runtime(gpu72_options: Gpu72Options) -> Result<(), String> {
+pub fn gpu72_runtime(gpu72_options: Gpu72Options) -> Result<(), Error> {
+    gpu72_runtime_with_transport(&ReqwestTransport::new()?, gpu72_options)
+}
+
+pub fn gpu72_runtime_with_transport(
+    transport: &dyn Transport,
+    gpu72_options: Gpu72Options,
+) -> Result<(), Error> {
     let Gpu72Options {
         primenet_credentials,
         gpu72_credentials: (gpu72_username, gpu72_password),
@@ -104,29 +205,108 @@ pub fn gpu72_runtime(gpu72_options: Gpu72Options) -> Result<(), String> {
             GeneralOptions {
                 work_directory,
                 num_cache,
+                days_of_work,
+                throughput,
                 timeout,
+                endpoints,
+                exponent_range,
+                client,
             },
     } = gpu72_options;
-    let client = ClientBuilder::default()
-        .cookie_store(true)
-        .build()
-        .map_err(|e| format!("Failed to build web client. Error: {}", e))?;
-    if let Some((p95_username, p95_password)) = primenet_credentials {
-        primenet_login(&client, &p95_username, &p95_password)?;
-        println!("Successfully logged into Primenet.");
+    gpu72_check_login(transport, &endpoints, &gpu72_username, &gpu72_password)?;
+    if let Some((p95_username, p95_password)) = &primenet_credentials {
+        primenet_ensure_login(transport, &endpoints, p95_username, p95_password)?;
     }
-    let worktodo_txt_path = Path::new(&work_directory).join(Path::new("worktodo.txt"));
-    let worktodo_ini_path = Path::new(&work_directory).join(Path::new("worktodo.ini"));
-    let (worktodo_path, worktodo_lock_path) = if worktodo_txt_path.exists() {
-        (
-            worktodo_txt_path,
-            Path::new(&work_directory).join(Path::new("worktodo.txt.lck")),
-        )
-    } else {
-        (
-            worktodo_ini_path,
-            Path::new(&work_directory).join(Path::new("worktodo.ini.lck")),
-        )
-    };
-    let results = Path::new(&work_directory).join(Path::new("results.txt"));
+    let work_directory = WorkDirectory::from_options(&work_directory, client);
+    loop {
+        let start = Instant::now();
+        let measured = update_throughput(&work_directory);
+        let days_of_work = days_of_work_with(days_of_work, throughput, measured);
+        let request = top_up(&work_directory, num_cache, days_of_work, |num_to_cache| {
+            gpu72_request(
+                transport,
+                &endpoints,
+                num_to_cache,
+                max_exp,
+                &work_directory,
+                work_type,
+                exponent_range,
+                &gpu72_username,
+                &gpu72_password,
+            )
+            .map(|_| None)
+        });
+        // GPU to 72 doesn't take results itself, they get reported to Primenet as usual.
+        let submit = match &primenet_credentials {
+            Some((p95_username, p95_password)) => {
+                with_primenet_session(transport, &endpoints, p95_username, p95_password, || {
+                    primenet_submit(transport, &endpoints, &work_directory)
+                })
+            }
+            None => Ok(()),
+        };
+        log_cycle_summary(transport);
+        if timeout == 0 {
+            return request.and(submit);
+        }
+        if let Err(e) = request {
+            error!("{}", e);
+            emit(Event::error(&e, false));
+        } else {
+            info!("Successfully requested and cached jobs.");
+        }
+        if let Err(e) = submit {
+            error!("{}", e);
+            emit(Event::error(&e, false));
+        }
+        debug!("Sleeping until the next update in {} seconds.", timeout);
+        let sleep_duration = Duration::from_secs(timeout as u64)
+            .checked_sub(start.elapsed())
+            .unwrap_or_default();
+        sleep(sleep_duration);
+    }
+}
+
+// Check the logins and print what a run would request and submit, without doing either or
+// touching any file.
+pub fn gpu72_dry_run_with_transport(
+    transport: &dyn Transport,
+    gpu72_options: Gpu72Options,
+) -> Result<(), Error> {
+    let Gpu72Options {
+        primenet_credentials,
+        gpu72_credentials: (gpu72_username, gpu72_password),
+        work_type,
+        max_exp,
+        general_options,
+    } = gpu72_options;
+    let endpoints = &general_options.endpoints;
+    gpu72_check_login(transport, endpoints, &gpu72_username, &gpu72_password)?;
+    if let Some((p95_username, p95_password)) = &primenet_credentials {
+        primenet_ensure_login(transport, endpoints, p95_username, p95_password)?;
+    }
+    let plan = DryRun::plan(
+        &general_options,
+        |num_to_get| {
+            gpu72_work_request(
+                endpoints,
+                num_to_get,
+                max_exp,
+                work_type,
+                general_options.exponent_range,
+            )
+        },
+        primenet_credentials.is_some(),
+        Utc::now(),
+    )?;
+    output(&plan, || Event::dry_run(&plan));
+    Ok(())
+}
+
+// Remove any lockfiles left behind in the work directory. Only safe to call when the error that
+// brought us here wasn't caused by somebody else's lock.
+pub fn gpu72_cleanup(gpu72_options: Gpu72Options) {
+    let general_options = &gpu72_options.general_options;
+    let _ = WorkDirectory::from_options(&general_options.work_directory, general_options.client)
+        .unlock_all();
 }
@@ -1,3 +1,5 @@
+use crate::assignment::AssignmentKind;
+
 #[derive(Copy, Clone, Debug)]
 pub enum Gpu72WorkType {
     LucasLehmerTrialFactor(Gpu72LLTFWorkOption),
@@ -5,16 +7,26 @@ pub enum Gpu72WorkType {
     LucasLehmerP1(Gpu72LLP1WorkOption),
 }
 
-const LLTF_ADDR: &str = "https://www.gpu72.com/account/getassignments/lltf";
-const DCTF_ADDR: &str = "https://www.gpu72.com/account/getassignments/dctf";
-const LLP1_ADDR: &str = "https://www.gpu72.com/account/getassignments/llp-1";
+// Relative to the GPU to 72 assignments page, see Endpoints::gpu72_request.
+const LLTF_PATH: &str = "lltf";
+const DCTF_PATH: &str = "dctf";
+const LLP1_PATH: &str = "llp-1";
 
 impl Gpu72WorkType {
+    // (path, option code)
     pub fn as_str(&self) -> (&'static str, &'static str) {
         match self {
-            Gpu72WorkType::LucasLehmerTrialFactor(opt) => (LLTF_ADDR, opt.as_str()),
-            Gpu72WorkType::DoubleCheckTrialFactor(opt) => (DCTF_ADDR, opt.as_str()),
-            Gpu72WorkType::LucasLehmerP1(opt) => (LLP1_ADDR, opt.as_str()),
+            Gpu72WorkType::LucasLehmerTrialFactor(opt) => (LLTF_PATH, opt.as_str()),
+            Gpu72WorkType::DoubleCheckTrialFactor(opt) => (DCTF_PATH, opt.as_str()),
+            Gpu72WorkType::LucasLehmerP1(opt) => (LLP1_PATH, opt.as_str()),
+        }
+    }
+
+    // Trial factoring comes as Factor lines and P-1 as Pfactor lines.
+    pub fn assignment_kind(&self) -> AssignmentKind {
+        match self {
+            Gpu72WorkType::LucasLehmerP1(_) => AssignmentKind::Pfactor,
+            _ => AssignmentKind::Factor,
         }
     }
 }
@@ -0,0 +1,78 @@
+use crate::logging::PARSING;
+use log::trace;
+use std::fmt;
+use std::fs::{read_dir, File};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+// Extensions of the checkpoints gpuowl saves PRP and LL progress in. Older versions use .owl,
+// newer ones and PRPLL use .prp and .ll, sometimes with the iteration in the file name.
+const CHECKPOINT_EXTENSIONS: &[&str] = &["owl", "prp", "ll"];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Progress {
+    pub exponent: u64,
+    pub iteration: u64,
+    // A PRP or LL test of M<exponent> takes about <exponent> iterations.
+    pub iterations: u64,
+}
+
+impl Progress {
+    pub fn percent(&self) -> f64 {
+        (self.iteration.min(self.iterations) as f64) * 100.0 / (self.iterations.max(1) as f64)
+    }
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "M{} is {:.1}% done ({} of {} iterations)",
+            self.exponent,
+            self.percent(),
+            self.iteration,
+            self.iterations
+        )
+    }
+}
+
+// The iteration a checkpoint was saved at, from its first line. Every version writes a text header
+// with the exponent followed by the iteration, e.g. "PRP 12 96364649 2500000 ...", and whatever
+// comes before the exponent (and after the iteration) varies between versions.
+pub fn checkpoint_iteration(header: &str, exponent: u64) -> Option<u64> {
+    let exponent = exponent.to_string();
+    let mut fields = header.split_whitespace();
+    fields.find(|field| *field == exponent)?;
+    fields.next()?.parse::<u64>().ok()
+}
+
+fn read_header(path: &Path) -> Option<String> {
+    let mut header = Vec::new();
+    BufReader::new(File::open(path).ok()?)
+        .read_until(b'\n', &mut header)
+        .ok()?;
+    Some(String::from_utf8_lossy(&header).into_owned())
+}
+
+// How far along M<exponent> is, going by the newest checkpoint in its directory under the work
+// directory. None if there's no readable checkpoint yet.
+pub fn checkpoint_progress(work_directory: &Path, exponent: u64) -> Option<Progress> {
+    let iteration = read_dir(work_directory.join(exponent.to_string()))
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| CHECKPOINT_EXTENSIONS.iter().any(|known| ext == *known))
+        })
+        .filter_map(|path| {
+            let iteration = checkpoint_iteration(&read_header(&path)?, exponent);
+            trace!(target: PARSING, "Checkpoint {}: iteration {:?}", path.display(), iteration);
+            iteration
+        })
+        .max()?;
+    Some(Progress {
+        exponent,
+        iteration,
+        iterations: exponent,
+    })
+}
@@ -0,0 +1,57 @@
+//! Work fetching and result submission for Primenet and GPU to 72.
+//!
+//! The `primenet-rs` binary parses the command line into [`options::Options`] and hands them to
+//! one of the runtimes:
+//!
+//! - [`primenet_runtime::primenet_runtime`] fetches work from and submits results to Primenet.
+//! - [`gpu72_runtime::gpu72_runtime`] does the same with GPU to 72.
+//! - [`queue_runtime::queue_runtime`] shows the results waiting to be submitted.
+//! - [`factors_runtime::factors_runtime`] shows the factors found.
+//! - [`status_runtime::status_runtime`] shows the state of a work directory.
+//!
+//! `--output json` prints the [`events`] instead of text, and `--dry-run` prints a
+//! [`dry_run::DryRun`] plan instead of fetching or submitting. Everything the runtimes are built
+//! from is public.
+
+pub mod activity;
+pub mod assignment;
+pub mod client;
+pub mod cookie_jar;
+pub mod credit;
+pub mod dry_run;
+pub mod endpoints;
+pub mod error;
+pub mod events;
+pub mod factor;
+pub mod factor_report;
+pub mod factors_runtime;
+pub mod gpu72_runtime;
+pub mod gpu72_work;
+pub mod gpuowl;
+pub mod logging;
+pub mod mlucas;
+pub mod options;
+pub mod p95_work;
+pub mod primenet_pages;
+pub mod primenet_runtime;
+pub mod proof_upload;
+pub mod queue_runtime;
+pub mod recording;
+pub mod retry;
+pub mod status;
+pub mod status_runtime;
+pub mod submission_queue;
+pub mod throughput;
+pub mod transport;
+pub mod util;
+pub mod work_directory;
+pub mod work_result;
+
+pub use assignment::{Assignment, AssignmentKind};
+pub use client::Client;
+pub use endpoints::Endpoints;
+pub use error::Error;
+pub use submission_queue::{QueuedResult, SubmissionQueue};
+pub use transport::{ReqwestTransport, ScriptedTransport, Transport};
+pub use work_directory::WorkDirectory;
+pub use work_result::{ResultKind, WorkResult};
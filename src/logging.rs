@@ -0,0 +1,246 @@
+use crate::{
+    error::Error,
+    options::{LogFileOptions, LoggingOptions},
+};
+use chrono::Local;
+use log::{LevelFilter, Log, Metadata, Record};
+use std::fs::{rename, File, OpenOptions};
+use std::io::{stderr, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// Log targets for the subsystems that can be filtered separately with --log-filter. Everything
+// else logs under its module path.
+pub const NETWORK: &str = "network";
+pub const LOCKING: &str = "locking";
+pub const PARSING: &str = "parsing";
+
+// Size-capped log file. Once the next line would push it past the size limit, log -> log.1,
+// log.1 -> log.2 and so on, dropping whatever falls off the end.
+struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    max_files: usize,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(path: &Path, max_size: u64, max_files: usize) -> Result<Self, Error> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| Error::io(format!("Failed to open log file {}.", path.display()), e))?;
+        let size = file
+            .metadata()
+            .map_err(|e| Error::io(format!("Failed to stat log file {}.", path.display()), e))?
+            .len();
+        Ok(RotatingFile {
+            path: path.to_path_buf(),
+            max_size,
+            max_files,
+            file,
+            size,
+        })
+    }
+
+    fn rotated_path(&self, n: usize) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        for n in (1..self.max_files).rev() {
+            let from = self.rotated_path(n);
+            if from.exists() {
+                rename(&from, self.rotated_path(n + 1))?;
+            }
+        }
+        if self.max_files > 0 {
+            rename(&self.path, self.rotated_path(1))?;
+        }
+        self.file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        let len = line.len() as u64 + 1;
+        if self.size > 0 && self.size + len > self.max_size {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.size += len;
+        Ok(())
+    }
+}
+
+struct Logger {
+    terminal_level: LevelFilter,
+    file_level: LevelFilter,
+    target_filters: Vec<(String, LevelFilter)>,
+    file: Option<Mutex<RotatingFile>>,
+}
+
+impl Logger {
+    // The most specific --log-filter entry for a target wins over the general levels.
+    fn target_filter(&self, target: &str) -> Option<LevelFilter> {
+        self.target_filters
+            .iter()
+            .filter(|(name, _)| {
+                target == name
+                    || (target.starts_with(name.as_str()) && target[name.len()..].starts_with("::"))
+            })
+            .max_by_key(|(name, _)| name.len())
+            .map(|(_, level)| *level)
+    }
+
+    // Dependencies like reqwest and hyper are chatty at debug level, so unless they're asked for
+    // with --log-filter they only show up at trace level.
+    fn level_for(&self, target: &str, general_level: LevelFilter) -> LevelFilter {
+        self.target_filter(target).unwrap_or_else(|| {
+            let own_target = target == NETWORK
+                || target == LOCKING
+                || target == PARSING
+                || target.starts_with(env!("CARGO_CRATE_NAME"));
+            if own_target || general_level == LevelFilter::Trace {
+                general_level
+            } else {
+                general_level.min(LevelFilter::Info)
+            }
+        })
+    }
+
+    fn terminal_enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level_for(metadata.target(), self.terminal_level)
+    }
+
+    fn file_enabled(&self, metadata: &Metadata) -> bool {
+        self.file.is_some()
+            && metadata.level() <= self.level_for(metadata.target(), self.file_level)
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.terminal_enabled(metadata) || self.file_enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        let metadata = record.metadata();
+        if !self.enabled(metadata) {
+            return;
+        }
+        let line = format!(
+            "[{}] {:<5} {}: {}",
+            Local::now().format("%Y-%m-%d %H:%M:%S"),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        if self.terminal_enabled(metadata) {
+            let _ = writeln!(stderr(), "{}", line);
+        }
+        if self.file_enabled(metadata) {
+            if let Some(file) = &self.file {
+                if let Ok(mut file) = file.lock() {
+                    if let Err(e) = file.write_line(&line) {
+                        let _ = writeln!(stderr(), "Failed to write to log file. Error: {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn flush(&self) {
+        let _ = stderr().flush();
+        if let Some(file) = &self.file {
+            if let Ok(mut file) = file.lock() {
+                let _ = file.file.flush();
+            }
+        }
+    }
+}
+
+pub fn init_logging(logging_options: &LoggingOptions) -> Result<(), Error> {
+    let LoggingOptions {
+        level,
+        log_file,
+        target_filters,
+    } = logging_options;
+    let (file, file_level) = match log_file {
+        Some(LogFileOptions {
+            path,
+            level,
+            max_size,
+            max_files,
+        }) => (
+            Some(Mutex::new(RotatingFile::open(
+                Path::new(path),
+                *max_size,
+                *max_files,
+            )?)),
+            *level,
+        ),
+        None => (None, LevelFilter::Off),
+    };
+    let max_level = target_filters
+        .iter()
+        .map(|(_, level)| *level)
+        .chain(vec![*level, file_level])
+        .max()
+        .unwrap_or(LevelFilter::Info);
+    log::set_boxed_logger(Box::new(Logger {
+        terminal_level: *level,
+        file_level,
+        target_filters: target_filters.clone(),
+        file,
+    }))
+    .map_err(|e| Error::Usage(format!("Failed to set up logging. Error: {}", e)))?;
+    log::set_max_level(max_level);
+    Ok(())
+}
+
+// Parse a --log-filter value like "network=debug,locking=off".
+pub fn parse_target_filters(s: &str) -> Result<Vec<(String, LevelFilter)>, String> {
+    s.split(',')
+        .filter(|entry| !entry.trim().is_empty())
+        .map(|entry| {
+            let mut parts = entry.splitn(2, '=');
+            let target = parts.next().unwrap_or("").trim();
+            let level = parts.next().map(str::trim).unwrap_or("");
+            if target.is_empty() {
+                return Err(format!("Log filter '{}' is missing a target.", entry));
+            }
+            level
+                .parse::<LevelFilter>()
+                .map(|level| (target.to_string(), level))
+                .map_err(|_| {
+                    format!(
+                        "Log filter '{}' has an invalid level. Expected one of off, error, warn, \
+                        info, debug or trace.",
+                        entry
+                    )
+                })
+        })
+        .collect()
+}
+
+// -q/-qq and -v/-vv move the terminal level down or up from the default of info. --debug is the
+// same as -v, like the Python scripts' --debug.
+pub fn level_from_verbosity(verbose: u64, quiet: u64, debug: bool) -> LevelFilter {
+    let verbose = if debug { verbose.max(1) } else { verbose };
+    match verbose as i64 - quiet as i64 {
+        n if n <= -2 => LevelFilter::Error,
+        -1 => LevelFilter::Warn,
+        0 => LevelFilter::Info,
+        1 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
@@ -1,31 +1,80 @@
 mod clap_handler;
-mod gpu72_runtime;
-mod primenet_runtime;
-mod util;
 
-use clap_handler::app::{request_from_args, Options};
-use gpu72_runtime::{gpu72_cleanup, gpu72_runtime};
-use primenet_runtime::{primenet_cleanup, primenet_runtime};
+use clap_handler::app::request_from_args;
+use log::{debug, error};
+use primenet_rs::{
+    error::Error,
+    events::{emit, init_output, Event},
+    factors_runtime::factors_runtime,
+    gpu72_runtime::{gpu72_cleanup, gpu72_dry_run_with_transport, gpu72_runtime_with_transport},
+    logging::init_logging,
+    options::Options,
+    primenet_runtime::{
+        primenet_cleanup, primenet_dry_run_with_transport, primenet_runtime_with_transport,
+    },
+    queue_runtime::queue_runtime,
+    status_runtime::status_runtime,
+    transport::build_transport,
+};
+use std::error::Error as StdError;
+use std::process::exit;
 
-fn main() {
-    match request_from_args() {
-        Ok(o) => {
-            println!("Successfully parsed command line arguments.");
-            match (o, o.clone()) {
-                (Options::Primenet(primenet_options), Options::Primenet(clone)) => {
-                    if primenet_runtime(primenet_options).is_err() {
-                        primenet_cleanup(clone);
-                    }
-                }
-                (Options::Gpu72(gpu72_options), Options::Gpu72(clone)) => {
-                    if gpu72_runtime(gpu72_options).is_err() {
-                        // gpu72_cleanup(clone);
-                        println!("oh no");
+fn report(e: &Error) {
+    error!("{}", e);
+    // Display already includes the immediate cause, so only walk what's underneath it.
+    let mut source = e.source().and_then(StdError::source);
+    while let Some(cause) = source {
+        error!("Caused by: {}", cause);
+        source = cause.source();
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let (global_options, options) = request_from_args().map_err(Error::Usage)?;
+    init_logging(&global_options.logging_options)?;
+    init_output(global_options.output);
+    debug!("Successfully parsed command line arguments.");
+    // Only the subcommands that talk to a server need a transport.
+    let transport = || build_transport(&global_options);
+    match options {
+        // A dry run takes no locks, so there's nothing to clean up after one.
+        Options::Primenet(primenet_options) if global_options.dry_run => {
+            primenet_dry_run_with_transport(&*transport()?, primenet_options)
+        }
+        Options::Gpu72(gpu72_options) if global_options.dry_run => {
+            gpu72_dry_run_with_transport(&*transport()?, gpu72_options)
+        }
+        Options::Primenet(primenet_options) => {
+            primenet_runtime_with_transport(&*transport()?, primenet_options.clone()).inspect_err(
+                |e| {
+                    if e.should_clean_up() {
+                        primenet_cleanup(primenet_options);
                     }
+                },
+            )
+        }
+        Options::Gpu72(gpu72_options) => {
+            gpu72_runtime_with_transport(&*transport()?, gpu72_options.clone()).inspect_err(|e| {
+                if e.should_clean_up() {
+                    gpu72_cleanup(gpu72_options);
                 }
-                _ => unreachable!(),
-            }
+            })
+        }
+        Options::Queue(queue_options) => queue_runtime(queue_options),
+        Options::Factors(factors_options) => factors_runtime(factors_options),
+        Options::Status(status_options) => status_runtime(status_options),
+    }
+}
+
+fn main() {
+    if let Err(e) = run() {
+        // Argument and logging setup errors happen before there's a logger to report them.
+        if log::max_level() == log::LevelFilter::Off {
+            eprintln!("{}", e);
+        } else {
+            report(&e);
         }
-        Err(e) => println!("{}", e),
+        emit(Event::error(&e, true));
+        exit(e.exit_code());
     }
 }
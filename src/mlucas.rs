@@ -0,0 +1,50 @@
+use crate::logging::PARSING;
+use log::trace;
+use regex::Regex;
+use std::fs::read_to_string;
+use std::path::Path;
+
+// How fast Mlucas was going at its last checkpoint, from the p<exponent>.stat file it logs each
+// checkpoint to.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct StatTiming {
+    pub exponent: u64,
+    pub iteration: u64,
+    pub seconds_per_iteration: f64,
+}
+
+// The timing in one line of a stat file, e.g.
+//     [2021-04-18 10:39:55] M110000117 Iter# = 10000 [ 0.01% complete] clocks = 00:01:23.456
+//     [  8.3456 msec/iter] Res64: 0123456789ABCDEF. ...
+// all on one line. Older versions give sec/iter instead of msec/iter.
+pub fn stat_line_timing(line: &str) -> Option<StatTiming> {
+    let captures = Regex::new(r"M([0-9]+) Iter# = ([0-9]+) .*\[\s*([0-9.]+) (m?sec)/iter\]")
+        .expect("Failed to build regex for Mlucas stat lines")
+        .captures(line)?;
+    let per_iteration = captures[3].parse::<f64>().ok()?;
+    let seconds_per_iteration = match &captures[4] {
+        "msec" => per_iteration / 1000.0,
+        _ => per_iteration,
+    };
+    if seconds_per_iteration <= 0.0 {
+        return None;
+    }
+    Some(StatTiming {
+        exponent: captures[1].parse().ok()?,
+        iteration: captures[2].parse().ok()?,
+        seconds_per_iteration,
+    })
+}
+
+// The newest timing Mlucas has logged for M<exponent>, if it's started on it.
+pub fn stat_timing(work_directory: &Path, exponent: u64) -> Option<StatTiming> {
+    let path = work_directory.join(format!("p{}.stat", exponent));
+    let stat = read_to_string(&path).ok()?;
+    let timing = stat
+        .lines()
+        .rev()
+        .filter_map(stat_line_timing)
+        .find(|timing| timing.exponent == exponent);
+    trace!(target: PARSING, "Stat file {}: {:?}", path.display(), timing);
+    timing
+}
@@ -0,0 +1,134 @@
+use crate::{
+    assignment::ExponentRange, client::Client, endpoints::Endpoints, gpu72_work::Gpu72WorkType,
+    p95_work::PrimenetWorkType, retry::RetryPolicy,
+};
+use log::LevelFilter;
+
+#[derive(Clone, Debug)]
+pub struct LogFileOptions {
+    pub path: String,
+    pub level: LevelFilter,
+    pub max_size: u64,
+    pub max_files: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct LoggingOptions {
+    pub level: LevelFilter,
+    pub log_file: Option<LogFileOptions>,
+    pub target_filters: Vec<(String, LevelFilter)>,
+}
+
+// Where server traffic goes. Recording saves every exchange (minus passwords) to a directory, and
+// replaying serves a saved directory back instead of using the network.
+#[derive(Clone, Debug)]
+pub enum TrafficOptions {
+    Live,
+    Record(String),
+    Replay(String),
+}
+
+// What goes to stdout: what the text subcommands print, or JSON events (see events.rs).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+impl OutputFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "text" => Some(OutputFormat::Text),
+            "json" => Some(OutputFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+// Options that apply no matter which subcommand is used.
+#[derive(Clone, Debug)]
+pub struct GlobalOptions {
+    pub logging_options: LoggingOptions,
+    pub output: OutputFormat,
+    pub traffic: TrafficOptions,
+    pub retry_policy: RetryPolicy,
+    // File the login session is kept in between runs.
+    pub cookie_jar: Option<String>,
+    // Work out what would be fetched and submitted, and print it instead of doing it.
+    pub dry_run: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct GeneralOptions {
+    pub work_directory: String,
+    pub num_cache: usize,
+    // Cache enough work for this many days instead of `num_cache` assignments.
+    pub days_of_work: Option<f64>,
+    // GHz-days of work done per day. Without it the throughput measured in the work directory is
+    // used.
+    pub throughput: Option<f64>,
+    pub timeout: usize,
+    pub endpoints: Endpoints,
+    // Assignments outside this range are never cached.
+    pub exponent_range: ExponentRange,
+    // The client working out of the work directory, which decides its file names and what work
+    // can be cached for it. Without one the file names are guessed and any work is cached.
+    pub client: Option<Client>,
+}
+
+// What happens to PRP proof files in the work directory once their results are submitted.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ProofOptions {
+    // Leave them alone, for uploading some other way.
+    NoUpload,
+    // Upload them, then move them to proofs-uploaded/.
+    Archive,
+    // Upload them, then delete them.
+    Delete,
+}
+
+#[derive(Clone, Debug)]
+pub struct PrimenetOptions {
+    pub credentials: (String, String),
+    pub work_type: PrimenetWorkType,
+    // Requested instead when Primenet has no work of `work_type` to give out.
+    pub fallback_work_type: Option<PrimenetWorkType>,
+    // Number of cores the assignments are meant for, which Primenet sizes them by.
+    pub cores: usize,
+    pub proofs: ProofOptions,
+    pub general_options: GeneralOptions,
+}
+
+#[derive(Clone, Debug)]
+pub struct Gpu72Options {
+    pub primenet_credentials: Option<(String, String)>,
+    pub gpu72_credentials: (String, String),
+    pub work_type: Gpu72WorkType,
+    pub max_exp: u8,
+    pub general_options: GeneralOptions,
+}
+
+#[derive(Clone, Debug)]
+pub struct QueueOptions {
+    pub work_directory: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct FactorsOptions {
+    pub work_directory: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct StatusOptions {
+    pub work_directory: String,
+    pub client: Option<Client>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Options {
+    Primenet(PrimenetOptions),
+    Gpu72(Gpu72Options),
+    Queue(QueueOptions),
+    Factors(FactorsOptions),
+    Status(StatusOptions),
+}
@@ -0,0 +1,127 @@
+use crate::assignment::AssignmentKind;
+use std::fmt;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PrimenetWorkType {
+    TrialFactoring,
+    P1Factoring,
+    EcmFactoring,
+    EcmFactoringOfMersenneCofactors,
+    SmallestAvailableFirstTimeLlTests,
+    DoubleCheckLlTests,
+    WorldRecordLlTests,
+    HundredMillionDigitsLlTests,
+    SmallestAvailableFirstTimePrpTests,
+    DoubleCheckPrpTests,
+    WorldRecordPrpTests,
+    HundredMillionDigitsPrpTests,
+    FirstPrpTestsOnMersenneCofactors,
+    DoubleCheckPrpTestsOnMersenneCofactors,
+    Certification,
+}
+
+impl PrimenetWorkType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PrimenetWorkType::TrialFactoring => "2",
+            PrimenetWorkType::P1Factoring => "4",
+            PrimenetWorkType::EcmFactoring => "5",
+            PrimenetWorkType::EcmFactoringOfMersenneCofactors => "8",
+            PrimenetWorkType::SmallestAvailableFirstTimeLlTests => "100",
+            PrimenetWorkType::DoubleCheckLlTests => "101",
+            PrimenetWorkType::WorldRecordLlTests => "102",
+            PrimenetWorkType::HundredMillionDigitsLlTests => "104",
+            PrimenetWorkType::SmallestAvailableFirstTimePrpTests => "150",
+            PrimenetWorkType::DoubleCheckPrpTests => "151",
+            PrimenetWorkType::WorldRecordPrpTests => "152",
+            PrimenetWorkType::HundredMillionDigitsPrpTests => "153",
+            PrimenetWorkType::FirstPrpTestsOnMersenneCofactors => "160",
+            PrimenetWorkType::DoubleCheckPrpTestsOnMersenneCofactors => "161",
+            PrimenetWorkType::Certification => "200",
+        }
+    }
+
+    // The command line name of the work type, as in --double-check-ll.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PrimenetWorkType::TrialFactoring => "trial-factoring",
+            PrimenetWorkType::P1Factoring => "p1-factoring",
+            PrimenetWorkType::EcmFactoring => "ecm-factoring",
+            PrimenetWorkType::EcmFactoringOfMersenneCofactors => {
+                "ecm-factoring-of-mersenne-cofactors"
+            }
+            PrimenetWorkType::SmallestAvailableFirstTimeLlTests => {
+                "smallest-available-first-time-ll"
+            }
+            PrimenetWorkType::DoubleCheckLlTests => "double-check-ll",
+            PrimenetWorkType::WorldRecordLlTests => "world-record-ll",
+            PrimenetWorkType::HundredMillionDigitsLlTests => "100m-digits-ll",
+            PrimenetWorkType::SmallestAvailableFirstTimePrpTests => {
+                "smallest-available-first-time-prp"
+            }
+            PrimenetWorkType::DoubleCheckPrpTests => "double-check-prp",
+            PrimenetWorkType::WorldRecordPrpTests => "world-record-prp",
+            PrimenetWorkType::HundredMillionDigitsPrpTests => "100m-digits-prp",
+            PrimenetWorkType::FirstPrpTestsOnMersenneCofactors => "first-prp-on-mersenne-cofactors",
+            PrimenetWorkType::DoubleCheckPrpTestsOnMersenneCofactors => {
+                "double-check-prp-on-mersenne-cofactors"
+            }
+            PrimenetWorkType::Certification => "cert",
+        }
+    }
+
+    // The kinds of worktodo line Primenet hands out for the work type.
+    pub fn assignment_kinds(&self) -> &'static [AssignmentKind] {
+        match self {
+            PrimenetWorkType::TrialFactoring => &[AssignmentKind::Factor],
+            PrimenetWorkType::P1Factoring => &[AssignmentKind::Pfactor, AssignmentKind::Pminus1],
+            PrimenetWorkType::EcmFactoring | PrimenetWorkType::EcmFactoringOfMersenneCofactors => {
+                &[AssignmentKind::Ecm]
+            }
+            PrimenetWorkType::DoubleCheckLlTests => &[AssignmentKind::DoubleCheck],
+            PrimenetWorkType::SmallestAvailableFirstTimeLlTests
+            | PrimenetWorkType::WorldRecordLlTests
+            | PrimenetWorkType::HundredMillionDigitsLlTests => &[AssignmentKind::Test],
+            PrimenetWorkType::DoubleCheckPrpTests
+            | PrimenetWorkType::DoubleCheckPrpTestsOnMersenneCofactors => {
+                &[AssignmentKind::Prp, AssignmentKind::PrpDoubleCheck]
+            }
+            PrimenetWorkType::SmallestAvailableFirstTimePrpTests
+            | PrimenetWorkType::WorldRecordPrpTests
+            | PrimenetWorkType::HundredMillionDigitsPrpTests
+            | PrimenetWorkType::FirstPrpTestsOnMersenneCofactors => &[AssignmentKind::Prp],
+            PrimenetWorkType::Certification => &[AssignmentKind::Cert],
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        PRIMENET_WORK_TYPES
+            .iter()
+            .find(|work_type| work_type.name() == name)
+            .copied()
+    }
+}
+
+pub const PRIMENET_WORK_TYPES: [PrimenetWorkType; 15] = [
+    PrimenetWorkType::TrialFactoring,
+    PrimenetWorkType::P1Factoring,
+    PrimenetWorkType::EcmFactoring,
+    PrimenetWorkType::EcmFactoringOfMersenneCofactors,
+    PrimenetWorkType::SmallestAvailableFirstTimeLlTests,
+    PrimenetWorkType::DoubleCheckLlTests,
+    PrimenetWorkType::WorldRecordLlTests,
+    PrimenetWorkType::HundredMillionDigitsLlTests,
+    PrimenetWorkType::SmallestAvailableFirstTimePrpTests,
+    PrimenetWorkType::DoubleCheckPrpTests,
+    PrimenetWorkType::WorldRecordPrpTests,
+    PrimenetWorkType::HundredMillionDigitsPrpTests,
+    PrimenetWorkType::FirstPrpTestsOnMersenneCofactors,
+    PrimenetWorkType::DoubleCheckPrpTestsOnMersenneCofactors,
+    PrimenetWorkType::Certification,
+];
+
+impl fmt::Display for PrimenetWorkType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
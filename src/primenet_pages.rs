@@ -0,0 +1,264 @@
+use crate::assignment::Assignment;
+use regex::Regex;
+use scraper::{node::Node, Html, Selector};
+
+// Elements that start a new line when a browser renders the page.
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "blockquote",
+    "br",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "li",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "td",
+    "th",
+    "tr",
+    "ul",
+];
+
+const LOGIN_FORM_SELECTOR: &str = r#"input[name="user_password"]"#;
+const LOGGED_IN_LINE: &str = "logged in";
+const PROCESSING_PREFIX: &str = "processing:";
+const CREDIT_REGEX: &str = r"CPU credit is ([0-9]+(?:\.[0-9]+)?) GHz-days";
+
+fn is_block(node: &Node) -> bool {
+    matches!(node, Node::Element(element) if BLOCK_ELEMENTS.contains(&element.name()))
+}
+
+fn is_hidden(node: &Node) -> bool {
+    match node {
+        Node::Element(element) => element.name() == "script" || element.name() == "style",
+        _ => false,
+    }
+}
+
+// The text of a page split into trimmed, non-empty lines, roughly as a browser would show it.
+pub fn page_lines(html: &str) -> Vec<String> {
+    let document = Html::parse_document(html);
+    let mut text = String::new();
+    for node in document.root_element().descendants() {
+        if is_block(node.value())
+            || node
+                .prev_sibling()
+                .is_some_and(|prev| is_block(prev.value()))
+        {
+            text.push('\n');
+        }
+        if let Node::Text(node_text) = node.value() {
+            if !node
+                .parent()
+                .is_some_and(|parent| is_hidden(parent.value()))
+            {
+                text.push_str(node_text);
+            }
+        }
+    }
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoginState {
+    // Logged in, as the user the page names.
+    LoggedIn(String),
+    // The login form is showing instead.
+    LoggedOut,
+    // Neither, e.g. an error page.
+    Unknown,
+}
+
+impl LoginState {
+    // Primenet user names aren't case sensitive.
+    pub fn is_logged_in_as(&self, username: &str) -> bool {
+        matches!(self, LoginState::LoggedIn(user) if user.eq_ignore_ascii_case(username))
+    }
+}
+
+// Primenet shows the user name with "logged in" on the line after it in the login box, and the
+// login form on any page that needs a session when there isn't one.
+pub fn login_state(html: &str) -> LoginState {
+    let lines = page_lines(html);
+    let logged_in_as = lines
+        .windows(2)
+        .find(|pair| pair[1].eq_ignore_ascii_case(LOGGED_IN_LINE))
+        .map(|pair| pair[0].clone());
+    if let Some(user) = logged_in_as {
+        return LoginState::LoggedIn(user);
+    }
+    let login_form = Selector::parse(LOGIN_FORM_SELECTOR).expect("Failed to build login selector");
+    if Html::parse_document(html)
+        .select(&login_form)
+        .next()
+        .is_some()
+    {
+        LoginState::LoggedOut
+    } else {
+        LoginState::Unknown
+    }
+}
+
+// Assignment lines on a manual assignment page. Primenet always gives out assignments with an AID,
+// which keeps example lines in the page's instructions from being picked up.
+pub fn assignments(html: &str) -> Vec<Assignment> {
+    page_lines(html)
+        .iter()
+        .filter_map(|line| Assignment::parse(line).ok())
+        .filter(|assignment| assignment.aid.is_some())
+        .collect()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ResultOutcome {
+    // GHz-days are only there when Primenet gave credit for the result.
+    Accepted { ghz_days: Option<f64> },
+    Rejected(String),
+    Unknown,
+}
+
+// What Primenet said about one of the result lines it processed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResultMessage {
+    // The result line as Primenet echoed it back. Empty for messages that came before any result
+    // was processed.
+    pub result: String,
+    pub outcome: ResultOutcome,
+}
+
+fn outcome(lines: &[String], credit_regex: &Regex) -> ResultOutcome {
+    if let Some(error) = lines.iter().find(|line| line.starts_with("Error")) {
+        return ResultOutcome::Rejected(error.clone());
+    }
+    let ghz_days = lines.iter().find_map(|line| {
+        credit_regex
+            .captures(line)
+            .and_then(|captures| captures[1].parse::<f64>().ok())
+    });
+    if ghz_days.is_some() || lines.iter().any(|line| line.contains("Accepted")) {
+        ResultOutcome::Accepted { ghz_days }
+    } else {
+        ResultOutcome::Unknown
+    }
+}
+
+// Split a manual result page into what it says about each result. Each result starts at its
+// "processing:" line and takes everything up to the next one.
+pub fn submission_results(html: &str) -> Vec<ResultMessage> {
+    let credit_regex = Regex::new(CREDIT_REGEX).expect("Failed to build regex for credit");
+    let mut blocks: Vec<(String, Vec<String>)> = Vec::new();
+    for line in page_lines(html) {
+        let is_processing = line
+            .get(..PROCESSING_PREFIX.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(PROCESSING_PREFIX));
+        if is_processing {
+            blocks.push((
+                line[PROCESSING_PREFIX.len()..].trim().to_string(),
+                Vec::new(),
+            ));
+        } else {
+            match blocks.last_mut() {
+                Some((_, lines)) => lines.push(line),
+                None => blocks.push((String::new(), vec![line])),
+            }
+        }
+    }
+    blocks
+        .into_iter()
+        .map(|(result, lines)| ResultMessage {
+            outcome: outcome(&lines, &credit_regex),
+            result,
+        })
+        .filter(|message| !(message.result.is_empty() && message.outcome == ResultOutcome::Unknown))
+        .collect()
+}
+
+// Phrases Primenet uses on the pages it shows instead of the one asked for, lower case. These
+// haven't been checked against pages saved from Primenet yet (see tests/fixtures/primenet/README),
+// so anything they miss comes out as NoAssignmentsReason::Unknown, which is only logged.
+const MAINTENANCE_PHRASES: &[&str] = &[
+    "down for maintenance",
+    "undergoing maintenance",
+    "temporarily unavailable",
+    "server is busy",
+];
+const LIMIT_PHRASES: &[&str] = &[
+    "maximum number of assignments",
+    "assignment limit",
+    "too many assignments",
+];
+const RANGE_EMPTY_PHRASES: &[&str] = &[
+    "in the range you requested",
+    "in the requested range",
+    "exponent range",
+    "between exponents",
+];
+const NO_WORK_PHRASES: &[&str] = &[
+    "no assignments available",
+    "no work available",
+    "no exponents available",
+    "no assignments of this type",
+];
+
+fn mentions(lines: &[String], phrases: &[&str]) -> bool {
+    lines.iter().any(|line| {
+        let line = line.to_lowercase();
+        phrases.iter().any(|phrase| line.contains(phrase))
+    })
+}
+
+// Whether the page is Primenet saying it isn't taking requests right now.
+pub fn under_maintenance(html: &str) -> bool {
+    mentions(&page_lines(html), MAINTENANCE_PHRASES)
+}
+
+// Why a manual assignment page came back without any assignments.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NoAssignmentsReason {
+    // Nothing of the requested work type left to hand out.
+    NoWork,
+    // The account already has as many assignments as it's allowed.
+    LimitReached,
+    // Nothing left between the requested lowest and highest exponents.
+    RangeEmpty,
+    LoggedOut,
+    Maintenance,
+    Unknown,
+}
+
+pub fn no_assignments_reason(html: &str) -> NoAssignmentsReason {
+    if login_state(html) == LoginState::LoggedOut {
+        return NoAssignmentsReason::LoggedOut;
+    }
+    let lines = page_lines(html);
+    if mentions(&lines, MAINTENANCE_PHRASES) {
+        NoAssignmentsReason::Maintenance
+    } else if mentions(&lines, LIMIT_PHRASES) {
+        NoAssignmentsReason::LimitReached
+    } else if mentions(&lines, RANGE_EMPTY_PHRASES) {
+        // Range messages also say there are no exponents available, so range is checked before
+        // NO_WORK_PHRASES.
+        NoAssignmentsReason::RangeEmpty
+    } else if mentions(&lines, NO_WORK_PHRASES) {
+        NoAssignmentsReason::NoWork
+    } else {
+        NoAssignmentsReason::Unknown
+    }
+}
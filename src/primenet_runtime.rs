@@ -1,359 +1,901 @@
 use crate::{
-    clap_handler::{
-        app::{GeneralOptions, PrimenetOptions},
-        p95_work::PrimenetWorkType,
+    activity::{record_fetch, record_submit, WorkSource},
+    assignment::{Assignment, ExponentRange},
+    credit::DaysOfWork,
+    dry_run::DryRun,
+    endpoints::Endpoints,
+    error::Error,
+    events::{emit, output, Event, SubmissionOutcome},
+    logging::{LOCKING, NETWORK, PARSING},
+    options::{GeneralOptions, PrimenetOptions, ProofOptions},
+    p95_work::PrimenetWorkType,
+    primenet_pages::{
+        assignments, login_state, no_assignments_reason, submission_results, under_maintenance,
+        LoginState, NoAssignmentsReason, ResultOutcome,
     },
+    proof_upload::upload_proofs,
+    retry::log_cycle_summary,
+    submission_queue::SubmissionQueue,
+    throughput::{days_of_work_with, measure_throughput},
+    transport::{Request, ReqwestTransport, Transport},
     util::*,
+    work_directory::{parse_assignments, WorkDirectory},
+    work_result::WorkResult,
 };
-use regex::RegexBuilder;
-use reqwest::blocking::{Client, ClientBuilder};
-use std::fs::{remove_file, File, OpenOptions};
-use std::io::{BufWriter, Write};
-use std::path::Path;
-use std::str::from_utf8;
+use chrono::{DateTime, Utc};
+use log::{debug, error, info, trace, warn};
 use std::thread::sleep;
 use std::time::{Duration, Instant};
 
-// Work validation regex
-const WVR: &str = r"((DoubleCheck|Test|PRP)\s*=\s*([0-9A-F]){32}(,[0-9]+){3}((,-?[0-9]+){3,5})?)$";
-
-const P95_LOGIN_ADDR: &str = "https://www.mersenne.org/";
-const P95_REQUEST_ADDR: &str = "https://www.mersenne.org/manual_assignment/?";
-const P95_REPORT_ADDR: &str = "https://www.mersenne.org/manual_result/?";
+fn session_expired(response_text: &str) -> bool {
+    login_state(response_text) == LoginState::LoggedOut
+}
 
-pub fn primenet_login(client: &Client, username: &str, password: &str) -> Result<(), String> {
-    let result = client
-        .post(P95_LOGIN_ADDR)
-        .form(&[("user_login", username), ("user_password", password)])
-        .send()
-        .map_err(|e| format!("Failed to send login attempt to Primenet. Error: {}", e))?;
-    let status = result.status().as_u16();
+pub fn primenet_login(
+    transport: &dyn Transport,
+    endpoints: &Endpoints,
+    username: &str,
+    password: &str,
+) -> Result<(), Error> {
+    let login_addr = endpoints.primenet_login();
+    debug!(target: NETWORK, "POST {} (login as '{}')", login_addr, username);
+    let request =
+        Request::post(&login_addr).form(&[("user_login", username), ("user_password", password)]);
+    let result = transport
+        .send(&request)
+        .map_err(|e| Error::network("Failed to send login attempt to Primenet.", e))?;
+    let status = result.status;
+    let url = result.url;
+    let result_text = result.body;
+    debug!(target: NETWORK, "Login response from {}: status {}", url, status);
+    trace!(target: NETWORK, "Login response body: {}", result_text);
     if status == 200 {
-        let url = result.url().clone();
-        let result_text = result
-            .text()
-            .map_err(|e| format!("Failed to read response text. Error: {}", e))?;
-        if result_text.contains(&format!("{}<br>logged in", username)) {
-            Ok(())
-        } else {
-            println!("Failed to log in to Primenet.");
-            println!("Login URL: {}", url);
-            println!("Request status code: {}", status);
-            println!("Login response: {}", result_text);
-            Err("Login failed.".to_string())
+        match login_state(&result_text) {
+            state if state.is_logged_in_as(username) => Ok(()),
+            LoginState::LoggedIn(other) => Err(Error::Authentication(format!(
+                "Primenet reported user '{}' as logged in instead of '{}'.",
+                other, username
+            ))),
+            _ => {
+                debug!(target: PARSING, "Login response: {}", result_text);
+                Err(Error::Authentication(format!(
+                    "Primenet did not report user '{}' as logged in.",
+                    username
+                )))
+            }
         }
     } else {
-        println!(
-            "Login attempt at address '{}' returned bad status: {}",
-            result.url(),
-            status
-        );
-        println!("Failure response: {:?}", result.text());
-        Err("Login failed.".to_string())
+        Err(Error::HttpStatus {
+            url,
+            status,
+            body: result_text,
+        })
     }
 }
 
-fn primenet_request(
-    client: &Client,
+// Whether the transport is still logged in as `username` from an earlier run. One GET of the front
+// page, which is a lot cheaper for Primenet than a fresh login.
+pub fn primenet_session_valid(
+    transport: &dyn Transport,
+    endpoints: &Endpoints,
+    username: &str,
+) -> Result<bool, Error> {
+    let check_addr = endpoints.primenet_login();
+    debug!(target: NETWORK, "GET {} (checking saved session)", check_addr);
+    let response = transport
+        .send(&Request::get(&check_addr))
+        .map_err(|e| Error::network("Failed to check Primenet session.", e))?;
+    trace!(target: NETWORK, "Session check response body: {}", response.body);
+    Ok(response.status == 200 && login_state(&response.body).is_logged_in_as(username))
+}
+
+// Log in, unless the transport's saved session is still good.
+pub fn primenet_ensure_login(
+    transport: &dyn Transport,
+    endpoints: &Endpoints,
+    username: &str,
+    password: &str,
+) -> Result<(), Error> {
+    if primenet_session_valid(transport, endpoints, username)? {
+        info!("Reusing saved Primenet session.");
+    } else {
+        primenet_login(transport, endpoints, username, password)?;
+        info!("Successfully logged into Primenet.");
+    }
+    Ok(())
+}
+
+// Run a step that needs a Primenet session. If the session turns out to have expired, log in again
+// and give the step one more go.
+pub fn with_primenet_session<T, F: FnMut() -> Result<T, Error>>(
+    transport: &dyn Transport,
+    endpoints: &Endpoints,
+    username: &str,
+    password: &str,
+    mut step: F,
+) -> Result<T, Error> {
+    match step() {
+        Err(Error::Authentication(reason)) => {
+            info!("{} Logging into Primenet again.", reason);
+            primenet_login(transport, endpoints, username, password)?;
+            step()
+        }
+        result => result,
+    }
+}
+
+// Assignment lines worth caching out of a batch that's just been handed out. The rest are
+// quarantined, since whoever handed them out still has them reserved for us and they're worth
+// unreserving by hand. Only safe to call with the worktodo locked.
+pub fn validate_fetched(
+    work_directory: &WorkDirectory,
+    fetched: Vec<Assignment>,
+    exponent_range: ExponentRange,
+) -> Result<Vec<String>, Error> {
+    let valid =
+        work_directory.quarantine_invalid(work_directory.runnable(fetched), exponent_range)?;
+    Ok(valid
+        .into_iter()
+        .map(|assignment| assignment.line)
+        .collect())
+}
+
+// What came of asking Primenet for work.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WorkRequestOutcome {
+    // Enough assignments were cached already, so nothing was asked for.
+    AlreadyCached,
+    Cached(usize),
+    // Primenet answered, but without any assignments.
+    NoAssignments(NoAssignmentsReason),
+}
+
+// The request for `num_to_get` assignments of `work_info`.
+pub fn primenet_work_request(
+    endpoints: &Endpoints,
+    num_to_get: usize,
+    work_info: PrimenetWorkType,
+    cores: usize,
+    exponent_range: ExponentRange,
+) -> Request {
+    // Primenet takes an empty bound to mean no bound.
+    let bound = |bound: Option<u64>| bound.map(|n| n.to_string()).unwrap_or_default();
+    Request::get(&endpoints.primenet_request()).query(&[
+        ("cores", &cores.to_string()),
+        ("num_to_get", &num_to_get.to_string()),
+        ("pref", work_info.as_str()),
+        ("exp_lo", &bound(exponent_range.min)),
+        ("exp_hi", &bound(exponent_range.max)),
+        ("B1", "Get+Assignments"),
+    ])
+}
+
+pub fn primenet_request(
+    transport: &dyn Transport,
+    endpoints: &Endpoints,
     num_to_cache: usize,
-    worktodo_path: &Path,
-    worktodo_lock_path: &Path,
+    work_directory: &WorkDirectory,
     work_info: PrimenetWorkType,
-) -> Result<(), String> {
+    cores: usize,
+    exponent_range: ExponentRange,
+) -> Result<WorkRequestOutcome, Error> {
+    let worktodo_path = &work_directory.worktodo;
+    let worktodo_lock_path = &work_directory.worktodo_lock;
     while worktodo_lock_path.exists() {
+        debug!(target: LOCKING, "Waiting for {} to be unlocked.", worktodo_path.display());
         sleep(Duration::from_secs(1));
     }
-    let workfile_contents = read_list_lock(worktodo_path, worktodo_lock_path)
-        .map_err(|e| format!("Failed to read worktodo file. Error: {}", e))?;
-    if num_to_cache <= workfile_contents.len() {
-        println!(
+    // Nothing new gets written to the worktodo file until we have validated jobs, so on any error
+    // before then it's enough to just drop the lock.
+    let unlock_on_err = |e: Error| {
+        let _ = unlock_file(worktodo_lock_path);
+        e
+    };
+    let worktodo = read_list_lock(worktodo_path, worktodo_lock_path)?;
+    let worktodo = work_directory
+        .check_worktodo(worktodo)
+        .map_err(unlock_on_err)?;
+    let cached = parse_assignments(&worktodo).len();
+    if num_to_cache <= cached {
+        info!(
             "Already have {} assignment(s) cached of the requested {}. Not requesting more.",
-            workfile_contents.len(),
-            num_to_cache
+            cached, num_to_cache
         );
-        unlock_file(worktodo_lock_path)
-            .map_err(|e| format!("Failed to unlock worktodo file. Error: {}", e))
+        unlock_file(worktodo_lock_path)?;
+        Ok(WorkRequestOutcome::AlreadyCached)
+    } else {
+        let num_to_get = num_to_cache - cached;
+        let request =
+            primenet_work_request(endpoints, num_to_get, work_info, cores, exponent_range);
+        debug!(
+            target: NETWORK,
+            "GET {} (num_to_get={}, pref={}, cores={}, exponents={})",
+            request.url,
+            num_to_get,
+            work_info.as_str(),
+            cores,
+            exponent_range
+        );
+        let response = transport
+            .send(&request)
+            .map_err(|e| Error::network("Failed to make work request to Primenet.", e))
+            .map_err(unlock_on_err)?;
+        let status = response.status;
+        let url = response.url;
+        let response_text = response.body;
+        debug!(target: NETWORK, "Work request response from {}: status {}", url, status);
+        trace!(target: NETWORK, "Work request response body: {}", response_text);
+        if status != 200 {
+            unlock_file(worktodo_lock_path)?;
+            return Err(Error::HttpStatus {
+                url,
+                status,
+                body: response_text,
+            });
+        }
+        let fetched = assignments(&response_text);
+        if fetched.is_empty() {
+            unlock_file(worktodo_lock_path)?;
+            return match no_assignments_reason(&response_text) {
+                NoAssignmentsReason::LoggedOut => Err(Error::Authentication(
+                    "Primenet session expired before requesting work.".to_string(),
+                )),
+                NoAssignmentsReason::Maintenance => Err(Error::Unavailable(
+                    "Primenet is down for maintenance.".to_string(),
+                )),
+                reason => Ok(WorkRequestOutcome::NoAssignments(reason)),
+            };
+        }
+        let validated_jobs =
+            validate_fetched(work_directory, fetched, exponent_range).map_err(unlock_on_err)?;
+        debug!(target: PARSING, "Validated jobs: {:?}", validated_jobs);
+        append_jobs(worktodo_path, &validated_jobs).map_err(unlock_on_err)?;
+        info!(
+            "Cached {} new assignment(s) in {}.",
+            validated_jobs.len(),
+            worktodo_path.display()
+        );
+        record_fetch(
+            work_directory,
+            WorkSource::Primenet,
+            &worktodo,
+            &validated_jobs,
+            Utc::now(),
+        );
+        emit(Event::fetch(
+            WorkSource::Primenet,
+            work_directory,
+            &parse_assignments(&validated_jobs),
+        ));
+        // Everything should be written to the file now, so we should be safe not to include it in
+        // the error message.
+        unlock_file(worktodo_lock_path)?;
+        Ok(WorkRequestOutcome::Cached(validated_jobs.len()))
+    }
+}
+
+pub fn primenet_submit(
+    transport: &dyn Transport,
+    endpoints: &Endpoints,
+    work_directory: &WorkDirectory,
+) -> Result<(), Error> {
+    let lockfile_paths = work_directory.lockfile_paths();
+    lock_all(&lockfile_paths)?;
+    let submission = submit_locked(transport, endpoints, work_directory);
+    // Locks are released whether or not submission worked, but a submission error takes priority.
+    let unlock = unlock_all(&lockfile_paths);
+    submission.and(unlock)
+}
+
+fn submit_result(
+    transport: &dyn Transport,
+    report_addr: &str,
+    result: &str,
+) -> Result<ResultOutcome, Error> {
+    debug!(target: NETWORK, "POST {}", report_addr);
+    let response_text = transport
+        .send(&Request::post(report_addr).form(&[("data", result)]))
+        .map_err(|e| Error::network("Failed to send work submission to Primenet.", e))?
+        .body;
+    trace!(target: NETWORK, "Submission response body: {}", response_text);
+    if session_expired(&response_text) {
+        return Err(Error::Authentication(
+            "Primenet session expired before submitting results.".to_string(),
+        ));
+    }
+    // Only one result is sent at a time, but if Primenet echoed it differently, take whatever it
+    // said about the first result it processed.
+    let messages = submission_results(&response_text);
+    debug!(target: PARSING, "Submission messages: {:?}", messages);
+    // A page that says anything about a result is an answer to it, whatever else is on it. Only a
+    // page without any is Primenet turning every request away.
+    if messages.is_empty() && under_maintenance(&response_text) {
+        return Err(Error::Unavailable(
+            "Primenet is down for maintenance.".to_string(),
+        ));
+    }
+    let message = messages
+        .iter()
+        .find(|message| message.result == result)
+        .or_else(|| messages.iter().find(|message| !message.result.is_empty()))
+        .or_else(|| messages.first());
+    Ok(message.map_or(ResultOutcome::Unknown, |message| message.outcome.clone()))
+}
+
+// Where the lines of results.txt go on the way to Primenet.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SortedResults {
+    // Lines that stay in results.txt for the next pass.
+    pub unsent: Vec<String>,
+    // Results that don't check out, with why not.
+    pub quarantined: Vec<(WorkResult, String)>,
+    // How many results were added to the queue.
+    pub queued: usize,
+}
+
+// Move completed jobs from results.txt onto the queue. Anything that's already been sent is
+// dropped rather than sent again, and anything that made it into results.sent comes off the queue,
+// since it was accepted even if we stopped before taking it off. Nothing is written.
+pub fn sort_results(
+    work_directory: &WorkDirectory,
+    worktodo: &[String],
+    results: Vec<String>,
+    results_sent: &[String],
+    queue: &mut SubmissionQueue,
+    now: DateTime<Utc>,
+) -> SortedResults {
+    queue
+        .entries
+        .retain(|entry| !results_sent.contains(&entry.result));
+    let results_format = work_directory.client.map(|client| client.results_format());
+    let mut sorted = SortedResults::default();
+    for line in results {
+        if line.is_empty() || results_sent.contains(&line) {
+            continue;
+        }
+        if worktodo.contains(&line) {
+            debug!(target: PARSING, "Found incomplete job in results.txt: {}", line);
+            sorted.unsent.push(line);
+        } else if !results_format.map_or(true, |format| format.accepts(&line)) {
+            debug!(target: PARSING, "Not submitting results line in the wrong format: {}", line);
+            sorted.unsent.push(line);
+        } else {
+            match WorkResult::parse(&line) {
+                Err(e) => {
+                    debug!(target: PARSING, "Not submitting results line. {}", e);
+                    sorted.unsent.push(line);
+                }
+                Ok(result) => match result.verify_factor() {
+                    Err(problem) => sorted.quarantined.push((result, problem.to_string())),
+                    Ok(()) => {
+                        if queue.push(line, now) {
+                            sorted.queued += 1;
+                        }
+                    }
+                },
+            }
+        }
+    }
+    sorted
+}
+
+fn submit_locked(
+    transport: &dyn Transport,
+    endpoints: &Endpoints,
+    work_directory: &WorkDirectory,
+) -> Result<(), Error> {
+    let now = Utc::now();
+    let worktodo_contents = read_list(&work_directory.worktodo)?;
+    let results_contents = read_list(&work_directory.results)?;
+    let results_sent_contents = if work_directory.results_sent.exists() {
+        read_list(&work_directory.results_sent)?
     } else {
-        let worktype = work_info.as_str();
-        let num_to_get = format!("{}", num_to_cache - workfile_contents.len());
-        let response = client
-            .get(P95_REQUEST_ADDR)
-            .query(&[
-                ("cores", "1"),
-                ("num_to_get", &num_to_get),
-                ("pref", worktype),
-                ("exp_lo", ""),
-                ("exp_hi", ""),
-                ("B1", "Get+Assignments"),
-            ])
-            .send()
-            .map_err(|e| format!("Failed to make work request to Primenet. Error: {}", e))?;
-        let status = response.status().as_u16();
-        let response_text = response
-            .text()
-            .map_err(|e| format!("Failed to read response text from Primenet. Error: {}", e))?;
-        if status == 200 {
-            println!("Got work request response.");
-            let work_validation_regex = RegexBuilder::new(WVR)
-                .multi_line(true)
-                .build()
-                .expect("Failed to build regex for task validation");
-            let validated_jobs = work_validation_regex
-                .captures_iter(&response_text)
-                .map(|captures| captures[0].to_string())
-                .collect::<Vec<_>>();
-            println!("Validated jobs: {:?}", validated_jobs);
-            if validated_jobs.len() == 0 {
-                println!("WARNING!");
-                println!(
-                    "Received work request response but failed to find any valid jobs in it. You \n\
-                    may want to check your Primenet account to see if any work has been \n\
-                    reserved, and if so, add it to your worktodo file manually."
+        Vec::new()
+    };
+    let mut queue = SubmissionQueue::load(&work_directory.queue)?;
+    let sorted = sort_results(
+        work_directory,
+        &worktodo_contents,
+        results_contents,
+        &results_sent_contents,
+        &mut queue,
+        now,
+    );
+    let mut quarantined = Vec::new();
+    for (result, problem) in sorted.quarantined {
+        error!(
+            "Not submitting '{}', since {}. It's been moved to {}.",
+            result.line,
+            problem,
+            work_directory.results_quarantine.display()
+        );
+        let reason = format!("M{}: {}", result.exponent, problem);
+        emit(Event::submission(
+            work_directory,
+            &result.line,
+            SubmissionOutcome::Quarantined,
+            Some(reason.clone()),
+            None,
+        ));
+        quarantined.push((result.line, reason));
+    }
+    // The queue and the quarantine have to be on disk before the results are taken out of
+    // results.txt.
+    queue.save(&work_directory.queue)?;
+    append_quarantine(&work_directory.results_quarantine, &quarantined, now)?;
+    write_list(&work_directory.results, &sorted.unsent)?;
+    if sorted.queued > 0 {
+        debug!("Queued {} new result(s) for submission.", sorted.queued);
+    }
+    let report_addr = endpoints.primenet_report();
+    let mut sent = Vec::new();
+    let mut rejected = Vec::new();
+    let mut stop_error = None;
+    let mut credited = 0.0;
+    for result in queue.due(now) {
+        match submit_result(transport, &report_addr, &result) {
+            Ok(ResultOutcome::Accepted { ghz_days }) => {
+                match ghz_days {
+                    Some(ghz_days) => {
+                        info!(
+                            "Primenet accepted result: {} ({} GHz-days credited)",
+                            result, ghz_days
+                        );
+                        credited += ghz_days;
+                    }
+                    None => info!("Primenet accepted result: {}", result),
+                }
+                emit(Event::submission(
+                    work_directory,
+                    &result,
+                    SubmissionOutcome::Accepted,
+                    None,
+                    ghz_days,
+                ));
+                queue.remove(&result);
+                sent.push(result);
+            }
+            // Sending it again would only get the same answer, so it comes off the queue.
+            Ok(ResultOutcome::Rejected(message)) => {
+                error!(
+                    "Primenet rejected '{}': {}. It's been moved to {}.",
+                    result,
+                    message,
+                    work_directory.results_quarantine.display()
                 );
-                return Ok(());
+                emit(Event::submission(
+                    work_directory,
+                    &result,
+                    SubmissionOutcome::Rejected,
+                    Some(message.clone()),
+                    None,
+                ));
+                queue.remove(&result);
+                rejected.push((result, format!("Rejected by Primenet: {}", message)));
             }
-            // On any errors below until everything is written, show what hasn't yet been written and
-            // ask the user to add it themselves.
-            let mut list_file = BufWriter::new(
-                OpenOptions::new()
-                    .append(true)
-                    .open(worktodo_path)
-                    .map_err(|e| {
-                        error_msg_with_jobs(e, "Failed to open worktodo file.", &validated_jobs)
-                    })?,
-            );
-            for i in 0..validated_jobs.len() {
-                list_file
-                    .write_all(validated_jobs[i].as_bytes())
-                    .map_err(|e| {
-                        error_msg_with_jobs(
-                            e,
-                            "Failed to write to worktodo file.",
-                            &validated_jobs[i..],
-                        )
-                    })?;
-                list_file.write_all(&[b'\n']).map_err(|e| {
-                    error_msg_with_jobs(
-                        e,
-                        "Failed to write to worktodo file.",
-                        &validated_jobs[i..],
-                    )
-                })?;
+            Ok(ResultOutcome::Unknown) => {
+                warn!(
+                    "Submission of '{}' failed for reasons unknown. It stays queued and will be \
+                    retried later.",
+                    result
+                );
+                let message = "Unrecognised response from Primenet".to_string();
+                emit(Event::submission(
+                    work_directory,
+                    &result,
+                    SubmissionOutcome::Unknown,
+                    Some(message.clone()),
+                    None,
+                ));
+                queue.record_failure(&result, message, now);
             }
-            list_file.flush().map_err(|e| {
-                error_msg_with_unwritten(
-                    e,
-                    "Failed to flush buffered reader to worktodo file.",
-                    from_utf8(list_file.buffer()).unwrap(),
-                )
-            })?;
-            // Everything should be written to the file now, so we should be safe not to include it in
-            // the error message.
-            remove_file(worktodo_lock_path).map_err(|e| {
-                format!(
-                    "Failed to remove lockfile after writing new jobs to it. Error: {}",
-                    e
-                )
-            })?;
-        } else {
-            println!("Failed to request work from Primenet. Status: {}", status);
-            println!("Response text: {}", response_text);
+            // Not the result's fault, so it doesn't count as an attempt.
+            Err(e @ Error::Authentication(_)) | Err(e @ Error::Unavailable(_)) => {
+                stop_error = Some(e);
+                break;
+            }
+            // If the server has gone away there's no point trying the rest now.
+            Err(e) => {
+                emit(Event::submission(
+                    work_directory,
+                    &result,
+                    SubmissionOutcome::Failed,
+                    Some(e.to_string()),
+                    None,
+                ));
+                queue.record_failure(&result, e.to_string(), now);
+                stop_error = Some(e);
+                break;
+            }
+        }
+    }
+    // Sent and rejected results are recorded before they come off the queue on disk, so a crash in
+    // between can't lose one.
+    append_list(&work_directory.results_sent, &sent)?;
+    append_quarantine(&work_directory.results_quarantine, &rejected, now)?;
+    queue.save(&work_directory.queue)?;
+    if !sent.is_empty() {
+        record_submit(work_directory, Utc::now());
+    }
+    if sent.len() > 1 && credited > 0.0 {
+        info!(
+            "Primenet credited {:.4} GHz-days for {} result(s).",
+            credited,
+            sent.len()
+        );
+    }
+    if !queue.is_empty() {
+        info!(
+            "{} result(s) waiting in the submission queue. Use the queue subcommand for details.",
+            queue.len()
+        );
+    }
+    match stop_error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+// Log how far along each cached assignment is, for clients whose checkpoints we can read. The
+// worktodo file isn't locked for this, so it's skipped while someone else has it.
+pub fn log_progress(work_directory: &WorkDirectory) {
+    if work_directory.client.is_none() {
+        return;
+    }
+    let worktodo = match read_nolock(&work_directory.worktodo, &work_directory.worktodo_lock) {
+        Ok(worktodo) => worktodo,
+        Err(e) => return debug!("Not checking progress. {}", e),
+    };
+    let lines = worktodo.lines().map(str::to_string).collect::<Vec<_>>();
+    for assignment in parse_assignments(&lines) {
+        if let Some(progress) = work_directory.progress(&assignment) {
+            info!("{}", progress);
         }
-        Ok(())
     }
 }
 
-fn writeback_on_failure(results_bufwriter: &mut BufWriter<File>, unsent_result: String) {
-    // If submission fails, write the result back to the results file.
+// Read the cached assignments once nobody else has the worktodo file locked.
+fn cached_assignments(work_directory: &WorkDirectory) -> Result<Vec<Assignment>, Error> {
+    while work_directory.worktodo_lock.exists() {
+        debug!(
+            target: LOCKING,
+            "Waiting for {} to be unlocked.",
+            work_directory.worktodo.display()
+        );
+        sleep(Duration::from_secs(1));
+    }
+    let lines = if work_directory.worktodo.exists() {
+        read_list(&work_directory.worktodo)?
+    } else {
+        Vec::new()
+    };
+    Ok(parse_assignments(&lines))
 }
 
-pub fn primenet_submit(
-    client: &Client,
-    worktodo_path: &Path,
-    worktodo_lock_path: &Path,
-    results_path: &Path,
-    results_lock_path: &Path,
-    results_sent_path: &Path,
-    results_sent_lock_path: &Path,
-) -> Result<(), String> {
-    let worktodo_contents = read_list_lock(worktodo_path, worktodo_lock_path)
-        .map_err(|e| format!("Could not lock and read worktodo file. Error: {}", e))?;
-    let mut results_contents = read_list_lock(results_path, results_lock_path)
-        .map_err(|e| format!("Could not lock and read results file. Error: {}", e))?;
-    lock_file(results_sent_lock_path)?;
-    let mut results_file = BufWriter::new(
-        OpenOptions::new()
-            .write(true)
-            .append(false)
-            .open(results_path)
-            .map_err(|e| {
-                format!(
-                    "Failed to open results file with write privileges. Error: {}",
-                    e
-                )
-            })?,
-    );
-    let mut results_sent_file = BufWriter::new(
-        OpenOptions::new()
-            .write(true)
-            .append(true)
-            .open(results_sent_path)
-            .map_err(|e| {
-                format!(
-                    "Failed to open sent results file with write privileges. Error: {}",
-                    e
-                )
-            })?,
-    );
-    let mut collisions = Vec::new();
-    // Only jobs that are completed are allowed to be submitted.
-    // Could handle this with hashset collisions, but then I have to sort out ordering when writing
-    // back to the file on submission errors. Resolve this.
-    for job in &worktodo_contents {
-        if let Some(pos) = results_contents.iter().position(|j| j == job) {
-            collisions.push(results_contents.remove(pos));
+// Measure the work directory's throughput, save it and return the rolling average. The runtimes
+// do this every cycle whether or not --days-of-work is set: checkpoint samples need progress seen
+// at intervals, and status uses the saved throughput for its ETAs. Failing to is no reason to
+// stop, since it's only used for estimates.
+pub fn update_throughput(work_directory: &WorkDirectory) -> Option<f64> {
+    match measure_throughput(work_directory) {
+        Ok(throughput) => throughput.ghz_days_per_day(),
+        Err(e) => {
+            warn!("Failed to measure throughput. {}", e);
+            None
         }
     }
-    println!("Found the following incomplete jobs in results.txt:");
-    for collision in collisions {
-        println!("    {}", collision);
+}
+
+// Most work requests made in one go to top up a --days-of-work cache.
+pub const MAX_TOP_UP_REQUESTS: usize = 10;
+
+// Call `request` with the number of assignments to have cached. That's `num_cache` unless a number
+// of days of work is wanted, in which case it's asked again with an updated estimate until the
+// cache covers the days, nothing more arrives or the server says to back off.
+pub fn top_up<F>(
+    work_directory: &WorkDirectory,
+    num_cache: usize,
+    days_of_work: Option<DaysOfWork>,
+    mut request: F,
+) -> Result<Option<Duration>, Error>
+where
+    F: FnMut(usize) -> Result<Option<Duration>, Error>,
+{
+    let days_of_work = match days_of_work {
+        Some(days_of_work) => days_of_work,
+        None => return request(num_cache),
+    };
+    let mut cached = cached_assignments(work_directory)?;
+    for _ in 0..MAX_TOP_UP_REQUESTS {
+        let num_to_cache = match days_of_work.assignments_to_cache(&cached) {
+            Some(num_to_cache) => num_to_cache,
+            None => break,
+        };
+        info!(
+            "Have about {:.1} day(s) of work cached of the requested {}. Topping up to {} \
+            assignment(s).",
+            days_of_work.days_cached(&cached),
+            days_of_work.days,
+            num_to_cache
+        );
+        let backoff = request(num_to_cache)?;
+        if backoff.is_some() {
+            return Ok(backoff);
+        }
+        let now_cached = cached_assignments(work_directory)?;
+        if now_cached.len() <= cached.len() {
+            return Ok(None);
+        }
+        cached = now_cached;
     }
-    if results_contents.len() > 0 {
-        while let Some(completed_job) = results_contents.pop() {
-            let response_text = client
-                .post(P95_REPORT_ADDR)
-                .form(&[("data", "completed_job")])
-                .send()
-                .map_err(|e| format!("Failed to send work submission to primenet. Error: {}", e))?
-                .text()
-                .map_err(|e| {
-                    format!(
-                        "Failed to read response text from work submission to Primenet. Error: {}",
-                        e
-                    )
-                })?;
-            if response_text.contains("Error") {
-                let e_start = response_text.find("Error").unwrap();
-                let e_end = response_text[e_start..].find("</div>").unwrap();
-                println!(
-                    "Submission failed. Error message from Primenet: {}",
-                    &response_text[e_start..e_end]
+    info!(
+        "Have about {:.1} day(s) of work cached of the requested {}.",
+        days_of_work.days_cached(&cached),
+        days_of_work.days
+    );
+    Ok(None)
+}
+
+// How long to leave Primenet alone after it turns down a work request.
+pub const NO_WORK_BACKOFF: Duration = Duration::from_secs(60 * 60);
+pub const LIMIT_BACKOFF: Duration = Duration::from_secs(6 * 60 * 60);
+pub const MAINTENANCE_BACKOFF: Duration = Duration::from_secs(30 * 60);
+
+// Request work, switching to the fallback work type if Primenet has none of the chosen type left.
+// Returns how long to wait before asking again, if Primenet turned us down.
+#[allow(clippy::too_many_arguments)]
+pub fn request_with_fallback(
+    transport: &dyn Transport,
+    endpoints: &Endpoints,
+    num_to_cache: usize,
+    work_directory: &WorkDirectory,
+    work_type: PrimenetWorkType,
+    fallback_work_type: Option<PrimenetWorkType>,
+    cores: usize,
+    exponent_range: ExponentRange,
+) -> Result<Option<Duration>, Error> {
+    let mut work_type = work_type;
+    loop {
+        let reason = match primenet_request(
+            transport,
+            endpoints,
+            num_to_cache,
+            work_directory,
+            work_type,
+            cores,
+            exponent_range,
+        ) {
+            Ok(WorkRequestOutcome::NoAssignments(reason)) => reason,
+            Ok(_) => return Ok(None),
+            Err(Error::Unavailable(msg)) => {
+                warn!(
+                    "{} Not asking for work again for {} minutes.",
+                    msg,
+                    MAINTENANCE_BACKOFF.as_secs() / 60
                 );
-                writeback_on_failure(&mut results_file, completed_job);
-            } else if response_text.contains("Accepted") {
-                // Submission was accepted by Primenet - write result to results.sent.txt
-            } else {
-                // Unknown failure case - write failed submission back to results.txt
+                return Ok(Some(MAINTENANCE_BACKOFF));
+            }
+            Err(e) => return Err(e),
+        };
+        match (reason, fallback_work_type) {
+            (NoAssignmentsReason::NoWork, Some(fallback))
+            | (NoAssignmentsReason::RangeEmpty, Some(fallback))
+                if fallback != work_type =>
+            {
+                warn!(
+                    "Primenet has no {} work available. Requesting {} work instead.",
+                    work_type, fallback
+                );
+                work_type = fallback;
+            }
+            (NoAssignmentsReason::NoWork, _) => {
+                warn!(
+                    "Primenet has no {} work available. Not asking again for {} minutes.",
+                    work_type,
+                    NO_WORK_BACKOFF.as_secs() / 60
+                );
+                return Ok(Some(NO_WORK_BACKOFF));
+            }
+            (NoAssignmentsReason::RangeEmpty, _) => {
+                warn!(
+                    "Primenet has no {} work left in the requested exponent range. Not asking \
+                    again for {} minutes.",
+                    work_type,
+                    NO_WORK_BACKOFF.as_secs() / 60
+                );
+                return Ok(Some(NO_WORK_BACKOFF));
+            }
+            (NoAssignmentsReason::LimitReached, _) => {
+                warn!(
+                    "This account has reached Primenet's assignment limit. Not asking for more \
+                    work for {} hours.",
+                    LIMIT_BACKOFF.as_secs() / 60 / 60
+                );
+                return Ok(Some(LIMIT_BACKOFF));
+            }
+            _ => {
+                warn!(
+                    "Received work request response but failed to find any valid jobs in it, or \
+                    a reason why there weren't any. Running with --record saves the page so it \
+                    can be looked at."
+                );
+                return Ok(None);
             }
         }
     }
-    unlock_file(worktodo_lock_path).map_err(|e| {
-        format!(
-            "Could not remove lockfile {}. Error: {}",
-            worktodo_lock_path.display(),
-            e
-        )
-    })?;
-    unlock_file(results_lock_path).map_err(|e| {
-        format!(
-            "Could not remove lockfile {}. Error: {}",
-            results_lock_path.display(),
-            e
-        )
-    })?;
-    unlock_file(results_sent_lock_path).map_err(|e| {
-        format!(
-            "Could not remove lockfile {}. Error: {}",
-            results_sent_lock_path.display(),
-            e
-        )
-    })?;
-    Ok(())
 }
 
-pub fn primenet_runtime(primenet_options: PrimenetOptions) -> Result<(), String> {
+pub fn primenet_runtime(primenet_options: PrimenetOptions) -> Result<(), Error> {
+    primenet_runtime_with_transport(&ReqwestTransport::new()?, primenet_options)
+}
+
+pub fn primenet_runtime_with_transport(
+    transport: &dyn Transport,
+    primenet_options: PrimenetOptions,
+) -> Result<(), Error> {
     let PrimenetOptions {
         credentials: (username, password),
         work_type,
+        fallback_work_type,
+        cores,
+        proofs,
         general_options:
             GeneralOptions {
                 work_directory,
                 num_cache,
+                days_of_work,
+                throughput,
                 timeout,
+                endpoints,
+                exponent_range,
+                client,
             },
     } = primenet_options;
-    let client = ClientBuilder::default()
-        .cookie_store(true)
-        .build()
-        .map_err(|e| format!("Failed to build web client. Error: {}", e))?;
-    primenet_login(&client, &username, &password)?;
-    println!("Successfully logged into Primenet.");
-    let worktodo_txt_path = Path::new(&work_directory).join(Path::new("worktodo.txt"));
-    let worktodo_ini_path = Path::new(&work_directory).join(Path::new("worktodo.ini"));
-    let (worktodo_path, worktodo_lock_path) = if worktodo_txt_path.exists() {
-        (worktodo_txt_path, worktodo_txt_path.join(".lck"))
-    } else {
-        (worktodo_ini_path, worktodo_ini_path.join(".lck"))
+    primenet_ensure_login(transport, &endpoints, &username, &password)?;
+    let work_directory = WorkDirectory::from_options(&work_directory, client);
+    debug!("Using worktodo path: {}", work_directory.worktodo.display());
+    debug!(
+        "Using worktodo_lock path: {}",
+        work_directory.worktodo_lock.display()
+    );
+    debug!("Using results path: {}", work_directory.results.display());
+    let request = || {
+        let measured = update_throughput(&work_directory);
+        let days_of_work = days_of_work_with(days_of_work, throughput, measured);
+        top_up(&work_directory, num_cache, days_of_work, |num_to_cache| {
+            with_primenet_session(transport, &endpoints, &username, &password, || {
+                request_with_fallback(
+                    transport,
+                    &endpoints,
+                    num_to_cache,
+                    &work_directory,
+                    work_type,
+                    fallback_work_type,
+                    cores,
+                    exponent_range,
+                )
+            })
+        })
+    };
+    let submit = || {
+        with_primenet_session(transport, &endpoints, &username, &password, || {
+            primenet_submit(transport, &endpoints, &work_directory)
+        })
+    };
+    // Proof uploads identify the user by name rather than by session.
+    let upload = || match proofs {
+        ProofOptions::NoUpload => Ok(0),
+        _ => upload_proofs(
+            transport,
+            &endpoints,
+            &username,
+            &work_directory,
+            proofs == ProofOptions::Delete,
+        ),
     };
-    let results_path = Path::new(&work_directory).join(Path::new("results.txt"));
-    let results_lock_path = results_path.join(".lck");
-    let results_sent_path = Path::new(&work_directory).join(Path::new("results.sent"));
-    let results_sent_lock_path = results_sent_path.join(".lck");
-    println!("Using worktodo path: {}", worktodo_path.display());
-    println!("Using worktodo_lock path: {}", worktodo_lock_path.display());
-    println!("Using results path: {}", results_path.display());
     if timeout == 0 {
-        primenet_request(
-            &client,
-            num_cache,
-            &worktodo_path,
-            &worktodo_lock_path,
-            work_type,
-        )?;
-        primenet_submit(
-            &client,
-            &worktodo_path,
-            &worktodo_lock_path,
-            &results_path,
-            &results_lock_path,
-            &results_sent_path,
-            &results_sent_lock_path,
-        )?;
+        log_progress(&work_directory);
+        let result = request().and_then(|_| submit()).and_then(|_| upload());
+        log_cycle_summary(transport);
+        result?;
     } else {
+        // Set when Primenet turns down a work request, so we don't keep asking.
+        let mut next_request: Option<Instant> = None;
         loop {
             let start = Instant::now();
-            if let Err(e) = primenet_request(
-                &client,
-                num_cache,
-                &worktodo_path,
-                &worktodo_lock_path,
-                work_type,
-            ) {
-                println!("{}", e);
-            } else {
-                println!("Successfully requested and cached jobs.");
+            log_progress(&work_directory);
+            match next_request {
+                Some(next) if start < next => debug!(
+                    "Not requesting work for another {} seconds.",
+                    (next - start).as_secs()
+                ),
+                _ => match request() {
+                    Ok(backoff) => {
+                        next_request = backoff.map(|backoff| start + backoff);
+                        if backoff.is_none() {
+                            info!("Successfully requested and cached jobs.");
+                        }
+                    }
+                    Err(e) => {
+                        error!("{}", e);
+                        emit(Event::error(&e, false));
+                    }
+                },
             }
-            if let Err(e) = primenet_submit(
-                &client,
-                &worktodo_path,
-                &worktodo_lock_path,
-                &results_path,
-                &results_lock_path,
-                &results_sent_path,
-                &results_sent_lock_path,
-            ) {
-                println!("{}", e);
+            if let Err(e) = submit() {
+                error!("{}", e);
+                emit(Event::error(&e, false));
             } else {
-                println!(
-                    "Successfully submitted cached results to Primenet. Submitted results can be"
+                info!(
+                    "Successfully submitted cached results to Primenet. Submitted results are kept \
+                    in $WORKDIR/results.sent."
                 );
-                println!("found in $WORDKDIR/results.sent until next submission.");
             }
-            let sleep_duration = Duration::from_secs(timeout as u64) - start.elapsed();
+            if let Err(e) = upload() {
+                error!("{}", e);
+                emit(Event::error(&e, false));
+            }
+            log_cycle_summary(transport);
+            debug!("Sleeping until the next update in {} seconds.", timeout);
+            let sleep_duration = Duration::from_secs(timeout as u64)
+                .checked_sub(start.elapsed())
+                .unwrap_or_default();
             sleep(sleep_duration);
         }
     }
     Ok(())
 }
 
-pub fn primenet_cleanup(primenet_options: PrimenetOptions) {}
+// Log in and print what a run would request and submit, without doing either or touching any
+// file.
+pub fn primenet_dry_run_with_transport(
+    transport: &dyn Transport,
+    primenet_options: PrimenetOptions,
+) -> Result<(), Error> {
+    let PrimenetOptions {
+        credentials: (username, password),
+        work_type,
+        fallback_work_type,
+        cores,
+        general_options,
+        ..
+    } = primenet_options;
+    let endpoints = &general_options.endpoints;
+    primenet_ensure_login(transport, endpoints, &username, &password)?;
+    let mut plan = DryRun::plan(
+        &general_options,
+        |num_to_get| {
+            primenet_work_request(
+                endpoints,
+                num_to_get,
+                work_type,
+                cores,
+                general_options.exponent_range,
+            )
+        },
+        true,
+        Utc::now(),
+    )?;
+    // request_with_fallback only switches to a different work type.
+    if let Some(fallback) = fallback_work_type.filter(|&fallback| fallback != work_type) {
+        plan = plan.with_fallback(|num_to_get| {
+            primenet_work_request(
+                endpoints,
+                num_to_get,
+                fallback,
+                cores,
+                general_options.exponent_range,
+            )
+        });
+    }
+    output(&plan, || Event::dry_run(&plan));
+    Ok(())
+}
+
+// Remove any lockfiles left behind in the work directory. Only safe to call when the error that
+// brought us here wasn't caused by somebody else's lock.
+pub fn primenet_cleanup(primenet_options: PrimenetOptions) {
+    let general_options = &primenet_options.general_options;
+    let _ = WorkDirectory::from_options(&general_options.work_directory, general_options.client)
+        .unlock_all();
+}
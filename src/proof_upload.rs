@@ -0,0 +1,393 @@
+use crate::{
+    endpoints::Endpoints,
+    error::Error,
+    logging::{NETWORK, PARSING},
+    transport::{Request, Transport},
+    util::read_list,
+    work_directory::WorkDirectory,
+    work_result::WorkResult,
+};
+use log::{debug, info, trace, warn};
+use md5::{Digest, Md5};
+use reqwest::Url;
+use serde_json::Value;
+use std::fs::{create_dir_all, read_dir, remove_file, rename, File};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+// Uploaded proofs are moved here unless they're to be deleted.
+pub const UPLOADED_PROOFS_DIR: &str = "proofs-uploaded";
+// Primenet takes proofs in pieces of up to 5MiB, so an interrupted upload only loses one piece.
+pub const PROOF_CHUNK_SIZE: usize = 5 * 1024 * 1024;
+// Clients write proofs to the work directory itself or a directory or two under it, e.g. gpuowl's
+// <exponent>/proof/.
+const PROOF_SEARCH_DEPTH: usize = 3;
+// Times the same piece is sent before giving up on the upload for this run.
+const MAX_CHUNK_ATTEMPTS: usize = 3;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofFile {
+    pub path: PathBuf,
+    pub exponent: u64,
+}
+
+// The exponent a proof is for, from its text header. Every version has a "NUMBER=M<exponent>"
+// line within the first few, before the binary data starts.
+pub fn proof_exponent(header: &str) -> Option<u64> {
+    if !header.starts_with("PRP PROOF") {
+        return None;
+    }
+    header
+        .lines()
+        .take(10)
+        .find_map(|line| line.trim().strip_prefix("NUMBER=M"))
+        .and_then(|number| {
+            // Cofactor proofs name the known factors after a slash.
+            let exponent = number.split('/').next().unwrap_or(number);
+            exponent.parse::<u64>().ok()
+        })
+}
+
+fn read_proof_header(path: &Path) -> Option<String> {
+    let mut header = Vec::new();
+    let mut reader = BufReader::new(File::open(path).ok()?);
+    for _ in 0..10 {
+        if reader.read_until(b'\n', &mut header).ok()? == 0 {
+            break;
+        }
+    }
+    Some(String::from_utf8_lossy(&header).into_owned())
+}
+
+fn find_proofs(dir: &Path, depth: usize, proofs: &mut Vec<ProofFile>) {
+    let entries = match read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => return debug!("Not looking for proofs in {}. {}", dir.display(), e),
+    };
+    for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+        if path.is_dir() {
+            if depth > 1 && !path.ends_with(UPLOADED_PROOFS_DIR) {
+                find_proofs(&path, depth - 1, proofs);
+            }
+        } else if path.extension().is_some_and(|ext| ext == "proof") {
+            match read_proof_header(&path).as_deref().and_then(proof_exponent) {
+                Some(exponent) => proofs.push(ProofFile { path, exponent }),
+                None => warn!(
+                    target: PARSING,
+                    "Skipping {}, which doesn't look like a PRP proof.",
+                    path.display()
+                ),
+            }
+        }
+    }
+}
+
+// Proof files anywhere in the work directory, apart from the ones already uploaded.
+pub fn proof_files(work_directory: &Path) -> Vec<ProofFile> {
+    let mut proofs = Vec::new();
+    find_proofs(work_directory, PROOF_SEARCH_DEPTH, &mut proofs);
+    proofs.sort_by(|a, b| a.path.cmp(&b.path));
+    proofs
+}
+
+// The MD5 of the proof a JSON result was written with, if it was written with one.
+pub fn result_proof_md5(result: &str) -> Option<String> {
+    let json = serde_json::from_str::<Value>(result).ok()?;
+    Some(json.get("proof")?.get("md5")?.as_str()?.to_lowercase())
+}
+
+// The submitted result a proof belongs to. Primenet won't take a proof before its result, so only
+// results.sent is searched.
+pub fn matching_result(results_sent: &[String], proof: &ProofFile) -> Option<String> {
+    results_sent
+        .iter()
+        .filter(|line| line.trim_start().starts_with('{'))
+        .filter(|line| {
+            WorkResult::parse(line).is_ok_and(|result| result.exponent == proof.exponent)
+        })
+        .find(|line| result_proof_md5(line).is_some())
+        .cloned()
+}
+
+pub fn file_md5(path: &Path) -> Result<(String, u64), Error> {
+    let context = || format!("Failed to read {}.", path.display());
+    let mut file = File::open(path).map_err(|e| Error::io(context(), e))?;
+    let mut md5 = Md5::new();
+    let mut buffer = vec![0; 64 * 1024];
+    let mut size = 0;
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| Error::io(context(), e))?;
+        if read == 0 {
+            break;
+        }
+        md5.update(&buffer[..read]);
+        size += read as u64;
+    }
+    Ok((format!("{:x}", md5.finalize()), size))
+}
+
+// What the upload server wants next.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum UploadState {
+    Done,
+    // Where to send pieces, and the byte ranges (inclusive) it doesn't have yet.
+    Need {
+        url: String,
+        ranges: Vec<(u64, u64)>,
+    },
+}
+
+fn json_u64(value: &Value) -> Option<u64> {
+    value
+        .as_u64()
+        .or_else(|| value.as_str().and_then(|s| s.trim().parse().ok()))
+}
+
+// Every response is a JSON object: an error_status, FileUploaded once the server has the whole
+// file, or the URL to send pieces to along with the ranges it still needs.
+fn upload_state(base_url: &str, body: &str) -> Result<UploadState, Error> {
+    let json = serde_json::from_str::<Value>(body).map_err(|e| {
+        Error::Parse(format!(
+            "Failed to parse proof upload response: {}\nResponse text: {}",
+            e, body
+        ))
+    })?;
+    if let Some(status) = json.get("error_status").and_then(json_u64) {
+        let description = json
+            .get("error_description")
+            .and_then(Value::as_str)
+            .unwrap_or("no description");
+        // 409 is the server saying it already has the file.
+        return if status == 409 {
+            debug!(target: NETWORK, "Proof already uploaded: {}", description);
+            Ok(UploadState::Done)
+        } else {
+            Err(Error::Rejected(format!(
+                "Proof upload refused with status {}: {}",
+                status, description
+            )))
+        };
+    }
+    if json.get("FileUploaded").is_some() {
+        return Ok(UploadState::Done);
+    }
+    let url = json.get("URLToUse").and_then(Value::as_str);
+    let need = json.get("need").and_then(Value::as_object);
+    match (url, need) {
+        (Some(url), Some(need)) => {
+            let url = Url::parse(base_url)
+                .and_then(|base| base.join(url))
+                .map_err(|e| {
+                    Error::Parse(format!("Bad proof upload URL '{}'. Error: {}", url, e))
+                })?;
+            let mut ranges = need
+                .iter()
+                .filter_map(|(start, end)| Some((start.trim().parse().ok()?, json_u64(end)?)))
+                .collect::<Vec<(u64, u64)>>();
+            ranges.sort_unstable();
+            Ok(UploadState::Need {
+                url: url.to_string(),
+                ranges,
+            })
+        }
+        _ => Err(Error::Parse(format!(
+            "Proof upload response had neither an error nor the pieces it needs: {}",
+            body
+        ))),
+    }
+}
+
+fn read_chunk(path: &Path, offset: u64, size: usize) -> Result<Vec<u8>, Error> {
+    let context = || format!("Failed to read {}.", path.display());
+    let mut file = File::open(path).map_err(|e| Error::io(context(), e))?;
+    file.seek(SeekFrom::Start(offset))
+        .map_err(|e| Error::io(context(), e))?;
+    let mut chunk = Vec::with_capacity(size);
+    file.take(size as u64)
+        .read_to_end(&mut chunk)
+        .map_err(|e| Error::io(context(), e))?;
+    Ok(chunk)
+}
+
+fn send(
+    transport: &dyn Transport,
+    request: &Request,
+    base_url: &str,
+) -> Result<UploadState, Error> {
+    let response = transport
+        .send(request)
+        .map_err(|e| Error::network("Failed to upload proof.", e))?;
+    trace!(target: NETWORK, "Proof upload response body: {}", response.body);
+    if response.status != 200 {
+        return Err(Error::HttpStatus {
+            url: response.url,
+            status: response.status,
+            body: response.body,
+        });
+    }
+    upload_state(base_url, &response.body)
+}
+
+// Upload one proof, picking up wherever the server says an earlier attempt got to. Returns once
+// the server has confirmed it has the whole file.
+pub fn upload_proof(
+    transport: &dyn Transport,
+    endpoints: &Endpoints,
+    user_id: &str,
+    proof: &ProofFile,
+    md5: &str,
+    size: u64,
+    chunk_size: usize,
+) -> Result<(), Error> {
+    let upload_addr = endpoints.proof_upload();
+    let exponent = proof.exponent.to_string();
+    let file_size = size.to_string();
+    debug!(
+        target: NETWORK,
+        "GET {} (exponent={}, size={}, md5={})", upload_addr, exponent, file_size, md5
+    );
+    let request = Request::get(&upload_addr).query(&[
+        ("UserID", user_id),
+        ("Exponent", &exponent),
+        ("FileSize", &file_size),
+        ("FileMD5", md5),
+    ]);
+    let mut state = send(transport, &request, &upload_addr)?;
+    let mut last_offset = None;
+    let mut attempts = 0;
+    while let UploadState::Need { url, ranges } = state {
+        let (start, end) = match ranges.first() {
+            Some(&range) => range,
+            None => {
+                return Err(Error::Parse(
+                    "Proof upload server wants more of the file but didn't say which part."
+                        .to_string(),
+                ))
+            }
+        };
+        if last_offset == Some(start) {
+            attempts += 1;
+            if attempts >= MAX_CHUNK_ATTEMPTS {
+                return Err(Error::Rejected(format!(
+                    "Proof upload server keeps asking for offset {} of {}.",
+                    start,
+                    proof.path.display()
+                )));
+            }
+        } else {
+            last_offset = Some(start);
+            attempts = 0;
+        }
+        let wanted = (end.saturating_sub(start) + 1).min(chunk_size as u64) as usize;
+        let chunk = read_chunk(&proof.path, start, wanted)?;
+        let data_offset = start.to_string();
+        let data_size = chunk.len().to_string();
+        let data_md5 = format!("{:x}", Md5::digest(&chunk));
+        debug!(
+            target: NETWORK,
+            "POST {} (offset={}, size={})", url, data_offset, data_size
+        );
+        let file_name = proof
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let request = Request::post(&url)
+            .query(&[
+                ("FileMD5", md5),
+                ("DataOffset", &data_offset),
+                ("DataSize", &data_size),
+                ("DataMD5", &data_md5),
+            ])
+            .upload("Data", &file_name, chunk);
+        state = send(transport, &request, &url)?;
+    }
+    Ok(())
+}
+
+// Move an uploaded proof out of the way, or delete it.
+fn retire_proof(work_directory: &Path, proof: &ProofFile, delete: bool) -> Result<(), Error> {
+    if delete {
+        return remove_file(&proof.path)
+            .map_err(|e| Error::io(format!("Failed to delete {}.", proof.path.display()), e));
+    }
+    let archive = work_directory.join(UPLOADED_PROOFS_DIR);
+    create_dir_all(&archive)
+        .map_err(|e| Error::io(format!("Failed to create {}.", archive.display()), e))?;
+    let destination = archive.join(proof.path.file_name().unwrap_or_default());
+    rename(&proof.path, &destination).map_err(|e| {
+        Error::io(
+            format!(
+                "Failed to move {} to {}.",
+                proof.path.display(),
+                destination.display()
+            ),
+            e,
+        )
+    })
+}
+
+// Upload every proof whose result has been submitted, and archive or delete each one once the
+// server has it. A proof the server refuses is left where it is for somebody to look at, but
+// problems reaching the server stop the whole run. Returns the number uploaded.
+pub fn upload_proofs(
+    transport: &dyn Transport,
+    endpoints: &Endpoints,
+    user_id: &str,
+    work_directory: &WorkDirectory,
+    delete_uploaded: bool,
+) -> Result<usize, Error> {
+    let proofs = proof_files(&work_directory.path);
+    if proofs.is_empty() {
+        return Ok(0);
+    }
+    let results_sent = if work_directory.results_sent.exists() {
+        read_list(&work_directory.results_sent)?
+    } else {
+        Vec::new()
+    };
+    let mut uploaded = 0;
+    for proof in proofs {
+        let result = match matching_result(&results_sent, &proof) {
+            Some(result) => result,
+            None => {
+                debug!(
+                    "Not uploading {} until its result for M{} has been submitted.",
+                    proof.path.display(),
+                    proof.exponent
+                );
+                continue;
+            }
+        };
+        let (md5, size) = file_md5(&proof.path)?;
+        if result_proof_md5(&result).as_deref() != Some(md5.as_str()) {
+            warn!(
+                "Not uploading {}, since its MD5 {} doesn't match the one in its result: {}",
+                proof.path.display(),
+                md5,
+                result
+            );
+            continue;
+        }
+        match upload_proof(
+            transport,
+            endpoints,
+            user_id,
+            &proof,
+            &md5,
+            size,
+            PROOF_CHUNK_SIZE,
+        ) {
+            Ok(()) => {
+                info!("Uploaded proof for M{}.", proof.exponent);
+                retire_proof(&work_directory.path, &proof, delete_uploaded)?;
+                uploaded += 1;
+            }
+            Err(Error::Rejected(msg)) => warn!("{} Leaving {} alone.", msg, proof.path.display()),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(uploaded)
+}
@@ -0,0 +1,28 @@
+use crate::{
+    error::Error,
+    events::{output, Event},
+    options::QueueOptions,
+    submission_queue::{Quarantine, SubmissionQueue},
+    work_directory::WorkDirectory,
+};
+use log::debug;
+
+// Print the submission queue for a work directory, and any results quarantined instead. Nothing is
+// locked, since this only reads, but a queue that's locked is probably being rewritten right now
+// so it's not worth reading.
+pub fn queue_runtime(queue_options: QueueOptions) -> Result<(), Error> {
+    let work_directory = WorkDirectory::new(&queue_options.work_directory);
+    debug!("Using queue path: {}", work_directory.queue.display());
+    if work_directory.queue_lock.exists() {
+        return Err(Error::LockContention(work_directory.queue_lock));
+    }
+    let queue = SubmissionQueue::load(&work_directory.queue)?;
+    let quarantine = Quarantine::load(&work_directory.results_quarantine)?;
+    let text = if quarantine.entries.is_empty() {
+        queue.to_string()
+    } else {
+        format!("{}\n\n{}", queue, quarantine)
+    };
+    output(text, || Event::queue(&work_directory, &queue, &quarantine));
+    Ok(())
+}
@@ -0,0 +1,223 @@
+use crate::{
+    error::Error,
+    logging::NETWORK,
+    retry::RetryStats,
+    transport::{Request, Response, Transport, TransportError},
+};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{create_dir_all, read_dir, read_to_string, write};
+use std::io::{Error as IoError, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// Stands in for passwords in recorded requests.
+pub const REDACTED: &str = "<redacted>";
+
+// Form fields that hold secrets. Usernames are left alone, since Primenet echoes them back in the
+// login page and replaying the login depends on that.
+const SECRET_FORM_FIELDS: &[&str] = &["user_password"];
+
+// One request and what came back, as saved in a recording directory. A request that never got a
+// response keeps the error message instead, so replaying it fails the same way.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Exchange {
+    pub request: Request,
+    pub response: Option<Response>,
+    pub error: Option<String>,
+}
+
+pub fn redact(request: &Request) -> Request {
+    let mut request = request.clone();
+    for (key, value) in &mut request.form {
+        if SECRET_FORM_FIELDS.contains(&key.as_str()) {
+            *value = REDACTED.to_string();
+        }
+    }
+    if let Some((_, password)) = &mut request.basic_auth {
+        *password = REDACTED.to_string();
+    }
+    request
+}
+
+fn recording_files(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut files = read_dir(dir)
+        .map_err(|e| {
+            Error::io(
+                format!("Failed to read recording directory {}.", dir.display()),
+                e,
+            )
+        })?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect::<Vec<_>>();
+    // Files are numbered, so name order is the order they were recorded in.
+    files.sort();
+    Ok(files)
+}
+
+// Last path segment of the URL for naming recording files, e.g. "manual_assignment", or "root"
+// for the site root where Primenet takes logins.
+fn url_slug(url: &str) -> String {
+    let without_scheme = url.splitn(2, "://").last().unwrap_or("");
+    let path = without_scheme.split('?').next().unwrap_or("");
+    path.split('/')
+        .skip(1)
+        .filter(|segment| !segment.is_empty())
+        .last()
+        .map(|segment| {
+            segment
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || c == '-' {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_else(|| "root".to_string())
+}
+
+// Passes everything through to another transport and saves each exchange to its own file in a
+// directory, numbered after whatever's already there.
+pub struct RecordingTransport<T: Transport> {
+    inner: T,
+    dir: PathBuf,
+    next: Mutex<usize>,
+}
+
+impl<T: Transport> RecordingTransport<T> {
+    pub fn new<P: AsRef<Path>>(inner: T, dir: P) -> Result<Self, Error> {
+        let dir = dir.as_ref().to_path_buf();
+        create_dir_all(&dir).map_err(|e| {
+            Error::io(
+                format!("Failed to create recording directory {}.", dir.display()),
+                e,
+            )
+        })?;
+        let next = recording_files(&dir)?.len() + 1;
+        Ok(RecordingTransport {
+            inner,
+            dir,
+            next: Mutex::new(next),
+        })
+    }
+
+    fn save(&self, exchange: &Exchange) {
+        let mut next = self.next.lock().unwrap();
+        let path = self.dir.join(format!(
+            "{:04}-{}-{}.json",
+            *next,
+            exchange.request.method,
+            url_slug(&exchange.request.url)
+        ));
+        *next += 1;
+        let saved = serde_json::to_string_pretty(exchange)
+            .map_err(|e| e.to_string())
+            .and_then(|json| write(&path, json).map_err(|e| e.to_string()));
+        match saved {
+            Ok(()) => debug!(target: NETWORK, "Recorded exchange to {}", path.display()),
+            // Losing a recording shouldn't stop the actual work.
+            Err(e) => warn!(
+                "Failed to record exchange to {}. Error: {}",
+                path.display(),
+                e
+            ),
+        }
+    }
+}
+
+impl<T: Transport> Transport for RecordingTransport<T> {
+    fn send(&self, request: &Request) -> Result<Response, TransportError> {
+        let result = self.inner.send(request);
+        let (response, error) = match &result {
+            Ok(response) => (Some(response.clone()), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        self.save(&Exchange {
+            request: redact(request),
+            response,
+            error,
+        });
+        result
+    }
+
+    fn take_stats(&self) -> Option<RetryStats> {
+        self.inner.take_stats()
+    }
+}
+
+// Serves a recording back in order without touching the network. Each request has to match the
+// method and URL of the next recorded one, otherwise the conversation has gone off script.
+pub struct ReplayTransport {
+    exchanges: Mutex<VecDeque<Exchange>>,
+}
+
+impl ReplayTransport {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Result<Self, Error> {
+        let dir = dir.as_ref();
+        let mut exchanges = VecDeque::new();
+        for path in recording_files(dir)? {
+            let json = read_to_string(&path)
+                .map_err(|e| Error::io(format!("Failed to read {}.", path.display()), e))?;
+            let exchange = serde_json::from_str::<Exchange>(&json).map_err(|e| {
+                Error::Parse(format!(
+                    "Failed to parse recorded exchange {}. Error: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+            exchanges.push_back(exchange);
+        }
+        debug!(
+            target: NETWORK,
+            "Loaded {} recorded exchange(s) from {}",
+            exchanges.len(),
+            dir.display()
+        );
+        Ok(ReplayTransport {
+            exchanges: Mutex::new(exchanges),
+        })
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.exchanges.lock().unwrap().len()
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn send(&self, request: &Request) -> Result<Response, TransportError> {
+        let exchange = self.exchanges.lock().unwrap().pop_front().ok_or_else(|| {
+            IoError::new(
+                ErrorKind::UnexpectedEof,
+                format!(
+                    "Recording has no more exchanges, but got {} {}",
+                    request.method, request.url
+                ),
+            )
+        })?;
+        if exchange.request.method != request.method || exchange.request.url != request.url {
+            return Err(IoError::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Recording expected {} {} next, but got {} {}",
+                    exchange.request.method, exchange.request.url, request.method, request.url
+                ),
+            )
+            .into());
+        }
+        debug!(target: NETWORK, "Replaying {} {}", request.method, request.url);
+        match (exchange.response, exchange.error) {
+            (Some(response), _) => Ok(response),
+            (None, Some(error)) => Err(IoError::other(error).into()),
+            (None, None) => Err(IoError::new(
+                ErrorKind::InvalidData,
+                "Recorded exchange has neither a response nor an error",
+            )
+            .into()),
+        }
+    }
+}
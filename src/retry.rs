@@ -0,0 +1,207 @@
+use crate::{
+    logging::NETWORK,
+    transport::{Method, Request, Response, Transport, TransportError},
+};
+use log::{info, warn};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{Error as IoError, ErrorKind};
+use std::sync::Mutex;
+use std::thread::sleep;
+use std::time::Duration;
+
+pub const DEFAULT_RETRIES: u32 = 3;
+pub const DEFAULT_RETRY_DELAY: u64 = 5;
+pub const DEFAULT_RETRY_MAX_DELAY: u64 = 300;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    // Retries after the first attempt, so 0 turns retrying off.
+    pub retries: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            retries: DEFAULT_RETRIES,
+            initial_delay: Duration::from_secs(DEFAULT_RETRY_DELAY),
+            max_delay: Duration::from_secs(DEFAULT_RETRY_MAX_DELAY),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        RetryPolicy {
+            retries: 0,
+            ..RetryPolicy::default()
+        }
+    }
+
+    // Exponential backoff capped at max_delay, with "equal jitter": somewhere between half the
+    // backoff and all of it, so clients that failed together don't all come back together.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let backoff = self
+            .initial_delay
+            .checked_mul(1 << retry.min(16))
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+        let half = backoff / 2;
+        let jitter_range = (backoff - half).as_millis() as u64;
+        let jitter = if jitter_range == 0 {
+            0
+        } else {
+            random_u64() % (jitter_range + 1)
+        };
+        half + Duration::from_millis(jitter)
+    }
+}
+
+// Good enough randomness for jitter without pulling in a dependency: std seeds every RandomState
+// differently.
+fn random_u64() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+fn is_retryable_status(status: u16) -> bool {
+    status == 429 || (500..600).contains(&status)
+}
+
+// Failures to reach the server at all are worth another go. Anything else (bad URLs, TLS
+// problems, running off the end of a script) will just fail the same way again.
+fn is_retryable_error(e: &TransportError) -> bool {
+    if let Some(e) = e.downcast_ref::<reqwest::Error>() {
+        e.is_connect() || e.is_timeout()
+    } else if let Some(e) = e.downcast_ref::<IoError>() {
+        matches!(
+            e.kind(),
+            ErrorKind::ConnectionRefused
+                | ErrorKind::ConnectionReset
+                | ErrorKind::ConnectionAborted
+                | ErrorKind::TimedOut
+                | ErrorKind::Interrupted
+        )
+    } else {
+        false
+    }
+}
+
+// Whether a failed request certainly never reached the server, so sending it again can't make it
+// happen twice.
+fn was_never_sent(e: &TransportError) -> bool {
+    if let Some(e) = e.downcast_ref::<reqwest::Error>() {
+        e.is_connect()
+    } else if let Some(e) = e.downcast_ref::<IoError>() {
+        e.kind() == ErrorKind::ConnectionRefused
+    } else {
+        false
+    }
+}
+
+// What happened on the wire since the last summary.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RetryStats {
+    pub requests: u32,
+    pub retries: u32,
+    // Requests that still hadn't succeeded when the retries ran out (or weren't retryable).
+    pub failures: u32,
+}
+
+// Retries requests on another transport according to a policy. Retryable statuses that never go
+// away are handed back as the final response, so callers still see the status. POSTs aren't
+// idempotent: a timeout or a 502 can come after the server has already acted on one, e.g. taken a
+// result, so they're only retried when they never got through. Anything else is left to the
+// caller, like the submission queue's own backoff.
+pub struct RetryingTransport<T: Transport> {
+    inner: T,
+    policy: RetryPolicy,
+    stats: Mutex<RetryStats>,
+}
+
+impl<T: Transport> RetryingTransport<T> {
+    pub fn new(inner: T, policy: RetryPolicy) -> Self {
+        RetryingTransport {
+            inner,
+            policy,
+            stats: Mutex::new(RetryStats::default()),
+        }
+    }
+}
+
+impl<T: Transport> Transport for RetryingTransport<T> {
+    fn send(&self, request: &Request) -> Result<Response, TransportError> {
+        let idempotent = request.method != Method::Post;
+        let mut retry = 0;
+        loop {
+            let result = self.inner.send(request);
+            let problem = match &result {
+                Ok(response) if is_retryable_status(response.status) => {
+                    Some((idempotent, format!("status {}", response.status)))
+                }
+                Ok(response) if response.status < 400 => None,
+                Ok(response) => Some((false, format!("status {}", response.status))),
+                Err(e) if idempotent => Some((is_retryable_error(e), e.to_string())),
+                Err(e) => Some((was_never_sent(e), e.to_string())),
+            };
+            let mut stats = self.stats.lock().unwrap();
+            match problem {
+                Some((true, reason)) if retry < self.policy.retries => {
+                    let delay = self.policy.delay(retry);
+                    retry += 1;
+                    stats.retries += 1;
+                    drop(stats);
+                    warn!(
+                        target: NETWORK,
+                        "{} {} failed ({}). Retry {} of {} in {:.1} seconds.",
+                        request.method,
+                        request.url,
+                        reason,
+                        retry,
+                        self.policy.retries,
+                        delay.as_secs_f64()
+                    );
+                    sleep(delay);
+                }
+                problem => {
+                    stats.requests += 1;
+                    if let Some((_, reason)) = problem {
+                        stats.failures += 1;
+                        if retry > 0 {
+                            warn!(
+                                target: NETWORK,
+                                "{} {} failed ({}) after {} attempt(s).",
+                                request.method,
+                                request.url,
+                                reason,
+                                retry + 1
+                            );
+                        }
+                    }
+                    return result;
+                }
+            }
+        }
+    }
+
+    fn take_stats(&self) -> Option<RetryStats> {
+        let mut stats = self.stats.lock().unwrap();
+        Some(std::mem::take(&mut *stats))
+    }
+}
+
+// Log how the network held up over the last update cycle, if there was anything worth mentioning.
+pub fn log_cycle_summary(transport: &dyn Transport) {
+    if let Some(stats) = transport.take_stats() {
+        if stats.retries > 0 || stats.failures > 0 {
+            info!(
+                target: NETWORK,
+                "Cycle summary: {} request(s), {} retry attempt(s), {} failure(s).",
+                stats.requests,
+                stats.retries,
+                stats.failures
+            );
+        }
+    }
+}
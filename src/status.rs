@@ -0,0 +1,205 @@
+use crate::{
+    activity::{Activity, WorkSource},
+    assignment::Assignment,
+    client::Client,
+    credit::iteration_credit,
+    error::Error,
+    gpuowl::Progress,
+    mlucas::stat_timing,
+    submission_queue::SubmissionQueue,
+    throughput::Throughput,
+    util::read_list,
+    work_directory::{parse_assignments, WorkDirectory},
+    work_result::WorkResult,
+};
+use chrono::{DateTime, Duration, Local, Utc};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CachedAssignment {
+    pub assignment: Assignment,
+    // None for assignments we didn't fetch, e.g. ones added by hand.
+    pub source: Option<WorkSource>,
+}
+
+// How far along the assignment the client is running is, and when it should be done.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CurrentProgress {
+    pub progress: Progress,
+    pub eta: Option<DateTime<Utc>>,
+}
+
+// Everything there is to know about a work directory without asking a server.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Status {
+    pub work_directory: PathBuf,
+    pub cached: Vec<CachedAssignment>,
+    pub current: Option<CurrentProgress>,
+    // The client, when there's work cached but its files don't say how far along it is.
+    pub progress_unavailable: Option<Client>,
+    // Finished results not yet accepted by Primenet, whether still in results.txt or queued.
+    pub results_waiting: usize,
+    pub activity: Activity,
+    pub throughput: Throughput,
+    // Lockfiles that exist right now, so somebody is (or was, and died) working on the directory.
+    pub locks: Vec<PathBuf>,
+}
+
+// When a test at `progress` should finish, going by Mlucas's own timing if there is any and the
+// measured throughput otherwise.
+pub fn eta(
+    progress: &Progress,
+    seconds_per_iteration: Option<f64>,
+    ghz_days_per_day: Option<f64>,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let seconds_per_iteration = seconds_per_iteration.or_else(|| {
+        ghz_days_per_day.map(|rate| iteration_credit(progress.exponent) * 86400.0 / rate)
+    })?;
+    let remaining = progress.iterations.saturating_sub(progress.iteration) as f64;
+    Some(now + Duration::milliseconds((remaining * seconds_per_iteration * 1000.0) as i64))
+}
+
+fn lines_if_exists(path: &Path) -> Result<Vec<String>, Error> {
+    if path.exists() {
+        read_list(path)
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+impl Status {
+    // Files are read without locking, so anything that's in the middle of being rewritten may be
+    // out of date. The locks are listed so that's easy to spot.
+    pub fn read(work_directory: &WorkDirectory, now: DateTime<Utc>) -> Result<Self, Error> {
+        let activity = Activity::load(&work_directory.activity)?;
+        let throughput = Throughput::load(&work_directory.throughput)?;
+        let worktodo = lines_if_exists(&work_directory.worktodo)?;
+        let cached = parse_assignments(&worktodo)
+            .into_iter()
+            .map(|assignment| CachedAssignment {
+                source: activity.source(&assignment),
+                assignment,
+            })
+            .collect::<Vec<_>>();
+        // The client works through its worktodo from the top.
+        let current = cached.first().and_then(|current| {
+            let assignment = &current.assignment;
+            let progress = work_directory.progress(assignment)?;
+            let seconds_per_iteration = stat_timing(&work_directory.path, assignment.exponent)
+                .map(|timing| timing.seconds_per_iteration);
+            Some(CurrentProgress {
+                progress,
+                eta: eta(
+                    &progress,
+                    seconds_per_iteration,
+                    throughput.ghz_days_per_day(),
+                    now,
+                ),
+            })
+        });
+        let progress_unavailable = work_directory.client.filter(|client| {
+            !cached.is_empty() && !client.checkpoint_format().has_readable_progress()
+        });
+        let results_sent = lines_if_exists(&work_directory.results_sent)?;
+        let queue = SubmissionQueue::load(&work_directory.queue)?;
+        let results_format = work_directory.client.map(|client| client.results_format());
+        let unqueued = lines_if_exists(&work_directory.results)?
+            .into_iter()
+            .filter(|line| !line.is_empty() && !worktodo.contains(line))
+            .filter(|line| results_format.map_or(true, |format| format.accepts(line)))
+            .filter(|line| WorkResult::parse(line).is_ok())
+            .filter(|line| !results_sent.contains(line) && !queue.contains(line))
+            .count();
+        let locks = work_directory
+            .lockfile_paths()
+            .iter()
+            .filter(|path| path.exists())
+            .map(|path| path.to_path_buf())
+            .collect();
+        Ok(Status {
+            work_directory: work_directory.path.clone(),
+            cached,
+            current,
+            progress_unavailable,
+            results_waiting: unqueued + queue.len(),
+            activity,
+            throughput,
+            locks,
+        })
+    }
+}
+
+fn local_time(time: DateTime<Utc>) -> String {
+    time.with_timezone(&Local).format(TIME_FORMAT).to_string()
+}
+
+fn local_time_or_never(time: Option<DateTime<Utc>>) -> String {
+    time.map_or_else(|| "never".to_string(), local_time)
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Work directory: {}", self.work_directory.display())?;
+        if self.cached.is_empty() {
+            write!(f, "\n\nNo assignments cached.")?;
+        } else {
+            write!(f, "\n\n{} assignment(s) cached:", self.cached.len())?;
+            write!(
+                f,
+                "\n    {:<12} {:>10}  {:<32}  Source",
+                "Type", "Exponent", "AID"
+            )?;
+            for cached in &self.cached {
+                let assignment = &cached.assignment;
+                write!(
+                    f,
+                    "\n    {:<12} {:>10}  {:<32}  {}",
+                    assignment.kind.as_str(),
+                    assignment.exponent,
+                    assignment.aid.as_deref().unwrap_or("N/A"),
+                    cached
+                        .source
+                        .map_or_else(|| "unknown".to_string(), |source| source.to_string())
+                )?;
+            }
+        }
+        if let Some(current) = &self.current {
+            write!(f, "\n\n{}", current.progress)?;
+            if let Some(eta) = current.eta {
+                write!(f, ", done around {}", local_time(eta))?;
+            }
+            write!(f, ".")?;
+        }
+        if let Some(client) = self.progress_unavailable {
+            write!(f, "\n\nProgress isn't available for {}.", client)?;
+        }
+        write!(
+            f,
+            "\n\nResults waiting to be submitted: {}",
+            self.results_waiting
+        )?;
+        write!(
+            f,
+            "\nLast fetch:  {}",
+            local_time_or_never(self.activity.last_fetch)
+        )?;
+        write!(
+            f,
+            "\nLast submit: {}",
+            local_time_or_never(self.activity.last_submit)
+        )?;
+        if self.locks.is_empty() {
+            write!(f, "\nLocks held:  none")?;
+        } else {
+            write!(f, "\nLocks held:")?;
+            for lock in &self.locks {
+                write!(f, "\n    {}", lock.display())?;
+            }
+        }
+        write!(f, "\n\n{}", self.throughput)
+    }
+}
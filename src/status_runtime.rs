@@ -0,0 +1,19 @@
+use crate::{
+    error::Error,
+    events::{output, Event},
+    options::StatusOptions,
+    status::Status,
+    work_directory::WorkDirectory,
+};
+use chrono::Utc;
+use log::debug;
+
+// Print what's known about a work directory. Only reads, the same as the queue subcommand.
+pub fn status_runtime(status_options: StatusOptions) -> Result<(), Error> {
+    let work_directory =
+        WorkDirectory::from_options(&status_options.work_directory, status_options.client);
+    debug!("Using worktodo path: {}", work_directory.worktodo.display());
+    let status = Status::read(&work_directory, Utc::now())?;
+    output(&status, || Event::status(&status));
+    Ok(())
+}
@@ -0,0 +1,179 @@
+use crate::{
+    error::Error,
+    retry::RetryPolicy,
+    util::{load_json, read_list, save_json},
+};
+use chrono::{DateTime, Duration as ChronoDuration, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+
+// A result that couldn't be sent, or got an answer we didn't recognise, waits this long before it's
+// tried again, doubling with each failed attempt up to the maximum.
+pub const QUEUE_RETRY_DELAY: u64 = 10 * 60;
+pub const QUEUE_RETRY_MAX_DELAY: u64 = 24 * 60 * 60;
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueuedResult {
+    pub result: String,
+    pub queued_at: DateTime<Utc>,
+    pub attempts: u32,
+    pub last_attempt: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub next_attempt: DateTime<Utc>,
+}
+
+// Results waiting to be accepted by Primenet, oldest first. It lives in the work directory next to
+// results.sent, so anything that couldn't be sent survives restarts.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SubmissionQueue {
+    pub entries: Vec<QueuedResult>,
+}
+
+// Results that were taken out of the queue or results.txt for good, each with the note saying when
+// and why. Primenet rejecting a result outright is final, so those end up here too rather than
+// being resent forever.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Quarantine {
+    pub entries: Vec<(String, String)>,
+}
+
+impl Quarantine {
+    // Read back what append_quarantine wrote. A missing file is an empty quarantine.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        if !path.exists() {
+            return Ok(Quarantine::default());
+        }
+        let mut entries = Vec::new();
+        let mut note = None;
+        for line in read_list(path)? {
+            if let Some(comment) = line.strip_prefix("# ") {
+                note = Some(comment.to_string());
+            } else if !line.is_empty() {
+                entries.push((line, note.take().unwrap_or_default()));
+            }
+        }
+        Ok(Quarantine { entries })
+    }
+}
+
+fn backoff(attempts: u32) -> ChronoDuration {
+    let policy = RetryPolicy {
+        retries: u32::MAX,
+        initial_delay: Duration::from_secs(QUEUE_RETRY_DELAY),
+        max_delay: Duration::from_secs(QUEUE_RETRY_MAX_DELAY),
+    };
+    ChronoDuration::from_std(policy.delay(attempts.saturating_sub(1)))
+        .unwrap_or_else(|_| ChronoDuration::zero())
+}
+
+impl SubmissionQueue {
+    // A missing queue file is just an empty queue.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        load_json(path, "submission queue")
+    }
+
+    // Saved so that a crash part way through can't leave a truncated queue behind.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        save_json(path, self)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains(&self, result: &str) -> bool {
+        self.entries.iter().any(|entry| entry.result == result)
+    }
+
+    // Add a result to the back of the queue, due straight away. Returns false if it was already
+    // queued.
+    pub fn push(&mut self, result: String, now: DateTime<Utc>) -> bool {
+        if self.contains(&result) {
+            return false;
+        }
+        self.entries.push(QueuedResult {
+            result,
+            queued_at: now,
+            attempts: 0,
+            last_attempt: None,
+            last_error: None,
+            next_attempt: now,
+        });
+        true
+    }
+
+    pub fn remove(&mut self, result: &str) {
+        self.entries.retain(|entry| entry.result != result);
+    }
+
+    // Results whose next attempt is due, in the order they were queued.
+    pub fn due(&self, now: DateTime<Utc>) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.next_attempt <= now)
+            .map(|entry| entry.result.clone())
+            .collect()
+    }
+
+    pub fn record_failure(&mut self, result: &str, error: String, now: DateTime<Utc>) {
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.result == result) {
+            entry.attempts += 1;
+            entry.last_attempt = Some(now);
+            entry.last_error = Some(error);
+            entry.next_attempt = now + backoff(entry.attempts);
+        }
+    }
+}
+
+fn local_time(time: &DateTime<Utc>) -> String {
+    time.with_timezone(&Local).format(TIME_FORMAT).to_string()
+}
+
+impl fmt::Display for SubmissionQueue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "No results waiting to be submitted to Primenet.");
+        }
+        write!(
+            f,
+            "{} result(s) waiting to be submitted to Primenet:",
+            self.len()
+        )?;
+        for entry in &self.entries {
+            write!(f, "\n\n{}", entry.result)?;
+            write!(f, "\n    Queued:       {}", local_time(&entry.queued_at))?;
+            write!(f, "\n    Attempts:     {}", entry.attempts)?;
+            if let Some(last_attempt) = &entry.last_attempt {
+                write!(f, "\n    Last attempt: {}", local_time(last_attempt))?;
+            }
+            if let Some(last_error) = &entry.last_error {
+                write!(f, "\n    Last error:   {}", last_error)?;
+            }
+            write!(f, "\n    Next attempt: {}", local_time(&entry.next_attempt))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Quarantine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} result(s) quarantined and not being submitted:",
+            self.entries.len()
+        )?;
+        for (line, note) in &self.entries {
+            write!(f, "\n\n{}\n    {}", line, note)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,296 @@
+use crate::{
+    credit::{iteration_credit, result_credit, DaysOfWork},
+    error::Error,
+    gpuowl::Progress,
+    mlucas::{stat_timing, StatTiming},
+    util::{load_json, read_nolock, save_json},
+    work_directory::{parse_assignments, WorkDirectory},
+    work_result::WorkResult,
+};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use log::{debug, warn};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+// How many measurements the rolling average is taken over.
+pub const THROUGHPUT_SAMPLES: usize = 10;
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+// mfaktc and mfakto's timestamps, e.g. "Sun Oct 18 12:00:00 2026".
+const MFAKTC_TIME_FORMAT: &str = "%a %b %e %H:%M:%S %Y";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ThroughputSource {
+    // Credit for the results finished since the last measurement, over the time they took.
+    Results,
+    // Mlucas's own timing per iteration.
+    MlucasStat,
+    // Iterations between two looks at the same checkpoint.
+    Checkpoint,
+}
+
+impl fmt::Display for ThroughputSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ThroughputSource::Results => write!(f, "completed results"),
+            ThroughputSource::MlucasStat => write!(f, "Mlucas stat timing"),
+            ThroughputSource::Checkpoint => write!(f, "checkpoint progress"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ThroughputSample {
+    pub at: DateTime<Utc>,
+    pub source: ThroughputSource,
+    pub ghz_days_per_day: f64,
+}
+
+// Where a test had got to when last looked at.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IterationMark {
+    pub exponent: u64,
+    pub iteration: u64,
+    pub at: DateTime<Utc>,
+}
+
+// How fast the client in a work directory gets through work, in GHz-days per day. It lives in the
+// work directory as throughput.json, along with what's already been measured so nothing is
+// counted twice.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Throughput {
+    // The newest THROUGHPUT_SAMPLES measurements, oldest first.
+    pub samples: Vec<ThroughputSample>,
+    // When the newest result already measured was finished.
+    pub last_result: Option<DateTime<Utc>>,
+    pub last_stat: Option<IterationMark>,
+    pub last_checkpoint: Option<IterationMark>,
+}
+
+// When a result was finished, for clients that say. Prime95 and gpuowl put a "timestamp" in their
+// JSON results, and mfaktc, mfakto and Mlucas can start text results with one in brackets.
+pub fn result_time(line: &str) -> Option<DateTime<Utc>> {
+    let line = line.trim();
+    if line.starts_with('{') {
+        let captures = Regex::new(r#""timestamp"\s*:\s*"([^"]+)""#)
+            .expect("Failed to build regex for result timestamps")
+            .captures(line)?;
+        let time = NaiveDateTime::parse_from_str(&captures[1], TIME_FORMAT).ok()?;
+        Some(Utc.from_utc_datetime(&time))
+    } else {
+        let captures = Regex::new(r"^\[([^\]]+)\]")
+            .expect("Failed to build regex for result timestamps")
+            .captures(line)?;
+        let time = NaiveDateTime::parse_from_str(captures[1].trim(), MFAKTC_TIME_FORMAT)
+            .or_else(|_| NaiveDateTime::parse_from_str(captures[1].trim(), TIME_FORMAT))
+            .ok()?;
+        Local
+            .from_local_datetime(&time)
+            .earliest()
+            .map(|time| time.with_timezone(&Utc))
+    }
+}
+
+fn days_between(from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+    (to - from).num_milliseconds() as f64 / 86_400_000.0
+}
+
+impl Throughput {
+    // A missing file just means nothing's been measured yet.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        load_json(path, "throughput file")
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        save_json(path, self)
+    }
+
+    // The rolling average, once there's anything to average.
+    pub fn ghz_days_per_day(&self) -> Option<f64> {
+        if self.samples.is_empty() {
+            None
+        } else {
+            let total = self.samples.iter().map(|sample| sample.ghz_days_per_day);
+            Some(total.sum::<f64>() / self.samples.len() as f64)
+        }
+    }
+
+    fn add_sample(&mut self, source: ThroughputSource, ghz_days_per_day: f64, at: DateTime<Utc>) {
+        if !ghz_days_per_day.is_finite() || ghz_days_per_day <= 0.0 {
+            return;
+        }
+        debug!(
+            "Measured {:.2} GHz-days/day from {}.",
+            ghz_days_per_day, source
+        );
+        self.samples.push(ThroughputSample {
+            at,
+            source,
+            ghz_days_per_day,
+        });
+        let excess = self.samples.len().saturating_sub(THROUGHPUT_SAMPLES);
+        self.samples.drain(..excess);
+    }
+
+    // Credit for the results finished since the last one measured, over the time since then. The
+    // first time through there's no last one, so the oldest result just marks the start.
+    pub fn measure_results(&mut self, lines: &[String]) {
+        let mut finished = lines
+            .iter()
+            .filter_map(|line| {
+                let time = result_time(line)?;
+                let credit = result_credit(&WorkResult::parse(line).ok()?)?;
+                Some((time, credit))
+            })
+            .collect::<Vec<_>>();
+        finished.sort_by_key(|(time, _)| *time);
+        finished.dedup();
+        let start = match self
+            .last_result
+            .or_else(|| finished.first().map(|(time, _)| *time))
+        {
+            Some(start) => start,
+            None => return,
+        };
+        let new = finished
+            .iter()
+            .filter(|(time, _)| *time > start)
+            .collect::<Vec<_>>();
+        if let Some((end, _)) = new.last() {
+            let credit = new.iter().map(|(_, credit)| credit).sum::<f64>();
+            self.add_sample(
+                ThroughputSource::Results,
+                credit / days_between(start, *end),
+                *end,
+            );
+        }
+        self.last_result = new.last().map(|(time, _)| *time).or(Some(start));
+    }
+
+    // A new stat line is worth one measurement. Repeats of the last one aren't.
+    pub fn measure_stat(&mut self, timing: StatTiming, now: DateTime<Utc>) {
+        let seen = self.last_stat.is_some_and(|last| {
+            last.exponent == timing.exponent && last.iteration == timing.iteration
+        });
+        if !seen {
+            let ghz_days_per_day =
+                iteration_credit(timing.exponent) * 86400.0 / timing.seconds_per_iteration;
+            self.add_sample(ThroughputSource::MlucasStat, ghz_days_per_day, now);
+            self.last_stat = Some(IterationMark {
+                exponent: timing.exponent,
+                iteration: timing.iteration,
+                at: now,
+            });
+        }
+    }
+
+    // Iterations done since the checkpoint was last looked at. A different exponent or a
+    // checkpoint that went backwards starts over.
+    pub fn measure_checkpoint(&mut self, progress: Progress, now: DateTime<Utc>) {
+        if let Some(last) = self.last_checkpoint {
+            if last.exponent == progress.exponent && last.iteration == progress.iteration {
+                return;
+            }
+            if last.exponent == progress.exponent && last.iteration < progress.iteration {
+                let credit = iteration_credit(progress.exponent)
+                    * (progress.iteration - last.iteration) as f64;
+                let days = days_between(last.at, now);
+                if days > 0.0 {
+                    self.add_sample(ThroughputSource::Checkpoint, credit / days, now);
+                }
+            }
+        }
+        self.last_checkpoint = Some(IterationMark {
+            exponent: progress.exponent,
+            iteration: progress.iteration,
+            at: now,
+        });
+    }
+}
+
+impl fmt::Display for Throughput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.ghz_days_per_day() {
+            Some(ghz_days_per_day) => write!(
+                f,
+                "Throughput: {:.2} GHz-days/day, averaged over {} measurement(s).",
+                ghz_days_per_day,
+                self.samples.len()
+            )?,
+            None => return write!(f, "Throughput: not measured yet."),
+        }
+        for sample in &self.samples {
+            write!(
+                f,
+                "\n    {}  {:>9.2}  from {}",
+                sample.at.with_timezone(&Local).format(TIME_FORMAT),
+                sample.ghz_days_per_day,
+                sample.source
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn read_lines(path: &Path, lock_path: &Path) -> Vec<String> {
+    if !path.exists() {
+        return Vec::new();
+    }
+    match read_nolock(path, lock_path) {
+        Ok(contents) => contents.lines().map(str::to_string).collect(),
+        Err(e) => {
+            debug!("Not measuring throughput from {}. {}", path.display(), e);
+            Vec::new()
+        }
+    }
+}
+
+// Take whatever new measurements the work directory has to offer and add them to its rolling
+// average. Files that are locked are left for next time.
+pub fn measure_throughput(work_directory: &WorkDirectory) -> Result<Throughput, Error> {
+    let mut throughput = Throughput::load(&work_directory.throughput)?;
+    let now = Utc::now();
+    let mut results = read_lines(
+        &work_directory.results_sent,
+        &work_directory.results_sent_lock,
+    );
+    results.extend(read_lines(
+        &work_directory.results,
+        &work_directory.results_lock,
+    ));
+    throughput.measure_results(&results);
+    let worktodo = read_lines(&work_directory.worktodo, &work_directory.worktodo_lock);
+    // The client works through its worktodo from the top, so the first assignment is the one
+    // it's running.
+    if let Some(current) = parse_assignments(&worktodo).first() {
+        if let Some(timing) = stat_timing(&work_directory.path, current.exponent) {
+            throughput.measure_stat(timing, now);
+        }
+        if let Some(progress) = work_directory.progress(current) {
+            throughput.measure_checkpoint(progress, now);
+        }
+    }
+    throughput.save(&work_directory.throughput)?;
+    Ok(throughput)
+}
+
+// Put --days-of-work together with --throughput, or failing that with what's been measured.
+pub fn days_of_work_with(
+    days: Option<f64>,
+    configured: Option<f64>,
+    measured: Option<f64>,
+) -> Option<DaysOfWork> {
+    let days = days?;
+    match configured.or(measured) {
+        Some(throughput) => Some(DaysOfWork { days, throughput }),
+        None => {
+            warn!(
+                "No throughput has been measured in this work directory yet, and none was given \
+                with --throughput. Caching by --num-cache until there is one."
+            );
+            None
+        }
+    }
+}
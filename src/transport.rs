@@ -0,0 +1,391 @@
+use crate::{
+    cookie_jar::CookieJar,
+    error::Error,
+    logging::NETWORK,
+    options::{GlobalOptions, TrafficOptions},
+    recording::{RecordingTransport, ReplayTransport},
+    retry::{RetryStats, RetryingTransport},
+};
+use chrono::Utc;
+use log::{debug, trace, warn};
+use reqwest::blocking::{
+    multipart::{Form, Part},
+    Client, ClientBuilder,
+};
+use reqwest::header::{COOKIE, LOCATION, SET_COOKIE};
+use reqwest::redirect::Policy;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::error::Error as StdError;
+use std::fmt;
+use std::io::{Error as IoError, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// Whatever went wrong below HTTP. For the reqwest transport this is a reqwest::Error.
+pub type TransportError = Box<dyn StdError + Send + Sync + 'static>;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Method {
+    Get,
+    Post,
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Method::Get => write!(f, "GET"),
+            Method::Post => write!(f, "POST"),
+        }
+    }
+}
+
+// A file sent as the only part of a multipart/form-data body.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Upload {
+    pub field: String,
+    pub file_name: String,
+    // Left out of recordings, which would otherwise hold whole proof files. Replays only compare
+    // the method and URL anyway.
+    #[serde(skip)]
+    pub data: Vec<u8>,
+}
+
+// Everything the runtimes need to say to a server. Query parameters go in the URL, form parameters
+// in a url-encoded body, and an upload in a multipart body instead.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Request {
+    pub method: Method,
+    pub url: String,
+    pub query: Vec<(String, String)>,
+    pub form: Vec<(String, String)>,
+    pub basic_auth: Option<(String, String)>,
+    #[serde(default)]
+    pub upload: Option<Upload>,
+}
+
+fn to_pairs(params: &[(&str, &str)]) -> Vec<(String, String)> {
+    params
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+impl Request {
+    pub fn get(url: &str) -> Self {
+        Request {
+            method: Method::Get,
+            url: url.to_string(),
+            query: Vec::new(),
+            form: Vec::new(),
+            basic_auth: None,
+            upload: None,
+        }
+    }
+
+    pub fn post(url: &str) -> Self {
+        Request {
+            method: Method::Post,
+            ..Request::get(url)
+        }
+    }
+
+    pub fn query(mut self, params: &[(&str, &str)]) -> Self {
+        self.query.extend(to_pairs(params));
+        self
+    }
+
+    pub fn form(mut self, params: &[(&str, &str)]) -> Self {
+        self.form.extend(to_pairs(params));
+        self
+    }
+
+    pub fn upload(mut self, field: &str, file_name: &str, data: Vec<u8>) -> Self {
+        self.upload = Some(Upload {
+            field: field.to_string(),
+            file_name: file_name.to_string(),
+            data,
+        });
+        self
+    }
+
+    pub fn basic_auth(mut self, username: &str, password: &str) -> Self {
+        self.basic_auth = Some((username.to_string(), password.to_string()));
+        self
+    }
+
+    // The URL with the query string it's sent with.
+    pub fn full_url(&self) -> String {
+        if self.query.is_empty() {
+            return self.url.clone();
+        }
+        Url::parse_with_params(&self.url, &self.query)
+            .map(String::from)
+            .unwrap_or_else(|_| self.url.clone())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Response {
+    pub status: u16,
+    // Where the response actually came from, after any redirects.
+    pub url: String,
+    pub body: String,
+}
+
+// How the runtimes talk to Primenet and GPU to 72. Any session state (cookies) is the transport's
+// business, so a logged in transport stays logged in for later requests.
+pub trait Transport {
+    fn send(&self, request: &Request) -> Result<Response, TransportError>;
+
+    // Retry statistics since the last call, for transports that retry.
+    fn take_stats(&self) -> Option<RetryStats> {
+        None
+    }
+}
+
+// Lets wrappers borrow a transport instead of owning it.
+impl<T: Transport + ?Sized> Transport for &T {
+    fn send(&self, request: &Request) -> Result<Response, TransportError> {
+        (**self).send(request)
+    }
+
+    fn take_stats(&self) -> Option<RetryStats> {
+        (**self).take_stats()
+    }
+}
+
+// Redirects followed for a single request before giving up.
+const MAX_REDIRECTS: usize = 10;
+
+fn multipart_form(upload: &Upload) -> Result<Form, TransportError> {
+    let part = Part::bytes(upload.data.clone())
+        .file_name(upload.file_name.clone())
+        .mime_str("application/octet-stream")?;
+    Ok(Form::new().part(upload.field.clone(), part))
+}
+
+pub struct ReqwestTransport {
+    client: Client,
+    cookies: Mutex<CookieJar>,
+    // Where the cookie jar is kept between runs, if anywhere.
+    cookie_jar_path: Option<PathBuf>,
+}
+
+impl ReqwestTransport {
+    pub fn new() -> Result<Self, Error> {
+        ReqwestTransport::build(CookieJar::default(), None)
+    }
+
+    // Primenet keeps the login in a cookie, so saving the cookies to `path` after every change and
+    // loading them here lets a later run carry on with the same session.
+    pub fn with_cookie_jar<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        ReqwestTransport::build(ReqwestTransport::load_cookies(&path)?, Some(path))
+    }
+
+    // Carry on with a saved session without saving any changes to it, for --dry-run.
+    pub fn with_read_only_cookie_jar<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        ReqwestTransport::build(ReqwestTransport::load_cookies(path.as_ref())?, None)
+    }
+
+    fn load_cookies(path: &Path) -> Result<CookieJar, Error> {
+        let cookies = CookieJar::load(path)?;
+        debug!(
+            target: NETWORK,
+            "Loaded {} cookie(s) from {}",
+            cookies.len(),
+            path.display()
+        );
+        Ok(cookies)
+    }
+
+    // Cookies and redirects are handled here rather than by reqwest, so that cookies set along a
+    // redirect chain still end up in our jar.
+    fn build(cookies: CookieJar, cookie_jar_path: Option<PathBuf>) -> Result<Self, Error> {
+        ClientBuilder::default()
+            .redirect(Policy::none())
+            .build()
+            .map(|client| ReqwestTransport {
+                client,
+                cookies: Mutex::new(cookies),
+                cookie_jar_path,
+            })
+            .map_err(|e| Error::network("Failed to build web client.", e))
+    }
+
+    fn store_cookies(&self, response: &reqwest::blocking::Response) {
+        let now = Utc::now();
+        let mut cookies = self.cookies.lock().unwrap();
+        let mut changed = false;
+        for header in response.headers().get_all(SET_COOKIE) {
+            if let Ok(header) = header.to_str() {
+                changed |= cookies.store(response.url(), header, now);
+            }
+        }
+        if let (true, Some(path)) = (changed, &self.cookie_jar_path) {
+            // Losing the saved session only costs a login next time.
+            match cookies.save(path) {
+                Ok(()) => trace!(target: NETWORK, "Saved cookies to {}", path.display()),
+                Err(e) => warn!("{}", e),
+            }
+        }
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn send(&self, request: &Request) -> Result<Response, TransportError> {
+        let mut method = request.method;
+        let mut url = Url::parse(&request.url)?;
+        // A redirect's Location is the whole URL, and a redirected POST becomes a plain GET.
+        let mut redirected = false;
+        for _ in 0..=MAX_REDIRECTS {
+            let mut builder = match method {
+                Method::Get => self.client.get(url.clone()),
+                Method::Post => self.client.post(url.clone()),
+            };
+            if !redirected && !request.query.is_empty() {
+                builder = builder.query(&request.query);
+            }
+            if method == Method::Post && !request.form.is_empty() {
+                builder = builder.form(&request.form);
+            }
+            if let (Method::Post, Some(upload)) = (method, &request.upload) {
+                builder = builder.multipart(multipart_form(upload)?);
+            }
+            if let Some((username, password)) = &request.basic_auth {
+                builder = builder.basic_auth(username, Some(password));
+            }
+            let cookie_header = self.cookies.lock().unwrap().header(&url, Utc::now());
+            if let Some(cookie_header) = cookie_header {
+                builder = builder.header(COOKIE, cookie_header);
+            }
+            let response = builder.send()?;
+            self.store_cookies(&response);
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok());
+            if let (true, Some(location)) = (response.status().is_redirection(), location) {
+                let next = response.url().join(location)?;
+                trace!(
+                    target: NETWORK,
+                    "{} {} redirected to {}",
+                    method,
+                    response.url(),
+                    next
+                );
+                let status = response.status().as_u16();
+                if status == 303 || (method == Method::Post && (status == 301 || status == 302)) {
+                    method = Method::Get;
+                }
+                url = next;
+                redirected = true;
+                continue;
+            }
+            let status = response.status().as_u16();
+            let url = response.url().to_string();
+            let body = response.text()?;
+            return Ok(Response { status, url, body });
+        }
+        Err(IoError::other(format!(
+            "Too many redirects for {} {}",
+            request.method, request.url
+        ))
+        .into())
+    }
+}
+
+// In-memory transport that answers with canned responses in order, and remembers what it was
+// sent. Running out of responses is a transport error like any other.
+#[derive(Default)]
+pub struct ScriptedTransport {
+    responses: Mutex<VecDeque<Result<Response, (ErrorKind, String)>>>,
+    requests: Mutex<Vec<Request>>,
+}
+
+impl ScriptedTransport {
+    pub fn new() -> Self {
+        ScriptedTransport::default()
+    }
+
+    pub fn respond(&self, status: u16, body: &str) -> &Self {
+        self.responses.lock().unwrap().push_back(Ok(Response {
+            status,
+            url: String::new(),
+            body: body.to_string(),
+        }));
+        self
+    }
+
+    // The next request fails as if the connection couldn't be made.
+    pub fn fail(&self, message: &str) -> &Self {
+        self.fail_with(ErrorKind::ConnectionRefused, message)
+    }
+
+    // The next request fails as if it was sent but no answer came back in time.
+    pub fn time_out(&self, message: &str) -> &Self {
+        self.fail_with(ErrorKind::TimedOut, message)
+    }
+
+    fn fail_with(&self, kind: ErrorKind, message: &str) -> &Self {
+        self.responses
+            .lock()
+            .unwrap()
+            .push_back(Err((kind, message.to_string())));
+        self
+    }
+
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.responses.lock().unwrap().len()
+    }
+}
+
+impl Transport for ScriptedTransport {
+    fn send(&self, request: &Request) -> Result<Response, TransportError> {
+        trace!(target: NETWORK, "Scripted {} {}", request.method, request.url);
+        self.requests.lock().unwrap().push(request.clone());
+        match self.responses.lock().unwrap().pop_front() {
+            Some(Ok(response)) => Ok(Response {
+                url: request.url.clone(),
+                ..response
+            }),
+            Some(Err((kind, message))) => Err(IoError::new(kind, message).into()),
+            None => Err(IoError::new(
+                ErrorKind::UnexpectedEof,
+                format!(
+                    "No scripted response left for {} {}",
+                    request.method, request.url
+                ),
+            )
+            .into()),
+        }
+    }
+}
+
+// Live traffic, or traffic saved to/served from a recording directory. Recordings hold the outcome
+// after any retries, so replaying them doesn't retry again. Live traffic keeps its session in the
+// cookie jar file, if there is one.
+pub fn build_transport(global_options: &GlobalOptions) -> Result<Box<dyn Transport>, Error> {
+    let live = || {
+        match &global_options.cookie_jar {
+            Some(path) if global_options.dry_run => {
+                ReqwestTransport::with_read_only_cookie_jar(path)
+            }
+            Some(path) => ReqwestTransport::with_cookie_jar(path),
+            None => ReqwestTransport::new(),
+        }
+        .map(|t| RetryingTransport::new(t, global_options.retry_policy))
+    };
+    Ok(match &global_options.traffic {
+        TrafficOptions::Live => Box::new(live()?),
+        TrafficOptions::Record(dir) => Box::new(RecordingTransport::new(live()?, dir)?),
+        TrafficOptions::Replay(dir) => Box::new(ReplayTransport::new(dir)?),
+    })
+}
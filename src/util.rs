@@ -1,60 +1,225 @@
-use std::error::Error;
-use std::fs::{remove_file, File, OpenOptions};
-use std::io::{BufReader, Error as IoError, ErrorKind, Read, Result as IoResult};
-use std::path::Path;
+use crate::{error::Error, logging::LOCKING};
+use chrono::{DateTime, Utc};
+use log::{debug, trace};
+use serde::{de::DeserializeOwned, Serialize};
+use std::fs::{remove_file, rename, write, File, OpenOptions};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::from_utf8;
 
-pub fn lock_file(lockfile_path: &Path) -> IoResult<()> {
+// worktodo.txt -> worktodo.txt.lck, same as the Python scripts and the clients themselves.
+pub fn lockfile_path(file_path: &Path) -> PathBuf {
+    let mut lockfile_name = file_path.as_os_str().to_owned();
+    lockfile_name.push(".lck");
+    PathBuf::from(lockfile_name)
+}
+
+pub fn lock_file(lockfile_path: &Path) -> Result<(), Error> {
     OpenOptions::new()
         .read(true)
         .write(true)
         // Essentially like opening with O_EXCL
         .create_new(true)
         .open(lockfile_path)
-        .map(|_| ())
+        .map(|_| trace!(target: LOCKING, "Locked {}", lockfile_path.display()))
+        .map_err(|e| {
+            if e.kind() == ErrorKind::AlreadyExists {
+                debug!(target: LOCKING, "Found lockfile {}", lockfile_path.display());
+                Error::LockContention(lockfile_path.to_path_buf())
+            } else {
+                Error::io(
+                    format!("Failed to create lockfile {}.", lockfile_path.display()),
+                    e,
+                )
+            }
+        })
 }
 
-pub fn unlock_file(lockfile_path: &Path) -> IoResult<()> {
+pub fn unlock_file(lockfile_path: &Path) -> Result<(), Error> {
     if Path::new(&lockfile_path).exists() {
-        remove_file(&lockfile_path)
+        trace!(target: LOCKING, "Unlocking {}", lockfile_path.display());
+        remove_file(lockfile_path).map_err(|e| {
+            Error::io(
+                format!("Could not remove lockfile {}.", lockfile_path.display()),
+                e,
+            )
+        })
     } else {
         Ok(())
     }
 }
 
+// Take each lock in order, releasing the ones already taken if a later one can't be.
+pub fn lock_all(lockfile_paths: &[&Path]) -> Result<(), Error> {
+    for (i, lockfile_path) in lockfile_paths.iter().enumerate() {
+        if let Err(e) = lock_file(lockfile_path) {
+            for taken in &lockfile_paths[..i] {
+                let _ = unlock_file(taken);
+            }
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+pub fn unlock_all(lockfile_paths: &[&Path]) -> Result<(), Error> {
+    // Try to remove every lock even if one of them fails, then report the first failure.
+    let mut result = Ok(());
+    for lockfile_path in lockfile_paths {
+        let unlocked = unlock_file(lockfile_path);
+        if result.is_ok() {
+            result = unlocked;
+        }
+    }
+    result
+}
+
+fn read_to_string(file_path: &Path) -> Result<String, Error> {
+    let mut file_contents = String::new();
+    File::open(file_path)
+        .and_then(|file| BufReader::new(file).read_to_string(&mut file_contents))
+        .map_err(|e| Error::io(format!("Failed to read {}.", file_path.display()), e))?;
+    Ok(file_contents)
+}
+
 // Read a file without locking it
-pub fn read_nolock(file_path: &Path, lockfile_path: &Path) -> IoResult<String> {
+pub fn read_nolock(file_path: &Path, lockfile_path: &Path) -> Result<String, Error> {
     if lockfile_path.exists() {
-        Err(IoError::new(
-            ErrorKind::Other,
-            format!("Found lockfile: {}", lockfile_path.display()),
-        ))
+        Err(Error::LockContention(lockfile_path.to_path_buf()))
     } else {
-        let mut file_contents = String::new();
-        BufReader::new(File::open(file_path)?).read_to_string(&mut file_contents)?;
-        Ok(file_contents)
+        read_to_string(file_path)
     }
 }
 
+// Read a list file whose lock is already held by the caller
+pub fn read_list(file_path: &Path) -> Result<Vec<String>, Error> {
+    let lines = read_to_string(file_path)?
+        .lines()
+        .map(|line| line.trim().to_string())
+        .collect::<Vec<_>>();
+    Ok(lines)
+}
+
 // Read a list file and lock it
-pub fn read_list_lock(file_path: &Path, lockfile_path: &Path) -> IoResult<Vec<String>> {
-    if lockfile_path.exists() {
-        Err(IoError::new(
-            ErrorKind::Other,
-            format!("Found lockfile: {}", lockfile_path.display()),
-        ))
-    } else {
-        lock_file(lockfile_path)?;
-        let mut file_contents = String::new();
-        BufReader::new(File::open(file_path)?).read_to_string(&mut file_contents)?;
-        let lines = file_contents
-            .lines()
-            .map(|line| line.trim().to_string())
-            .collect::<Vec<_>>();
-        Ok(lines)
+pub fn read_list_lock(file_path: &Path, lockfile_path: &Path) -> Result<Vec<String>, Error> {
+    lock_file(lockfile_path)?;
+    // Don't leave the lock behind if we couldn't read the file we took it for.
+    read_list(file_path).inspect_err(|_| {
+        let _ = unlock_file(lockfile_path);
+    })
+}
+
+// Append jobs to a worktodo file whose lock is already held by the caller. On any errors until
+// everything is written, the error message shows what hasn't yet been written and asks the user to
+// add it themselves.
+pub fn append_jobs(worktodo_path: &Path, jobs: &[String]) -> Result<(), Error> {
+    let mut list_file = BufWriter::new(
+        OpenOptions::new()
+            .append(true)
+            .open(worktodo_path)
+            .map_err(|e| {
+                Error::io(
+                    error_msg_with_jobs("Failed to open worktodo file.", jobs),
+                    e,
+                )
+            })?,
+    );
+    for (i, job) in jobs.iter().enumerate() {
+        list_file
+            .write_all(job.as_bytes())
+            .and_then(|_| list_file.write_all(b"\n"))
+            .map_err(|e| {
+                Error::io(
+                    error_msg_with_jobs("Failed to write to worktodo file.", &jobs[i..]),
+                    e,
+                )
+            })?;
+    }
+    list_file.flush().map_err(|e| {
+        Error::io(
+            error_msg_with_unwritten(
+                "Failed to flush buffered reader to worktodo file.",
+                from_utf8(list_file.buffer()).unwrap(),
+            ),
+            e,
+        )
+    })
+}
+
+// Replace the contents of a list file whose lock is already held by the caller.
+pub fn write_list(file_path: &Path, lines: &[String]) -> Result<(), Error> {
+    let mut contents = lines.join("\n");
+    if !contents.is_empty() {
+        contents.push('\n');
     }
+    write(file_path, contents)
+        .map_err(|e| Error::io(format!("Failed to write {}.", file_path.display()), e))
+}
+
+// Append to a list file whose lock is already held by the caller, creating it if needed.
+pub fn append_list(file_path: &Path, lines: &[String]) -> Result<(), Error> {
+    if lines.is_empty() {
+        return Ok(());
+    }
+    let mut contents = lines.join("\n");
+    contents.push('\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(file_path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .map_err(|e| Error::io(format!("Failed to append to {}.", file_path.display()), e))
+}
+
+// Append lines that failed a check to a quarantine file, each after a comment saying when and why,
+// so they can be looked at and put back by hand.
+pub fn append_quarantine(
+    file_path: &Path,
+    entries: &[(String, String)],
+    now: DateTime<Utc>,
+) -> Result<(), Error> {
+    let lines = entries
+        .iter()
+        .flat_map(|(line, reason)| {
+            vec![
+                format!("# {}: {}", now.format("%Y-%m-%d %H:%M:%S UTC"), reason),
+                line.clone(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    append_list(file_path, &lines)
+}
+
+// Load state kept in a JSON file in the work directory. A missing file is the default state.
+// `what` names the file in errors.
+pub fn load_json<T: DeserializeOwned + Default>(path: &Path, what: &str) -> Result<T, Error> {
+    match std::fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json).map_err(|e| {
+            Error::Parse(format!(
+                "Failed to parse {} {}. Error: {}",
+                what,
+                path.display(),
+                e
+            ))
+        }),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(Error::io(format!("Failed to read {}.", path.display()), e)),
+    }
+}
+
+// Written to a temporary file and renamed over the old one, so a crash part way through can't
+// leave a truncated file behind.
+pub fn save_json<T: Serialize>(path: &Path, value: &T) -> Result<(), Error> {
+    let json = serde_json::to_string_pretty(value).expect("Failed to serialize state");
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let temp_path = PathBuf::from(temp_path);
+    write(&temp_path, json)
+        .and_then(|_| rename(&temp_path, path))
+        .map_err(|e| Error::io(format!("Failed to write {}.", path.display()), e))
 }
 
-pub fn error_msg_with_jobs<E: Error>(e: E, msg_start: &str, unwritten_jobs: &[String]) -> String {
+pub fn error_msg_with_jobs(msg_start: &str, unwritten_jobs: &[String]) -> String {
     let mut msg = format!("{}\n\n", msg_start);
     msg.push_str("Jobs queued to be written to worktodo:\n");
     for job in unwritten_jobs {
@@ -62,13 +227,13 @@ pub fn error_msg_with_jobs<E: Error>(e: E, msg_start: &str, unwritten_jobs: &[St
         msg.push('\n');
     }
     msg.push_str("\nPlease add these to your worktodo manually.");
-    format!("{}\n\nError: {}", msg, e)
+    msg
 }
 
-pub fn error_msg_with_unwritten<E: Error>(e: E, msg_start: &str, unwritten: &str) -> String {
+pub fn error_msg_with_unwritten(msg_start: &str, unwritten: &str) -> String {
     let mut msg = format!("{}\n\n", msg_start);
     msg.push_str("Jobs queued to be written to worktodo:\n");
     msg.push_str(unwritten);
     msg.push_str("\nPlease add these to your worktodo manually.");
-    format!("{}\n\nError: {}", msg, e)
+    msg
 }
@@ -0,0 +1,268 @@
+use crate::{
+    assignment::{is_prime, is_worktodo_filler, Assignment, ExponentRange},
+    client::{CheckpointFormat, Client},
+    error::Error,
+    gpuowl::{checkpoint_progress, Progress},
+    logging::PARSING,
+    mlucas::stat_timing,
+    util::{append_quarantine, lockfile_path, unlock_all, write_list},
+};
+use chrono::Utc;
+use log::{error, warn};
+use std::path::{Path, PathBuf};
+
+// The files a client works out of, along with the lockfiles that guard them.
+#[derive(Clone, Debug)]
+pub struct WorkDirectory {
+    pub path: PathBuf,
+    // The client working out of the directory, if we've been told.
+    pub client: Option<Client>,
+    pub worktodo: PathBuf,
+    pub worktodo_lock: PathBuf,
+    // Assignments that failed a check and won't be run. Only written with the worktodo locked.
+    pub worktodo_quarantine: PathBuf,
+    pub results: PathBuf,
+    pub results_lock: PathBuf,
+    pub results_sent: PathBuf,
+    pub results_sent_lock: PathBuf,
+    // Results that failed a local check and won't be submitted. Only written with results.txt
+    // locked.
+    pub results_quarantine: PathBuf,
+    // Results waiting to be accepted by Primenet, see SubmissionQueue.
+    pub queue: PathBuf,
+    pub queue_lock: PathBuf,
+    // Measured throughput, see Throughput.
+    pub throughput: PathBuf,
+    // When work was last fetched and submitted, see Activity.
+    pub activity: PathBuf,
+}
+
+impl WorkDirectory {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref().to_path_buf();
+        // Mlucas uses worktodo.ini, everything else uses worktodo.txt.
+        let worktodo_txt = path.join("worktodo.txt");
+        let worktodo = if worktodo_txt.exists() {
+            worktodo_txt
+        } else {
+            path.join("worktodo.ini")
+        };
+        let results = path.join("results.txt");
+        WorkDirectory::with_files(path, None, worktodo, results)
+    }
+
+    // A work directory for a known client, using that client's file names instead of guessing.
+    pub fn for_client<P: AsRef<Path>>(path: P, client: Client) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let worktodo = path.join(client.worktodo_file());
+        let results = path.join(client.results_file());
+        WorkDirectory::with_files(path, Some(client), worktodo, results)
+    }
+
+    // Whatever the runtimes were told about the directory.
+    pub fn from_options<P: AsRef<Path>>(path: P, client: Option<Client>) -> Self {
+        match client {
+            Some(client) => WorkDirectory::for_client(path, client),
+            None => WorkDirectory::new(path),
+        }
+    }
+
+    fn with_files(
+        path: PathBuf,
+        client: Option<Client>,
+        worktodo: PathBuf,
+        results: PathBuf,
+    ) -> Self {
+        let results_sent = path.join("results.sent");
+        let queue = path.join("results.queue.json");
+        let results_quarantine = path.join("results.quarantine");
+        let worktodo_quarantine = path.join("worktodo.quarantine");
+        let throughput = path.join("throughput.json");
+        let activity = path.join("activity.json");
+        WorkDirectory {
+            worktodo_lock: lockfile_path(&worktodo),
+            results_lock: lockfile_path(&results),
+            results_sent_lock: lockfile_path(&results_sent),
+            queue_lock: lockfile_path(&queue),
+            path,
+            client,
+            worktodo,
+            worktodo_quarantine,
+            results,
+            results_sent,
+            results_quarantine,
+            queue,
+            throughput,
+            activity,
+        }
+    }
+
+    // Whether the client can run an assignment. Anything goes when we don't know the client.
+    pub fn can_run(&self, assignment: &Assignment) -> bool {
+        self.client
+            .map_or(true, |client| client.can_run(assignment.kind))
+    }
+
+    // The assignments the client can run, warning about the rest.
+    pub fn runnable(&self, assignments: Vec<Assignment>) -> Vec<Assignment> {
+        let (runnable, unrunnable): (Vec<_>, Vec<_>) = assignments
+            .into_iter()
+            .partition(|assignment| self.can_run(assignment));
+        if let Some(client) = self.client {
+            for assignment in &unrunnable {
+                warn!(
+                    "Not caching '{}', since {} can't run {} assignments. You may want to \
+                    unreserve it.",
+                    assignment, client, assignment.kind
+                );
+            }
+        }
+        runnable
+    }
+
+    // Why an assignment isn't worth running, if it isn't: every Mersenne number worth testing has a
+    // prime exponent, and it has to be one the client can handle.
+    pub fn assignment_problem(&self, assignment: &Assignment) -> Option<String> {
+        let exponent = assignment.exponent;
+        let client_max = self
+            .client
+            .and_then(|client| Some((client, client.max_exponent()?)));
+        if !is_prime(exponent) {
+            Some(format!("the exponent {} isn't prime", exponent))
+        } else if let Some((client, max)) = client_max.filter(|(_, max)| exponent > *max) {
+            Some(format!(
+                "M{} is above the largest exponent {} can handle, {}",
+                exponent, client, max
+            ))
+        } else {
+            None
+        }
+    }
+
+    fn quarantine(&self, quarantined: &[(Assignment, String)]) -> Result<(), Error> {
+        for (assignment, reason) in quarantined {
+            error!(
+                "Quarantined '{}', since {}. You may want to unreserve it.",
+                assignment, reason
+            );
+        }
+        let entries = quarantined
+            .iter()
+            .map(|(assignment, reason)| (assignment.line.clone(), reason.clone()))
+            .collect::<Vec<_>>();
+        append_quarantine(&self.worktodo_quarantine, &entries, Utc::now())
+    }
+
+    // Move fetched assignments that aren't worth running, or that aren't in the range we asked for,
+    // to the quarantine file, returning the rest. Only safe to call with the worktodo locked.
+    pub fn quarantine_invalid(
+        &self,
+        assignments: Vec<Assignment>,
+        exponent_range: ExponentRange,
+    ) -> Result<Vec<Assignment>, Error> {
+        let mut valid = Vec::new();
+        let mut quarantined = Vec::new();
+        for assignment in assignments {
+            let problem = self.assignment_problem(&assignment).or_else(|| {
+                if exponent_range.contains(assignment.exponent) {
+                    None
+                } else {
+                    Some(format!(
+                        "M{} is outside the requested range of {}",
+                        assignment.exponent, exponent_range
+                    ))
+                }
+            });
+            match problem {
+                Some(reason) => quarantined.push((assignment, reason)),
+                None => valid.push(assignment),
+            }
+        }
+        self.quarantine(&quarantined)?;
+        Ok(valid)
+    }
+
+    // Split worktodo lines into the ones to keep and the assignments that aren't worth running,
+    // with why not. Anything that isn't an assignment is kept. The requested exponent range isn't
+    // checked here: it's for what we ask for, and lines already in the worktodo are reserved, maybe
+    // being run right now.
+    pub fn sort_worktodo(&self, lines: Vec<String>) -> (Vec<String>, Vec<(Assignment, String)>) {
+        let mut kept = Vec::new();
+        let mut quarantined = Vec::new();
+        for line in lines {
+            let problem = if is_worktodo_filler(&line) {
+                None
+            } else {
+                Assignment::parse(&line).ok().and_then(|assignment| {
+                    let reason = self.assignment_problem(&assignment)?;
+                    Some((assignment, reason))
+                })
+            };
+            match problem {
+                Some(problem) => quarantined.push(problem),
+                None => kept.push(line),
+            }
+        }
+        (kept, quarantined)
+    }
+
+    // Take assignments that aren't worth running out of the worktodo lines just read, and out of
+    // the worktodo file, returning the lines left. Only safe to call with the worktodo locked.
+    pub fn check_worktodo(&self, lines: Vec<String>) -> Result<Vec<String>, Error> {
+        let (kept, quarantined) = self.sort_worktodo(lines);
+        if !quarantined.is_empty() {
+            // Quarantined before they're taken out, so a failure in between can't lose them.
+            self.quarantine(&quarantined)?;
+            write_list(&self.worktodo, &kept)?;
+        }
+        Ok(kept)
+    }
+
+    // How far the client has got with an assignment, for clients whose checkpoints or stat files
+    // we can read. See CheckpointFormat::has_readable_progress.
+    pub fn progress(&self, assignment: &Assignment) -> Option<Progress> {
+        match self.client?.checkpoint_format() {
+            CheckpointFormat::Gpuowl => checkpoint_progress(&self.path, assignment.exponent),
+            CheckpointFormat::Mlucas => {
+                stat_timing(&self.path, assignment.exponent).map(|timing| Progress {
+                    exponent: assignment.exponent,
+                    iteration: timing.iteration,
+                    iterations: assignment.exponent,
+                })
+            }
+            CheckpointFormat::Mfaktc | CheckpointFormat::CudaLucas | CheckpointFormat::Mprime => {
+                None
+            }
+        }
+    }
+
+    // Every lockfile we might take, in the order they have to be taken.
+    pub fn lockfile_paths(&self) -> [&Path; 4] {
+        [
+            &self.worktodo_lock,
+            &self.results_lock,
+            &self.results_sent_lock,
+            &self.queue_lock,
+        ]
+    }
+
+    // Remove any lockfiles left behind. Only safe to call when the error that brought us here
+    // wasn't caused by somebody else's lock.
+    pub fn unlock_all(&self) -> Result<(), Error> {
+        unlock_all(&self.lockfile_paths())
+    }
+}
+
+// Parse the lines of a worktodo file, skipping comments and blank lines. Lines that aren't
+// recognised are left alone in the file but don't count as assignments.
+pub fn parse_assignments(lines: &[String]) -> Vec<Assignment> {
+    lines
+        .iter()
+        .filter(|line| !is_worktodo_filler(line))
+        .filter_map(|line| {
+            Assignment::parse(line)
+                .map_err(|e| warn!(target: PARSING, "Skipping worktodo line. {}", e))
+                .ok()
+        })
+        .collect()
+}
@@ -0,0 +1,110 @@
+use crate::{
+    error::Error,
+    factor::{check_factor, factor_info, FactorInfo, FactorProblem},
+};
+use regex::Regex;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ResultKind {
+    NoFactor,
+    // The factor as written by the client, in decimal.
+    Factor(String),
+    Composite,
+    Prime,
+    // A PRP proof certified, with the hash of the final residue for the server to check.
+    Cert,
+    // Anything we can find an exponent in but don't otherwise understand, e.g. P-1 with no factor
+    // or ECM results. These still get submitted as-is.
+    Unknown,
+}
+
+// A single line of a results file, e.g.
+//     no factor for M332194529 from 2^74 to 2^75 [mfaktc 0.21 barrett76_mul32_gs]
+//     M89459323 has a factor: 1193028769481932346719
+//     {"status":"C", "exponent":96364649, "worktype":"PRP-3", ...}
+#[derive(Clone, Debug, PartialEq)]
+pub struct WorkResult {
+    pub exponent: u64,
+    pub kind: ResultKind,
+    pub line: String,
+}
+
+impl WorkResult {
+    pub fn parse(line: &str) -> Result<Self, Error> {
+        let line = line.trim();
+        let exponent_regex = if line.starts_with('{') {
+            Regex::new(r#""exponent"\s*:\s*"?([0-9]+)"#)
+        } else {
+            Regex::new(r"M([0-9]+)")
+        }
+        .expect("Failed to build regex for result parsing");
+        let exponent = exponent_regex
+            .captures(line)
+            .and_then(|captures| captures[1].parse::<u64>().ok())
+            .ok_or_else(|| {
+                Error::Parse(format!(
+                    "Couldn't find the exponent in result line '{}'.",
+                    line
+                ))
+            })?;
+        // gpuowl reports P-1 factors in JSON, e.g. "factors":["1193028769481932346719"].
+        let factor_regex =
+            Regex::new(r#"has a factor:\s*([0-9]+)|"factors"\s*:\s*\[\s*"?([0-9]+)"#)
+                .expect("Failed to build regex for result parsing");
+        let kind = if let Some(captures) = factor_regex.captures(line) {
+            let factor = captures.get(1).or_else(|| captures.get(2)).unwrap();
+            ResultKind::Factor(factor.as_str().to_string())
+        } else if Regex::new(r#""worktype"\s*:\s*"Cert""#)
+            .expect("Failed to build regex for result parsing")
+            .is_match(line)
+        {
+            ResultKind::Cert
+        } else if line.contains("no factor for") || line.contains(r#""status":"NF""#) {
+            ResultKind::NoFactor
+        } else if line.contains("is not prime") || line.contains(r#""status":"C""#) {
+            ResultKind::Composite
+        } else if line.contains("is prime") || line.contains(r#""status":"P""#) {
+            ResultKind::Prime
+        } else {
+            ResultKind::Unknown
+        };
+        Ok(WorkResult {
+            exponent,
+            kind,
+            line: line.to_string(),
+        })
+    }
+
+    // Bit size, k and class of a reported factor, for factors of the form 2kp+1.
+    pub fn factor_info(&self) -> Option<FactorInfo> {
+        match &self.kind {
+            ResultKind::Factor(factor) => factor_info(self.exponent, factor),
+            _ => None,
+        }
+    }
+
+    // Check a reported factor before it goes anywhere near Primenet. A factor that doesn't divide
+    // M<exponent> means the client (or the GPU it runs on) got something wrong.
+    pub fn verify_factor(&self) -> Result<(), FactorProblem> {
+        match &self.kind {
+            ResultKind::Factor(factor) => check_factor(self.exponent, factor),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl FromStr for WorkResult {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        WorkResult::parse(s)
+    }
+}
+
+impl fmt::Display for WorkResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.line)
+    }
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use common::{example_workdir, lines};
+use primenet_rs::{
+    assignment::{AssignmentKind, ExponentRange},
+    client::{CheckpointFormat, Client, ResultsFormat},
+    endpoints::Endpoints,
+    p95_work::PrimenetWorkType,
+    primenet_runtime::{primenet_request, primenet_submit},
+    transport::ScriptedTransport,
+    work_directory::WorkDirectory,
+};
+use std::fs::write;
+
+const TF_1: &str = "Factor=3F2A0C6BA2B55A2F8E1C9D7E6B5A4F30,332194529,74,75";
+const DC_1: &str = "DoubleCheck=7A30B8B6C0FC79C534A271D9561F7DCC,51234577,73,1";
+const PRP_RESULT: &str =
+    r#"{"status":"C", "exponent":96364649, "worktype":"PRP-3", "res64":"1A2B3C4D5E6F7081"}"#;
+
+#[test]
+fn clients_decide_the_file_names() {
+    let dir = tempfile::tempdir().unwrap();
+    // Without a client, worktodo.ini is only used when there's no worktodo.txt.
+    write(dir.path().join("worktodo.txt"), "").unwrap();
+
+    let mlucas = WorkDirectory::for_client(dir.path(), Client::Mlucas);
+    let mfaktc = WorkDirectory::for_client(dir.path(), Client::Mfaktc);
+
+    assert_eq!(mlucas.worktodo, dir.path().join("worktodo.ini"));
+    assert_eq!(mlucas.results, dir.path().join("results.txt"));
+    assert_eq!(mfaktc.worktodo, dir.path().join("worktodo.txt"));
+    assert_eq!(mfaktc.client, Some(Client::Mfaktc));
+    assert_eq!(WorkDirectory::new(dir.path()).client, None);
+}
+
+#[test]
+fn clients_know_what_they_can_run() {
+    assert!(Client::Mfaktc.can_run(AssignmentKind::Factor));
+    assert!(!Client::Mfaktc.can_run(AssignmentKind::DoubleCheck));
+    assert!(!Client::Gpuowl.can_run(AssignmentKind::Factor));
+    assert!(Client::CudaLucas.can_run(AssignmentKind::DoubleCheck));
+    assert!(Client::Mprime.can_run(AssignmentKind::Ecm));
+
+    assert_eq!(Client::from_name("CUDALucas"), Some(Client::CudaLucas));
+    assert_eq!(Client::from_name("prime95"), None);
+    assert_eq!(Client::Gpuowl.results_format(), ResultsFormat::Json);
+    assert_eq!(
+        Client::Mlucas.checkpoint_format().file_names(51234577),
+        vec!["p51234577", "q51234577"]
+    );
+    assert_eq!(
+        CheckpointFormat::Gpuowl.file_names(96364649),
+        vec!["96364649/96364649.prp", "96364649/96364649.owl"]
+    );
+    assert!(Client::Gpuowl.checkpoint_format().has_readable_progress());
+    assert!(!Client::Mfakto.checkpoint_format().has_readable_progress());
+}
+
+#[test]
+fn work_the_client_cannot_run_is_not_cached() {
+    let dir = example_workdir("mfakto-example-workdir");
+    let work_directory = WorkDirectory::for_client(dir.path(), Client::Mfakto);
+    let before = lines(work_directory.worktodo.clone());
+    let transport = ScriptedTransport::new();
+    transport.respond(200, &format!("<pre>\n{}\n{}\n</pre>", DC_1, TF_1));
+
+    primenet_request(
+        &transport,
+        &Endpoints::default(),
+        before.len() + 2,
+        &work_directory,
+        PrimenetWorkType::TrialFactoring,
+        1,
+        ExponentRange::default(),
+    )
+    .unwrap();
+
+    let after = lines(work_directory.worktodo.clone());
+    assert_eq!(after[before.len()..].to_vec(), vec![TF_1]);
+    assert!(!work_directory.worktodo_lock.exists());
+}
+
+#[test]
+fn only_results_in_the_clients_format_are_submitted() {
+    let dir = tempfile::tempdir().unwrap();
+    let work_directory = WorkDirectory::for_client(dir.path(), Client::Gpuowl);
+    write(&work_directory.worktodo, "").unwrap();
+    write(
+        &work_directory.results,
+        format!("M96364649 progress 42%\n{}\n", PRP_RESULT),
+    )
+    .unwrap();
+    let transport = ScriptedTransport::new();
+    transport.respond(200, "Accepted");
+
+    primenet_submit(&transport, &Endpoints::default(), &work_directory).unwrap();
+
+    assert_eq!(transport.requests().len(), 1);
+    assert_eq!(lines(work_directory.results_sent.clone()), vec![PRP_RESULT]);
+    assert_eq!(
+        lines(work_directory.results.clone()),
+        vec!["M96364649 progress 42%"]
+    );
+}
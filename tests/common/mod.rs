@@ -0,0 +1,575 @@
+// Local stand-in for mersenne.org and gpu72.com. It speaks just enough HTTP/1.1 for reqwest and
+// implements the pages the runtimes use. Each route behaves like the real site by default, and
+// tests can queue scripted responses (errors, odd pages) that are served first.
+#![allow(dead_code)]
+
+use md5::{Digest, Md5};
+use primenet_rs::{
+    assignment::ExponentRange,
+    endpoints::Endpoints,
+    options::GeneralOptions,
+    util::{lockfile_path, read_list},
+};
+use std::collections::{HashMap, VecDeque};
+use std::fs::{copy, read_dir};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{spawn, JoinHandle};
+use tempfile::TempDir;
+
+pub const PRIMENET_USER: &str = "test_user";
+pub const PRIMENET_PASS: &str = "test_pass";
+pub const GPU72_USER: &str = "gpu72_user";
+pub const GPU72_PASS: &str = "gpu72_pass";
+
+const LOGIN_FORM: &str = "<form method=\"post\" action=\"/\">\n<input type=\"text\" \
+    name=\"user_login\">\n<input type=\"password\" name=\"user_password\">\n</form>";
+// "gpu72_user:gpu72_pass"
+const GPU72_AUTHORIZATION: &str = "Basic Z3B1NzJfdXNlcjpncHU3Ml9wYXNz";
+
+#[derive(Clone, Debug)]
+pub struct MockResponse {
+    pub status: u16,
+    pub body: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl MockResponse {
+    pub fn new(status: u16, body: &str) -> Self {
+        MockResponse {
+            status,
+            body: body.to_string(),
+            headers: Vec::new(),
+        }
+    }
+
+    pub fn ok(body: &str) -> Self {
+        MockResponse::new(200, body)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    // The body as it was sent, for uploads that aren't text.
+    pub data: Vec<u8>,
+}
+
+impl RecordedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        find_param(&self.query, name)
+    }
+
+    pub fn form_param(&self, name: &str) -> Option<String> {
+        find_param(&parse_params(&self.body), name).map(str::to_string)
+    }
+
+    // The contents of the file in a multipart body with a single part.
+    pub fn upload_data(&self) -> Vec<u8> {
+        let start = self
+            .data
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+            .map_or(0, |position| position + 4);
+        let end = self
+            .data
+            .windows(4)
+            .rposition(|window| window == b"\r\n--")
+            .unwrap_or(self.data.len());
+        self.data[start..end.max(start)].to_vec()
+    }
+}
+
+fn find_param<'a>(params: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    params
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.as_str())
+}
+
+// A proof file on its way to the mock proof_upload/ route.
+#[derive(Clone, Debug, Default)]
+pub struct MockProof {
+    pub exponent: u64,
+    pub size: usize,
+    pub data: Vec<u8>,
+}
+
+#[derive(Default)]
+struct State {
+    scripted: HashMap<(String, String), VecDeque<MockResponse>>,
+    requests: Vec<RecordedRequest>,
+    primenet_work: VecDeque<String>,
+    gpu72_work: VecDeque<String>,
+    // Results that manual_result should reject, with the error message to give.
+    rejected_results: HashMap<String, String>,
+    // Proof uploads by the file's MD5.
+    proofs: HashMap<String, MockProof>,
+    // Bumped to expire every session handed out so far.
+    session_generation: usize,
+    logins: usize,
+}
+
+impl State {
+    fn session_cookie(&self) -> String {
+        format!("PHPSESSID=mock-session-{}", self.session_generation)
+    }
+
+    fn has_session(&self, request: &RecordedRequest) -> bool {
+        let session_cookie = self.session_cookie();
+        request
+            .header("Cookie")
+            .map(|cookies| cookies.split(';').any(|c| c.trim() == session_cookie))
+            .unwrap_or(false)
+    }
+}
+
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    shutdown: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MockServer {
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind mock server");
+        let addr = listener.local_addr().unwrap();
+        let state = Arc::new(Mutex::new(State::default()));
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let state = state.clone();
+            let shutdown = shutdown.clone();
+            spawn(move || {
+                for stream in listener.incoming() {
+                    if shutdown.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    if let Ok(stream) = stream {
+                        handle_connection(stream, &state);
+                    }
+                }
+            })
+        };
+        MockServer {
+            addr,
+            state,
+            shutdown,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}/", self.addr)
+    }
+
+    pub fn endpoints(&self) -> Endpoints {
+        Endpoints::new(&self.url(), &self.url())
+    }
+
+    // Serve `response` for the next request to `path` instead of the default page.
+    pub fn script(&self, method: &str, path: &str, response: MockResponse) {
+        self.state
+            .lock()
+            .unwrap()
+            .scripted
+            .entry((method.to_string(), path.to_string()))
+            .or_default()
+            .push_back(response);
+    }
+
+    pub fn add_primenet_work(&self, lines: &[&str]) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .primenet_work
+            .extend(lines.iter().map(|line| line.to_string()));
+    }
+
+    pub fn add_gpu72_work(&self, lines: &[&str]) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .gpu72_work
+            .extend(lines.iter().map(|line| line.to_string()));
+    }
+
+    pub fn reject_result(&self, result: &str, message: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .rejected_results
+            .insert(result.to_string(), message.to_string());
+    }
+
+    // Pretend an earlier run already got `data` through of a proof with the given MD5.
+    pub fn seed_proof(&self, md5: &str, exponent: u64, size: usize, data: &[u8]) {
+        self.state.lock().unwrap().proofs.insert(
+            md5.to_string(),
+            MockProof {
+                exponent,
+                size,
+                data: data.to_vec(),
+            },
+        );
+    }
+
+    pub fn proof(&self, md5: &str) -> Option<MockProof> {
+        self.state.lock().unwrap().proofs.get(md5).cloned()
+    }
+
+    // Log everybody out, the way a session timing out on the real site would.
+    pub fn expire_sessions(&self) {
+        self.state.lock().unwrap().session_generation += 1;
+    }
+
+    // Number of successful logins so far.
+    pub fn logins(&self) -> usize {
+        self.state.lock().unwrap().logins
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    pub fn requests_to(&self, method: &str, path: &str) -> Vec<RecordedRequest> {
+        self.requests()
+            .into_iter()
+            .filter(|request| request.method == method && request.path == path)
+            .collect()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // Wake the accept loop up so it sees the flag.
+        let _ = TcpStream::connect(self.addr);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => match u8::from_str_radix(&s[i + 1..i + 3], 16) {
+                Ok(byte) => {
+                    decoded.push(byte);
+                    i += 2;
+                }
+                Err(_) => decoded.push(b'%'),
+            },
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn parse_params(s: &str) -> Vec<(String, String)> {
+    s.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let mut parts = pair.splitn(2, '=');
+            let key = percent_decode(parts.next().unwrap_or(""));
+            let value = percent_decode(parts.next().unwrap_or(""));
+            (key, value)
+        })
+        .collect()
+}
+
+fn read_request(stream: &TcpStream) -> Option<RecordedRequest> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).ok()?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let target = parts.next()?;
+    let mut target_parts = target.splitn(2, '?');
+    let path = target_parts.next().unwrap_or("/").to_string();
+    let query = parse_params(target_parts.next().unwrap_or(""));
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let mut header = line.splitn(2, ':');
+        headers.push((
+            header.next()?.trim().to_string(),
+            header.next().unwrap_or("").trim().to_string(),
+        ));
+    }
+    let content_length = find_param(
+        &headers
+            .iter()
+            .map(|(key, value)| (key.to_ascii_lowercase(), value.clone()))
+            .collect::<Vec<_>>(),
+        "content-length",
+    )
+    .and_then(|len| len.parse::<usize>().ok())
+    .unwrap_or(0);
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).ok()?;
+    Some(RecordedRequest {
+        method,
+        path,
+        query,
+        headers,
+        body: String::from_utf8_lossy(&body).into_owned(),
+        data: body,
+    })
+}
+
+fn handle_connection(mut stream: TcpStream, state: &Mutex<State>) {
+    let request = match read_request(&stream) {
+        Some(request) => request,
+        None => return,
+    };
+    let response = {
+        let mut state = state.lock().unwrap();
+        state.requests.push(request.clone());
+        let scripted = state
+            .scripted
+            .get_mut(&(request.method.clone(), request.path.clone()))
+            .and_then(VecDeque::pop_front);
+        scripted.unwrap_or_else(|| default_response(&mut state, &request))
+    };
+    let mut head = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.body.len()
+    );
+    for (key, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", key, value));
+    }
+    head.push_str("\r\n");
+    let _ = stream.write_all(head.as_bytes());
+    let _ = stream.write_all(response.body.as_bytes());
+    let _ = stream.flush();
+}
+
+fn page(content: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<body>\n<div class=\"content\">\n{}\n</div>\n</body>\n</html>\n",
+        content
+    )
+}
+
+fn proof_error(status: u16, description: &str) -> String {
+    format!(
+        "{{\"error_status\": {}, \"error_description\": \"{}\"}}",
+        status, description
+    )
+}
+
+fn proof_need(proof: &MockProof) -> String {
+    format!(
+        "{{\"URLToUse\": \"/proof_upload/chunk/\", \"need\": {{\"{}\": {}}}}}",
+        proof.data.len(),
+        proof.size - 1
+    )
+}
+
+fn default_response(state: &mut State, request: &RecordedRequest) -> MockResponse {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/") => {
+            if state.has_session(request) {
+                MockResponse::ok(&page(&format!("{}<br>logged in", PRIMENET_USER)))
+            } else {
+                MockResponse::ok(&page(LOGIN_FORM))
+            }
+        }
+        ("POST", "/") => {
+            let user = request.form_param("user_login").unwrap_or_default();
+            let pass = request.form_param("user_password").unwrap_or_default();
+            if user == PRIMENET_USER && pass == PRIMENET_PASS {
+                state.logins += 1;
+                let mut response =
+                    MockResponse::ok(&page(&format!("{}<br>logged in", PRIMENET_USER)));
+                response.headers.push((
+                    "Set-Cookie".to_string(),
+                    format!("{}; Path=/", state.session_cookie()),
+                ));
+                response
+            } else {
+                MockResponse::ok(&page(&format!("Login failed.\n{}", LOGIN_FORM)))
+            }
+        }
+        ("GET", "/manual_assignment/") => {
+            if !state.has_session(request) {
+                return MockResponse::ok(&page(LOGIN_FORM));
+            }
+            let num_to_get = request
+                .query_param("num_to_get")
+                .and_then(|n| n.parse::<usize>().ok())
+                .unwrap_or(1);
+            let count = num_to_get.min(state.primenet_work.len());
+            let lines = state.primenet_work.drain(..count).collect::<Vec<_>>();
+            if lines.is_empty() {
+                MockResponse::ok(&page("No assignments available."))
+            } else {
+                MockResponse::ok(&page(&format!("<pre>\n{}\n</pre>", lines.join("\n"))))
+            }
+        }
+        ("POST", "/manual_result/") => {
+            if !state.has_session(request) {
+                return MockResponse::ok(&page(LOGIN_FORM));
+            }
+            let data = request.form_param("data").unwrap_or_default();
+            match state.rejected_results.get(data.trim()) {
+                Some(message) => MockResponse::ok(&page(&format!(
+                    "<div class=\"error\">Error {}</div>",
+                    message
+                ))),
+                None => MockResponse::ok(&page(&format!(
+                    "processing: {}\nAccepted\nCPU credit is 1.2345 GHz-days.",
+                    data
+                ))),
+            }
+        }
+        ("GET", "/proof_upload/") => {
+            if request.query_param("UserID") != Some(PRIMENET_USER) {
+                return MockResponse::ok(&proof_error(401, "Unknown user"));
+            }
+            let md5 = request.query_param("FileMD5").unwrap_or_default();
+            let size = request.query_param("FileSize").and_then(|n| n.parse().ok());
+            let exponent = request.query_param("Exponent").and_then(|n| n.parse().ok());
+            let (size, exponent) = match (size, exponent) {
+                (Some(size), Some(exponent)) => (size, exponent),
+                _ => return MockResponse::ok(&proof_error(400, "Bad FileSize or Exponent")),
+            };
+            let proof = state
+                .proofs
+                .entry(md5.to_string())
+                .or_insert_with(|| MockProof {
+                    exponent,
+                    size,
+                    data: Vec::new(),
+                });
+            if proof.data.len() == proof.size {
+                MockResponse::ok(&proof_error(409, "Proof already uploaded"))
+            } else {
+                MockResponse::ok(&proof_need(proof))
+            }
+        }
+        ("POST", "/proof_upload/chunk/") => {
+            let md5 = request.query_param("FileMD5").unwrap_or_default();
+            let data = request.upload_data();
+            let proof = match state.proofs.get_mut(md5) {
+                Some(proof) => proof,
+                None => return MockResponse::ok(&proof_error(400, "Unknown file")),
+            };
+            let offset = request
+                .query_param("DataOffset")
+                .and_then(|n| n.parse().ok());
+            let size = request.query_param("DataSize").and_then(|n| n.parse().ok());
+            // Anything that doesn't fit onto what we have is asked for again.
+            if offset != Some(proof.data.len())
+                || size != Some(data.len())
+                || request.query_param("DataMD5") != Some(md5_hex(&data).as_str())
+            {
+                return MockResponse::ok(&proof_need(proof));
+            }
+            proof.data.extend_from_slice(&data);
+            if proof.data.len() < proof.size {
+                MockResponse::ok(&proof_need(proof))
+            } else if md5_hex(&proof.data) == md5 {
+                MockResponse::ok("{\"FileUploaded\": \"Yes\"}")
+            } else {
+                proof.data.clear();
+                MockResponse::ok(&proof_error(400, "File MD5 mismatch"))
+            }
+        }
+        ("GET", path) if path.starts_with("/account/getassignments/") => {
+            if request.header("Authorization") != Some(GPU72_AUTHORIZATION) {
+                return MockResponse::new(401, &page("Unauthorized"));
+            }
+            if path == "/account/getassignments/" {
+                return MockResponse::ok(&page("Get Assignments"));
+            }
+            let number = request
+                .query_param("Number")
+                .and_then(|n| n.parse::<usize>().ok())
+                .unwrap_or(1);
+            let count = number.min(state.gpu72_work.len());
+            let lines = state.gpu72_work.drain(..count).collect::<Vec<_>>();
+            MockResponse::ok(&format!("{}\n", lines.join("\n")))
+        }
+        _ => MockResponse::new(404, &page("Not found")),
+    }
+}
+
+// A copy of one of the example work directories shipped with the repo, so tests never touch the
+// originals.
+pub fn md5_hex(data: &[u8]) -> String {
+    format!("{:x}", Md5::digest(data))
+}
+
+pub fn example_workdir(name: &str) -> TempDir {
+    let source = Path::new(env!("CARGO_MANIFEST_DIR")).join(name);
+    let dir = tempfile::tempdir().expect("Failed to create temporary work directory");
+    for entry in read_dir(&source).expect("Failed to read example work directory") {
+        let entry = entry.unwrap();
+        copy(entry.path(), dir.path().join(entry.file_name())).unwrap();
+    }
+    dir
+}
+
+pub fn general_options(dir: &TempDir, server: &MockServer, num_cache: usize) -> GeneralOptions {
+    GeneralOptions {
+        work_directory: dir.path().display().to_string(),
+        num_cache,
+        days_of_work: None,
+        throughput: None,
+        timeout: 0,
+        endpoints: server.endpoints(),
+        exponent_range: ExponentRange::default(),
+        client: None,
+    }
+}
+
+pub fn lines(path: PathBuf) -> Vec<String> {
+    if path.exists() {
+        read_list(&path)
+            .unwrap()
+            .into_iter()
+            .filter(|line| !line.is_empty())
+            .collect()
+    } else {
+        Vec::new()
+    }
+}
+
+pub fn assert_unlocked(dir: &TempDir) {
+    for name in &[
+        "worktodo.txt",
+        "worktodo.ini",
+        "results.txt",
+        "results.sent",
+        "results.queue.json",
+    ] {
+        let lock = lockfile_path(&dir.path().join(name));
+        assert!(!lock.exists(), "{} was left behind", lock.display());
+    }
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use common::*;
+use primenet_rs::{
+    assignment::Assignment,
+    credit::{assignment_credit, ll_credit, tf_credit, DaysOfWork},
+    options::{PrimenetOptions, ProofOptions},
+    p95_work::PrimenetWorkType,
+    primenet_runtime::primenet_runtime,
+};
+use std::fs::write;
+
+const DC_1: &str = "DoubleCheck=7A30B8B6C0FC79C534A271D9561F7DCC,51234577,73,1";
+const DC_2: &str = "DoubleCheck=BC914675C81023F252E92CF034BEFF6C,51234587,73,1";
+const DC_3: &str = "DoubleCheck=3F2A0C6BA2B55A2F8E1C9D7E6B5A4F30,51234611,73,1";
+const DC_4: &str = "DoubleCheck=B2EE67DC0A514E85D0F8A82544CF9B17,51234613,73,1";
+const PRP_1: &str = "PRP=BC914675C81023F252E92CF034BEFF6C,1,2,110000117,-1,77,0";
+const TF_1: &str = "Factor=N/A,332194529,74,75";
+const CERT_1: &str = "Cert=B2EE67DC0A514E85D0F8A82544CF9B17,1,2,110000117,-1,430000";
+
+fn credit(line: &str) -> f64 {
+    assignment_credit(&Assignment::parse(line).unwrap())
+}
+
+#[test]
+fn assignments_are_credited_like_primenet_does() {
+    // Primenet's own formula gives 11.517 GHz-days for this one.
+    assert!((credit(TF_1) - 11.517).abs() < 0.001);
+    assert_eq!(credit(TF_1), tf_credit(332194529, 74, 75));
+    // Bit levels below 2^48 aren't credited at all.
+    assert_eq!(tf_credit(332194529, 40, 47), 0.0);
+    // A 110M PRP test is a few hundred GHz-days, around 4.8 times a 51M double check.
+    let prp = credit(PRP_1);
+    assert!(prp > 400.0 && prp < 500.0, "{}", prp);
+    assert_eq!(prp, ll_credit(110000117));
+    let ratio = prp / credit(DC_1);
+    assert!(ratio > 4.5 && ratio < 5.0, "{}", ratio);
+    // Certifying takes a small fraction of the squarings of the test itself.
+    let cert = credit(CERT_1);
+    assert!((cert / prp - 430000.0 / 110000117.0).abs() < 1e-9);
+}
+
+#[test]
+fn cache_is_sized_by_the_days_it_covers() {
+    let prp = vec![Assignment::parse(PRP_1).unwrap()];
+    let tf = Assignment::parse(TF_1).unwrap();
+    let days_of_work = |days, throughput| DaysOfWork { days, throughput };
+
+    // With nothing to go by, ask for one at a time.
+    assert_eq!(days_of_work(10.0, 20.0).assignments_to_cache(&[]), Some(1));
+    // One PRP test is over 20 days at 20 GHz-days a day...
+    assert_eq!(days_of_work(10.0, 20.0).assignments_to_cache(&prp), None);
+    // ...but only about 5 at 90.
+    assert_eq!(days_of_work(10.0, 90.0).assignments_to_cache(&prp), Some(2));
+    let days = days_of_work(1.0, 50.0).days_cached(&[tf.clone(), tf.clone()]);
+    assert!((days - 2.0 * 11.517 / 50.0).abs() < 0.001);
+    // Two TF assignments make up 23 of the 100 GHz-days wanted, and more like them need another 7.
+    assert_eq!(
+        days_of_work(2.0, 50.0).assignments_to_cache(&[tf.clone(), tf]),
+        Some(9)
+    );
+}
+
+#[test]
+fn runtime_tops_up_until_the_days_are_covered() {
+    let server = MockServer::start();
+    server.add_primenet_work(&[DC_1, DC_2, DC_3, DC_4]);
+    let dir = example_workdir("mfakto-example-workdir");
+    write(dir.path().join("worktodo.txt"), "").unwrap();
+    let mut general_options = general_options(&dir, &server, 1);
+    // About 94 GHz-days a double check, so 250 GHz-days takes three of them.
+    general_options.days_of_work = Some(10.0);
+    general_options.throughput = Some(25.0);
+    let options = PrimenetOptions {
+        credentials: (PRIMENET_USER.to_string(), PRIMENET_PASS.to_string()),
+        work_type: PrimenetWorkType::DoubleCheckLlTests,
+        fallback_work_type: None,
+        cores: 1,
+        proofs: ProofOptions::Archive,
+        general_options,
+    };
+
+    primenet_runtime(options.clone()).unwrap();
+
+    assert_eq!(
+        lines(dir.path().join("worktodo.txt")),
+        vec![DC_1, DC_2, DC_3]
+    );
+    let num_to_get = server
+        .requests_to("GET", "/manual_assignment/")
+        .iter()
+        .map(|request| request.query_param("num_to_get").unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(num_to_get, vec!["1", "2"]);
+
+    // Covered now, so another pass doesn't ask for anything.
+    primenet_runtime(options).unwrap();
+    assert_eq!(server.requests_to("GET", "/manual_assignment/").len(), 2);
+    assert_unlocked(&dir);
+}
@@ -0,0 +1,181 @@
+mod common;
+
+use chrono::Utc;
+use common::*;
+use primenet_rs::{
+    dry_run::DryRun,
+    events::Event,
+    gpu72_runtime::gpu72_work_request,
+    gpu72_work::{Gpu72LLTFWorkOption, Gpu72WorkType},
+    options::{PrimenetOptions, ProofOptions},
+    p95_work::PrimenetWorkType,
+    primenet_runtime::{primenet_dry_run_with_transport, primenet_work_request},
+    submission_queue::SubmissionQueue,
+    transport::ReqwestTransport,
+    work_directory::WorkDirectory,
+};
+use std::collections::BTreeMap;
+use std::fs::{read, read_dir, write};
+use std::path::Path;
+
+const DC_1: &str = "DoubleCheck=7A30B8B6C0FC79C534A271D9561F7DCC,51234577,73,1";
+const DC_2: &str = "DoubleCheck=BC914675C81023F252E92CF034BEFF6C,51234587,73,1";
+// 51234579 is divisible by 3.
+const COMPOSITE: &str = "DoubleCheck=3F2A0C6BA2B55A2F8E1C9D7E6B5A4F30,51234579,73,1";
+const RESULT_1: &str = "M51234559 is not prime. Res64: 1A2B3C4D5E6F7081. Wh8: 00000000,00000000";
+const RESULT_2: &str = "M51234563 is not prime. Res64: 8070605040302010. Wh8: 00000000,00000000";
+const BAD_FACTOR: &str = "M51234611 has a factor: 7";
+
+fn snapshot(dir: &Path) -> BTreeMap<String, Vec<u8>> {
+    read_dir(dir)
+        .unwrap()
+        .map(|entry| {
+            let entry = entry.unwrap();
+            (
+                entry.file_name().to_string_lossy().to_string(),
+                read(entry.path()).unwrap(),
+            )
+        })
+        .collect()
+}
+
+// Something cached, something to quarantine, a result due, a bad factor and a result that's
+// already failed once and is waiting to be retried.
+fn work_directory(dir: &Path) -> WorkDirectory {
+    let work_directory = WorkDirectory::new(dir);
+    write(
+        &work_directory.worktodo,
+        format!("{}\n{}\n", DC_1, COMPOSITE),
+    )
+    .unwrap();
+    write(
+        &work_directory.results,
+        format!("{}\n{}\n", RESULT_1, BAD_FACTOR),
+    )
+    .unwrap();
+    let mut queue = SubmissionQueue::default();
+    let now = Utc::now();
+    queue.push(RESULT_2.to_string(), now);
+    queue.record_failure(RESULT_2, "Server error".to_string(), now);
+    queue.save(&work_directory.queue).unwrap();
+    work_directory
+}
+
+#[test]
+fn primenet_dry_run_sends_and_changes_nothing() {
+    let server = MockServer::start();
+    server.add_primenet_work(&[DC_2]);
+    let dir = tempfile::tempdir().unwrap();
+    work_directory(dir.path());
+    let before = snapshot(dir.path());
+
+    primenet_dry_run_with_transport(
+        &ReqwestTransport::new().unwrap(),
+        PrimenetOptions {
+            credentials: (PRIMENET_USER.to_string(), PRIMENET_PASS.to_string()),
+            work_type: PrimenetWorkType::DoubleCheckLlTests,
+            fallback_work_type: None,
+            cores: 1,
+            proofs: ProofOptions::Archive,
+            general_options: general_options(&dir, &server, 3),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(server.logins(), 1);
+    assert!(server.requests_to("GET", "/manual_assignment/").is_empty());
+    assert!(server.requests_to("POST", "/manual_result/").is_empty());
+    assert_eq!(snapshot(dir.path()), before);
+}
+
+#[test]
+fn dry_run_plans_the_request_and_submissions() {
+    let server = MockServer::start();
+    let dir = tempfile::tempdir().unwrap();
+    work_directory(dir.path());
+    let general_options = general_options(&dir, &server, 3);
+    let endpoints = server.endpoints();
+
+    let plan = DryRun::plan(
+        &general_options,
+        |num_to_get| {
+            primenet_work_request(
+                &endpoints,
+                num_to_get,
+                PrimenetWorkType::DoubleCheckLlTests,
+                1,
+                general_options.exponent_range,
+            )
+        },
+        true,
+        Utc::now(),
+    )
+    .unwrap()
+    .with_fallback(|num_to_get| {
+        primenet_work_request(
+            &endpoints,
+            num_to_get,
+            PrimenetWorkType::DoubleCheckPrpTests,
+            1,
+            general_options.exponent_range,
+        )
+    });
+
+    assert_eq!(plan.cached.len(), 1);
+    assert_eq!(plan.invalid_assignments.len(), 1);
+    assert_eq!(plan.invalid_assignments[0].0.line, COMPOSITE);
+    // The composite one doesn't count towards the three wanted.
+    let (num_to_get, request) = plan.work_request.clone().unwrap();
+    assert_eq!(num_to_get, 2);
+    assert!(request.full_url().contains("num_to_get=2"));
+    let fallback = plan.fallback_request.clone().unwrap();
+    assert!(fallback.full_url().contains("num_to_get=2"));
+    assert_ne!(fallback.full_url(), request.full_url());
+    assert_eq!(plan.submissions, vec![RESULT_1]);
+    assert_eq!(plan.invalid_results.len(), 1);
+    assert_eq!(plan.invalid_results[0].0.line, BAD_FACTOR);
+    assert_eq!(plan.waiting, 1);
+    let shown = plan.to_string();
+    assert!(shown.contains("Would request 2 more assignment(s):"));
+    assert!(shown.contains("If none of that type is left, would request instead:"));
+    assert!(shown.contains("Would submit 1 result(s)"));
+    assert!(shown.contains("1 more result(s) queued"));
+    let json = Event::dry_run(&plan).to_json(Utc::now());
+    assert!(json.contains(r#""event":"dry_run""#));
+    assert!(json.contains(r#""waiting":1"#));
+    assert!(json.contains(r#""fallback_request":{"count":2"#));
+}
+
+#[test]
+fn gpu72_dry_run_without_primenet_credentials_submits_nothing() {
+    let server = MockServer::start();
+    let dir = tempfile::tempdir().unwrap();
+    work_directory(dir.path());
+    let general_options = general_options(&dir, &server, 1);
+    let endpoints = server.endpoints();
+    let work_type = Gpu72WorkType::LucasLehmerTrialFactor(Gpu72LLTFWorkOption::WhatMakesSense);
+
+    let plan = DryRun::plan(
+        &general_options,
+        |num_to_get| {
+            gpu72_work_request(
+                &endpoints,
+                num_to_get,
+                73,
+                work_type,
+                general_options.exponent_range,
+            )
+        },
+        false,
+        Utc::now(),
+    )
+    .unwrap();
+
+    assert_eq!(plan.work_request, None);
+    assert_eq!(plan.fallback_request, None);
+    assert_eq!(plan.report_url, None);
+    assert!(plan.submissions.is_empty());
+    let shown = plan.to_string();
+    assert!(shown.contains("Enough work is cached."));
+    assert!(shown.contains("Would not submit results without Primenet credentials."));
+}
@@ -0,0 +1,124 @@
+use chrono::{TimeZone, Utc};
+use primenet_rs::{
+    activity::WorkSource,
+    client::Client,
+    error::Error,
+    events::{Event, SubmissionOutcome, EVENT_SCHEMA_VERSION},
+    factor_report::FactorReport,
+    status::Status,
+    submission_queue::{Quarantine, SubmissionQueue},
+    work_directory::WorkDirectory,
+    Assignment,
+};
+use serde_json::{json, Value};
+use std::fs::write;
+
+const DC_1: &str = "DoubleCheck=7A30B8B6C0FC79C534A271D9561F7DCC,51234577,73,1";
+const MANUAL: &str = "Test=N/A,89459323,76,1";
+const RESULT_1: &str = "M51234559 is not prime. Res64: 1A2B3C4D5E6F7081. Wh8: 00000000,00000000";
+const FACTOR: &str =
+    "M332194679 has a factor: 701595162049 [TF:39:40*:mfaktc 0.21 barrett76_mul32_gs]";
+
+fn event_json(event: &Event) -> Value {
+    let time = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+    let line = event.to_json(time);
+    assert!(!line.contains('\n'));
+    let value = serde_json::from_str::<Value>(&line).unwrap();
+    assert_eq!(value["version"], json!(EVENT_SCHEMA_VERSION));
+    assert_eq!(value["time"], json!("2026-10-18T12:00:00Z"));
+    value
+}
+
+#[test]
+fn fetch_submission_and_error_events() {
+    let dir = tempfile::tempdir().unwrap();
+    let work_directory = WorkDirectory::for_client(dir.path(), Client::Mlucas);
+    let fetch = event_json(&Event::fetch(
+        WorkSource::Primenet,
+        &work_directory,
+        &[Assignment::parse(DC_1).unwrap()],
+    ));
+    assert_eq!(fetch["event"], json!("fetch"));
+    assert_eq!(fetch["source"], json!("primenet"));
+    assert_eq!(
+        fetch["assignments"],
+        json!([{
+            "kind": "DoubleCheck",
+            "exponent": 51234577,
+            "aid": "7A30B8B6C0FC79C534A271D9561F7DCC",
+            "line": DC_1,
+        }])
+    );
+
+    let submission = event_json(&Event::submission(
+        &work_directory,
+        RESULT_1,
+        SubmissionOutcome::Rejected,
+        Some("Invalid result".to_string()),
+        None,
+    ));
+    assert_eq!(submission["event"], json!("submission"));
+    assert_eq!(submission["result"], json!(RESULT_1));
+    assert_eq!(submission["outcome"], json!("rejected"));
+    assert_eq!(submission["message"], json!("Invalid result"));
+    assert_eq!(submission["ghz_days"], Value::Null);
+
+    let error = event_json(&Event::error(
+        &Error::Authentication("Bad password.".to_string()),
+        true,
+    ));
+    assert_eq!(error["event"], json!("error"));
+    assert_eq!(error["exit_code"], json!(77));
+    assert_eq!(error["fatal"], json!(true));
+}
+
+#[test]
+fn snapshot_events() {
+    let dir = tempfile::tempdir().unwrap();
+    let work_directory = WorkDirectory::for_client(dir.path(), Client::Mfaktc);
+    write(&work_directory.worktodo, format!("{}\n", MANUAL)).unwrap();
+    write(&work_directory.results, format!("{}\n", RESULT_1)).unwrap();
+    write(&work_directory.worktodo_lock, "").unwrap();
+
+    let status = event_json(&Event::status(
+        &Status::read(&work_directory, Utc::now()).unwrap(),
+    ));
+    assert_eq!(status["event"], json!("status"));
+    assert_eq!(status["cached"][0]["exponent"], json!(89459323));
+    assert_eq!(status["cached"][0]["source"], Value::Null);
+    assert_eq!(status["current"], Value::Null);
+    assert_eq!(status["results_waiting"], json!(1));
+    assert_eq!(status["last_fetch"], Value::Null);
+    assert_eq!(status["locks"].as_array().unwrap().len(), 1);
+    assert_eq!(
+        status["throughput"],
+        json!({"ghz_days_per_day": null, "measurements": 0})
+    );
+
+    let mut queue = SubmissionQueue::default();
+    let queued_at = Utc.with_ymd_and_hms(2026, 10, 18, 0, 0, 0).unwrap();
+    queue.push(RESULT_1.to_string(), queued_at);
+    let quarantine = Quarantine {
+        entries: vec![(RESULT_1.to_string(), "Rejected by Primenet".to_string())],
+    };
+    let queue = event_json(&Event::queue(&work_directory, &queue, &quarantine));
+    assert_eq!(queue["event"], json!("queue"));
+    assert_eq!(queue["results"][0]["result"], json!(RESULT_1));
+    assert_eq!(
+        queue["results"][0]["queued_at"],
+        json!("2026-10-18T00:00:00Z")
+    );
+    assert_eq!(queue["results"][0]["attempts"], json!(0));
+    assert_eq!(
+        queue["quarantined"],
+        json!([{"line": RESULT_1, "reason": "Rejected by Primenet"}])
+    );
+
+    let report = FactorReport::from_results(&[FACTOR.to_string()]);
+    let factors = event_json(&Event::factors(&work_directory, &report));
+    assert_eq!(factors["event"], json!("factors"));
+    assert_eq!(factors["factors"][0]["factor"], json!("701595162049"));
+    assert_eq!(factors["factors"][0]["k"], json!("1056"));
+    assert_eq!(factors["yields"][0]["bits"], json!(39));
+    assert_eq!(factors["yields"][0]["found"], json!(1));
+}
@@ -0,0 +1,178 @@
+mod common;
+
+use common::lines;
+use primenet_rs::{
+    assignment::{is_prime, ExponentRange},
+    client::Client,
+    endpoints::Endpoints,
+    p95_work::PrimenetWorkType,
+    primenet_runtime::{primenet_request, WorkRequestOutcome},
+    transport::ScriptedTransport,
+    work_directory::WorkDirectory,
+};
+use std::fs::write;
+
+const TF_1: &str = "Factor=3F2A0C6BA2B55A2F8E1C9D7E6B5A4F30,332194529,74,75";
+const TF_COMPOSITE: &str = "Factor=7A30B8B6C0FC79C534A271D9561F7DCC,332194535,74,75";
+const TF_HUGE: &str = "Factor=BC914675C81023F252E92CF034BEFF6C,4294967311,80,81";
+const DC_1: &str = "DoubleCheck=7A30B8B6C0FC79C534A271D9561F7DCC,51234577,73,1";
+const DC_COMPOSITE: &str = "DoubleCheck=BC914675C81023F252E92CF034BEFF6C,51234579,73,1";
+
+#[test]
+fn miller_rabin_is_exact_for_u64() {
+    for prime in &[
+        2,
+        3,
+        37,
+        41,
+        51234577,
+        332194529,
+        4294967291,
+        4294967311,
+        18446744073709551557,
+    ] {
+        assert!(is_prime(*prime), "{} is prime", prime);
+    }
+    for composite in &[
+        0,
+        1,
+        4,
+        561,
+        332194535,
+        // Strong pseudoprimes to the bases 2, 3, 5 and 7...
+        3215031751,
+        // ...and to every prime base up to 23.
+        3825123056546413051,
+        u64::MAX,
+    ] {
+        assert!(!is_prime(*composite), "{} is composite", composite);
+    }
+}
+
+#[test]
+fn fetched_assignments_with_bad_exponents_are_quarantined() {
+    let dir = tempfile::tempdir().unwrap();
+    let work_directory = WorkDirectory::for_client(dir.path(), Client::Mfaktc);
+    write(&work_directory.worktodo, "").unwrap();
+    let transport = ScriptedTransport::new();
+    transport.respond(
+        200,
+        &format!("<pre>\n{}\n{}\n{}\n</pre>", TF_COMPOSITE, TF_HUGE, TF_1),
+    );
+
+    primenet_request(
+        &transport,
+        &Endpoints::default(),
+        3,
+        &work_directory,
+        PrimenetWorkType::TrialFactoring,
+        1,
+        ExponentRange::default(),
+    )
+    .unwrap();
+
+    assert_eq!(lines(work_directory.worktodo.clone()), vec![TF_1]);
+    let quarantine = lines(work_directory.worktodo_quarantine.clone());
+    assert_eq!(quarantine.len(), 4);
+    assert!(quarantine[0].ends_with("the exponent 332194535 isn't prime"));
+    assert_eq!(quarantine[1], TF_COMPOSITE);
+    assert!(quarantine[2]
+        .ends_with("M4294967311 is above the largest exponent mfaktc can handle, 4294967295"));
+    assert_eq!(quarantine[3], TF_HUGE);
+    assert!(!work_directory.worktodo_lock.exists());
+}
+
+#[test]
+fn fetched_assignments_outside_the_requested_range_are_quarantined() {
+    let dir = tempfile::tempdir().unwrap();
+    let work_directory = WorkDirectory::new(dir.path());
+    write(&work_directory.worktodo, "").unwrap();
+    let transport = ScriptedTransport::new();
+    transport.respond(200, &format!("<pre>\n{}\n</pre>", DC_1));
+
+    primenet_request(
+        &transport,
+        &Endpoints::default(),
+        1,
+        &work_directory,
+        PrimenetWorkType::DoubleCheckLlTests,
+        1,
+        ExponentRange {
+            min: Some(60_000_000),
+            max: None,
+        },
+    )
+    .unwrap();
+
+    assert!(lines(work_directory.worktodo.clone()).is_empty());
+    let quarantine = lines(work_directory.worktodo_quarantine.clone());
+    assert!(quarantine[0].ends_with("M51234577 is outside the requested range of M60000000 and up"));
+    assert_eq!(quarantine[1], DC_1);
+}
+
+#[test]
+fn worktodo_lines_above_the_client_limit_are_quarantined_when_read() {
+    let dir = tempfile::tempdir().unwrap();
+    let work_directory = WorkDirectory::for_client(dir.path(), Client::Mfaktc);
+    write(&work_directory.worktodo, format!("{}\n{}\n", TF_HUGE, TF_1)).unwrap();
+    let transport = ScriptedTransport::new();
+
+    primenet_request(
+        &transport,
+        &Endpoints::default(),
+        1,
+        &work_directory,
+        PrimenetWorkType::TrialFactoring,
+        1,
+        ExponentRange::default(),
+    )
+    .unwrap();
+
+    assert_eq!(lines(work_directory.worktodo.clone()), vec![TF_1]);
+    let quarantine = lines(work_directory.worktodo_quarantine.clone());
+    assert_eq!(quarantine.len(), 2);
+    assert_eq!(quarantine[1], TF_HUGE);
+}
+
+#[test]
+fn worktodo_lines_with_bad_exponents_are_quarantined_when_read() {
+    // TF_1 is outside the requested range, but it's already reserved and may be running, so the
+    // range only applies to new work.
+    let dir = tempfile::tempdir().unwrap();
+    let work_directory = WorkDirectory::new(dir.path());
+    write(
+        &work_directory.worktodo,
+        format!(
+            "# Double checks\n{}\n{}\n{}\nNot an assignment\n",
+            DC_COMPOSITE, DC_1, TF_1
+        ),
+    )
+    .unwrap();
+    let transport = ScriptedTransport::new();
+
+    let outcome = primenet_request(
+        &transport,
+        &Endpoints::default(),
+        1,
+        &work_directory,
+        PrimenetWorkType::DoubleCheckLlTests,
+        1,
+        ExponentRange {
+            min: None,
+            max: Some(100_000_000),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(outcome, WorkRequestOutcome::AlreadyCached);
+    assert!(transport.requests().is_empty());
+    assert_eq!(
+        lines(work_directory.worktodo.clone()),
+        vec!["# Double checks", DC_1, TF_1, "Not an assignment"]
+    );
+    let quarantine = lines(work_directory.worktodo_quarantine.clone());
+    assert_eq!(quarantine.len(), 2);
+    assert!(quarantine[0].ends_with("the exponent 51234579 isn't prime"));
+    assert_eq!(quarantine[1], DC_COMPOSITE);
+    assert!(!work_directory.worktodo_lock.exists());
+}
@@ -0,0 +1,156 @@
+mod common;
+
+use common::lines;
+use primenet_rs::{
+    endpoints::Endpoints,
+    factor::{check_factor, credit_log2_factor, factor_info, FactorProblem},
+    factor_report::{BitLevelYield, FactorReport},
+    primenet_runtime::primenet_submit,
+    transport::ScriptedTransport,
+    work_directory::WorkDirectory,
+    work_result::WorkResult,
+};
+use std::fs::write;
+
+// M332194679 = 2657557433 * 701595162049 * 2933943404929 * ...
+const GOOD_FACTOR: &str =
+    "M332194679 has a factor: 701595162049 [TF:39:40:mfaktc 0.21 barrett76_mul32_gs]";
+// One bit off from a real factor, the way a flaky GPU might report it.
+const BAD_FACTOR: &str =
+    "M332194679 has a factor: 701595162048 [TF:39:40:mfaktc 0.21 barrett76_mul32_gs]";
+const NO_FACTOR: &str =
+    "no factor for M332194529 from 2^74 to 2^75 [mfaktc 0.21 barrett76_mul32_gs]";
+
+#[test]
+fn factors_are_checked_against_the_exponent() {
+    assert_eq!(check_factor(11, "23"), Ok(()));
+    assert_eq!(check_factor(11, "89"), Ok(()));
+    assert_eq!(check_factor(67, "761838257287"), Ok(()));
+    // Composite factors are fine too, as long as they divide.
+    assert_eq!(check_factor(332194679, "1864529437860159460217"), Ok(()));
+
+    assert_eq!(check_factor(11, "1"), Err(FactorProblem::NotANumber));
+    assert_eq!(check_factor(11, "0x17"), Err(FactorProblem::NotANumber));
+    assert_eq!(check_factor(11, "25"), Err(FactorProblem::NotOneMod2p));
+    assert_eq!(
+        check_factor(11, "45"),
+        Err(FactorProblem::NotPlusMinusOneMod8)
+    );
+    assert_eq!(check_factor(11, "111"), Err(FactorProblem::DoesNotDivide));
+    assert_eq!(
+        check_factor(332194679, "1864529437860159465532"),
+        Err(FactorProblem::NotOneMod2p)
+    );
+    assert_eq!(
+        check_factor(
+            332194679,
+            &(1864529437860159460217u128 + 16 * 332194679).to_string()
+        ),
+        Err(FactorProblem::DoesNotDivide)
+    );
+
+    assert_eq!(
+        WorkResult::parse(GOOD_FACTOR).unwrap().verify_factor(),
+        Ok(())
+    );
+    assert_eq!(
+        WorkResult::parse(BAD_FACTOR).unwrap().verify_factor(),
+        Err(FactorProblem::NotOneMod2p)
+    );
+    assert_eq!(
+        WorkResult::parse(NO_FACTOR).unwrap().verify_factor(),
+        Ok(())
+    );
+}
+
+#[test]
+fn bad_factors_are_quarantined_instead_of_submitted() {
+    let dir = tempfile::tempdir().unwrap();
+    let work_directory = WorkDirectory::new(dir.path());
+    write(&work_directory.worktodo, "").unwrap();
+    write(
+        &work_directory.results,
+        format!("{}\n{}\n{}\n", GOOD_FACTOR, BAD_FACTOR, NO_FACTOR),
+    )
+    .unwrap();
+    let transport = ScriptedTransport::new();
+    transport.respond(200, "Accepted");
+    transport.respond(200, "Accepted");
+
+    primenet_submit(&transport, &Endpoints::default(), &work_directory).unwrap();
+
+    assert_eq!(
+        lines(work_directory.results_sent.clone()),
+        vec![GOOD_FACTOR, NO_FACTOR]
+    );
+    assert_eq!(lines(work_directory.results.clone()), Vec::<String>::new());
+    let quarantine = lines(work_directory.results_quarantine.clone());
+    assert_eq!(quarantine.len(), 2);
+    assert!(quarantine[0].starts_with("# "));
+    assert!(quarantine[0].ends_with("M332194679: the factor isn't of the form 2kp+1"));
+    assert_eq!(quarantine[1], BAD_FACTOR);
+    assert_eq!(transport.requests().len(), 2);
+}
+
+#[test]
+fn factor_metadata_matches_primenet() {
+    let info = WorkResult::parse(GOOD_FACTOR)
+        .unwrap()
+        .factor_info()
+        .unwrap();
+    assert_eq!(info.factor.to_string(), "701595162049");
+    assert_eq!(info.k.to_string(), "1056");
+    assert_eq!(info.class, 49);
+    assert_eq!(info.pass, Some(7));
+    assert!((info.bits - 39.3523).abs() < 1e-4);
+    assert_eq!(info.bit_level(), 39);
+
+    let big = factor_info(332194679, "1864529437860159460217").unwrap();
+    assert_eq!(big.k.to_string(), "2806380649252");
+    assert_eq!(big.class, 17);
+    assert_eq!(big.pass, Some(2));
+    assert_eq!(big.bit_level(), 70);
+    // Primenet's estimate from the leading digits, rather than the exact 70.66.
+    assert!((credit_log2_factor("1864529437860159460217") - 70.6604).abs() < 1e-4);
+    assert!((credit_log2_factor("23") - 23f64.log2()).abs() < 1e-9);
+
+    assert_eq!(factor_info(11, "25"), None);
+    assert_eq!(WorkResult::parse(NO_FACTOR).unwrap().factor_info(), None);
+}
+
+#[test]
+fn factor_report_counts_yield_by_bit_level() {
+    let results = [
+        GOOD_FACTOR,
+        "found 1 factor for M332194679 from 2^39 to 2^40 [mfaktc 0.21 barrett76_mul32_gs]",
+        "no factor for M332194681 from 2^39 to 2^41 [mfaktc 0.21 barrett76_mul32_gs]",
+        NO_FACTOR,
+        "M51234559 is not prime. Res64: 1A2B3C4D5E6F7081. Wh8: 00000000,00000000",
+    ]
+    .iter()
+    .map(|line| line.to_string())
+    .collect::<Vec<_>>();
+
+    let report = FactorReport::from_results(&results);
+
+    assert_eq!(report.factors.len(), 1);
+    assert_eq!(report.factors[0].exponent, 332194679);
+    assert_eq!(report.factors[0].line, GOOD_FACTOR);
+    assert_eq!(
+        report.yields.get(&39),
+        Some(&BitLevelYield {
+            tested: 2,
+            found: 1
+        })
+    );
+    assert_eq!(report.yields.get(&40).map(|level| level.tested), Some(1));
+    assert_eq!(report.yields.get(&74).map(|level| level.found), Some(0));
+    let text = report.to_string();
+    assert!(text.starts_with("1 factor(s) found:\n\nM332194679 has a factor: 701595162049\n"));
+    assert!(text.contains("    k:     1056\n    Class: 49 mod 120, pass 8 of 16"));
+    assert!(text.contains("\n    39-40           2      1      0.05"));
+    assert_eq!(
+        FactorReport::from_results(&[]).to_string(),
+        "No factors found."
+    );
+}
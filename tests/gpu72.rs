@@ -0,0 +1,107 @@
+mod common;
+
+use common::*;
+use primenet_rs::{
+    error::Error,
+    gpu72_runtime::gpu72_runtime,
+    gpu72_work::{Gpu72LLTFWorkOption, Gpu72WorkType},
+    options::Gpu72Options,
+};
+use std::fs::write;
+
+const TF_1: &str = "Factor=N/A,332194529,74,75";
+const TF_2: &str = "Factor=N/A,332194559,74,75";
+const TF_RESULT: &str =
+    "no factor for M332194511 from 2^74 to 2^75 [mfakto 0.15pre6-Win cl_barrett15_82_gs_2]";
+
+fn options(
+    dir: &tempfile::TempDir,
+    server: &MockServer,
+    gpu72_password: &str,
+    primenet: bool,
+    num_cache: usize,
+) -> Gpu72Options {
+    Gpu72Options {
+        primenet_credentials: if primenet {
+            Some((PRIMENET_USER.to_string(), PRIMENET_PASS.to_string()))
+        } else {
+            None
+        },
+        gpu72_credentials: (GPU72_USER.to_string(), gpu72_password.to_string()),
+        work_type: Gpu72WorkType::LucasLehmerTrialFactor(Gpu72LLTFWorkOption::LowestExponent),
+        max_exp: 75,
+        general_options: general_options(dir, server, num_cache),
+    }
+}
+
+#[test]
+fn caches_trial_factoring_work() {
+    let server = MockServer::start();
+    server.add_gpu72_work(&[TF_1, TF_2]);
+    let dir = example_workdir("mfakto-example-workdir");
+
+    gpu72_runtime(options(&dir, &server, GPU72_PASS, false, 2)).unwrap();
+
+    assert_eq!(lines(dir.path().join("worktodo.txt")), vec![TF_1, TF_2]);
+    let requests = server.requests_to("GET", "/account/getassignments/lltf");
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].query_param("Number"), Some("2"));
+    assert_eq!(requests[0].query_param("Option"), Some("3"));
+    assert_eq!(requests[0].query_param("Pledge"), Some("75"));
+    assert_eq!(requests[0].query_param("Low"), Some("0"));
+    assert_eq!(requests[0].query_param("High"), Some("10000000000"));
+    // Without Primenet credentials nothing is submitted.
+    assert!(server.requests_to("POST", "/").is_empty());
+    assert!(server.requests_to("POST", "/manual_result/").is_empty());
+    assert_unlocked(&dir);
+}
+
+#[test]
+fn submits_results_to_primenet_when_given_credentials() {
+    let server = MockServer::start();
+    server.add_gpu72_work(&[TF_1]);
+    let dir = example_workdir("mfakto-example-workdir");
+    write(dir.path().join("results.txt"), format!("{}\n", TF_RESULT)).unwrap();
+
+    gpu72_runtime(options(&dir, &server, GPU72_PASS, true, 1)).unwrap();
+
+    assert_eq!(lines(dir.path().join("worktodo.txt")), vec![TF_1]);
+    assert_eq!(lines(dir.path().join("results.sent")), vec![TF_RESULT]);
+    assert!(lines(dir.path().join("results.txt")).is_empty());
+    assert_unlocked(&dir);
+}
+
+#[test]
+fn rejected_credentials_are_an_authentication_error() {
+    let server = MockServer::start();
+    let dir = example_workdir("mfakto-example-workdir");
+
+    let result = gpu72_runtime(options(&dir, &server, "wrong", false, 1));
+
+    assert!(matches!(result, Err(Error::Authentication(_))));
+    assert!(server
+        .requests_to("GET", "/account/getassignments/lltf")
+        .is_empty());
+    assert_unlocked(&dir);
+}
+
+#[test]
+fn server_error_is_reported_and_nothing_is_cached() {
+    let server = MockServer::start();
+    server.add_gpu72_work(&[TF_1]);
+    server.script(
+        "GET",
+        "/account/getassignments/lltf",
+        MockResponse::new(500, "Internal Server Error"),
+    );
+    let dir = example_workdir("mfakto-example-workdir");
+
+    let result = gpu72_runtime(options(&dir, &server, GPU72_PASS, false, 1));
+
+    match result {
+        Err(Error::HttpStatus { status, .. }) => assert_eq!(status, 500),
+        other => panic!("Expected an HTTP status error, got {:?}", other),
+    }
+    assert!(lines(dir.path().join("worktodo.txt")).is_empty());
+    assert_unlocked(&dir);
+}
@@ -0,0 +1,172 @@
+mod common;
+
+use common::*;
+use primenet_rs::{
+    assignment::{Assignment, AssignmentKind},
+    client::Client,
+    gpuowl::{checkpoint_iteration, checkpoint_progress, Progress},
+    options::{PrimenetOptions, ProofOptions},
+    p95_work::PrimenetWorkType,
+    primenet_runtime::primenet_runtime,
+    work_directory::WorkDirectory,
+    work_result::{ResultKind, WorkResult},
+};
+use std::fs::{create_dir, write};
+
+const PRP_1: &str = "PRP=BC914675C81023F252E92CF034BEFF6C,1,2,96364649,-1,77,0";
+const PRP_2: &str = "PRP=7A30B8B6C0FC79C534A271D9561F7DCC,1,2,96364691,-1,77,0";
+const PRP_RESULT: &str = r#"{"status":"C", "exponent":96364649, "worktype":"PRP-3", "res64":"1A2B3C4D5E6F7081", "aid":"BC914675C81023F252E92CF034BEFF6C"}"#;
+
+#[test]
+fn checkpoint_headers_give_the_iteration() {
+    assert_eq!(
+        checkpoint_iteration(
+            "PRP 12 96364649 2500000 1000 1a2b3c4d5e6f7081 0 9f8e7d6c\n",
+            96364649
+        ),
+        Some(2500000)
+    );
+    assert_eq!(
+        checkpoint_iteration(
+            "OWL PRP 9 96364649 1200000 400 0123456789abcdef\n",
+            96364649
+        ),
+        Some(1200000)
+    );
+    assert_eq!(
+        checkpoint_iteration("PRP 12 96364691 2500000\n", 96364649),
+        None
+    );
+}
+
+#[test]
+fn progress_comes_from_the_newest_checkpoint() {
+    let dir = tempfile::tempdir().unwrap();
+    let checkpoints = dir.path().join("96364649");
+    create_dir(&checkpoints).unwrap();
+    let mut newest = b"PRP 12 96364649 48182324 1000 1a2b3c4d5e6f7081 0 9f8e7d6c\n".to_vec();
+    // The header is followed by the binary residue.
+    newest.extend_from_slice(&[0xff, 0x00, 0x9a, b'\n', 0x17]);
+    write(checkpoints.join("96364649.prp"), newest).unwrap();
+    write(
+        checkpoints.join("96364649-old.prp"),
+        "PRP 12 96364649 48000000 1000 0123456789abcdef 0 12345678\n",
+    )
+    .unwrap();
+    write(checkpoints.join("96364649.log"), "96364649 99999999\n").unwrap();
+
+    let progress = checkpoint_progress(dir.path(), 96364649).unwrap();
+
+    assert_eq!(
+        progress,
+        Progress {
+            exponent: 96364649,
+            iteration: 48182324,
+            iterations: 96364649,
+        }
+    );
+    assert_eq!(
+        progress.to_string(),
+        "M96364649 is 50.0% done (48182324 of 96364649 iterations)"
+    );
+    assert_eq!(checkpoint_progress(dir.path(), 96364691), None);
+
+    let work_directory = WorkDirectory::for_client(dir.path(), Client::Gpuowl);
+    let assignment = Assignment::parse(PRP_1).unwrap();
+    assert_eq!(work_directory.progress(&assignment), Some(progress));
+    let mlucas = WorkDirectory::for_client(dir.path(), Client::Mlucas);
+    assert_eq!(mlucas.progress(&assignment), None);
+}
+
+#[test]
+fn gpuowl_worktodo_and_results_lines_parse() {
+    let assignment =
+        Assignment::parse("B1=1000000,B2=30000000;PFactor=N/A,1,2,96364649,-1,77,2").unwrap();
+    assert_eq!(assignment.kind, AssignmentKind::Pfactor);
+    assert_eq!(assignment.exponent, 96364649);
+
+    let factor = WorkResult::parse(
+        r#"{"status":"F", "exponent":"96364649", "worktype":"PM1", "factors":["1193028769481932346719"]}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        factor.kind,
+        ResultKind::Factor("1193028769481932346719".to_string())
+    );
+    let no_factor = WorkResult::parse(
+        r#"{"status":"NF", "exponent":"96364649", "worktype":"PM1", "B1":"1000000"}"#,
+    )
+    .unwrap();
+    assert_eq!(no_factor.kind, ResultKind::NoFactor);
+    assert_eq!(
+        WorkResult::parse(PRP_RESULT).unwrap().kind,
+        ResultKind::Composite
+    );
+}
+
+#[test]
+fn gpuowl_prp_results_are_submitted() {
+    let server = MockServer::start();
+    server.add_primenet_work(&[PRP_2]);
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path().join("worktodo.txt"), format!("{}\n", PRP_1)).unwrap();
+    write(dir.path().join("results.txt"), format!("{}\n", PRP_RESULT)).unwrap();
+    let mut general_options = general_options(&dir, &server, 2);
+    general_options.client = Some(Client::Gpuowl);
+
+    primenet_runtime(PrimenetOptions {
+        credentials: (PRIMENET_USER.to_string(), PRIMENET_PASS.to_string()),
+        work_type: PrimenetWorkType::DoubleCheckPrpTests,
+        fallback_work_type: None,
+        cores: 1,
+        proofs: ProofOptions::Archive,
+        general_options,
+    })
+    .unwrap();
+
+    assert_eq!(lines(dir.path().join("worktodo.txt")), vec![PRP_1, PRP_2]);
+    assert_eq!(lines(dir.path().join("results.sent")), vec![PRP_RESULT]);
+    let submissions = server.requests_to("POST", "/manual_result/");
+    assert_eq!(submissions.len(), 1);
+    assert_eq!(
+        submissions[0].form_param("data").as_deref(),
+        Some(PRP_RESULT)
+    );
+    assert_unlocked(&dir);
+}
+
+#[test]
+fn cert_work_is_cached_and_its_results_submitted() {
+    const CERT_1: &str = "Cert=B2EE67DC0A514E85D0F8A82544CF9B17,1,2,110000117,-1,430000";
+    const CERT_RESULT: &str = r#"{"status":"C", "exponent":110000117, "worktype":"Cert", "sha3-hash":"2E5C0A4B9D1F83E67A0C5B2D9F4E1A37", "aid":"B2EE67DC0A514E85D0F8A82544CF9B17"}"#;
+    let server = MockServer::start();
+    server.add_primenet_work(&[CERT_1]);
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path().join("worktodo.txt"), "").unwrap();
+    write(dir.path().join("results.txt"), format!("{}\n", CERT_RESULT)).unwrap();
+    let mut general_options = general_options(&dir, &server, 1);
+    general_options.client = Some(Client::Gpuowl);
+
+    primenet_runtime(PrimenetOptions {
+        credentials: (PRIMENET_USER.to_string(), PRIMENET_PASS.to_string()),
+        work_type: PrimenetWorkType::Certification,
+        fallback_work_type: None,
+        cores: 1,
+        proofs: ProofOptions::Archive,
+        general_options,
+    })
+    .unwrap();
+
+    let assignment = Assignment::parse(CERT_1).unwrap();
+    assert_eq!(assignment.kind, AssignmentKind::Cert);
+    assert_eq!(assignment.exponent, 110000117);
+    assert_eq!(lines(dir.path().join("worktodo.txt")), vec![CERT_1]);
+    let requests = server.requests_to("GET", "/manual_assignment/");
+    assert_eq!(requests[0].query_param("pref"), Some("200"));
+    assert_eq!(
+        WorkResult::parse(CERT_RESULT).unwrap().kind,
+        ResultKind::Cert
+    );
+    assert_eq!(lines(dir.path().join("results.sent")), vec![CERT_RESULT]);
+    assert_unlocked(&dir);
+}
@@ -0,0 +1,154 @@
+mod common;
+
+use common::*;
+use primenet_rs::{
+    error::Error,
+    options::{PrimenetOptions, ProofOptions},
+    p95_work::PrimenetWorkType,
+    primenet_runtime::primenet_runtime,
+    submission_queue::{Quarantine, SubmissionQueue},
+};
+use std::fs::write;
+
+const DC_1: &str = "DoubleCheck=7A30B8B6C0FC79C534A271D9561F7DCC,51234577,73,1";
+const DC_2: &str = "DoubleCheck=BC914675C81023F252E92CF034BEFF6C,51234611,73,1";
+const RESULT_1: &str = "M51234559 is not prime. Res64: 1A2B3C4D5E6F7081. Wh8: 00000000,00000000";
+const RESULT_2: &str = "M51234563 is not prime. Res64: 8070605040302010. Wh8: 00000000,00000000";
+
+fn options(
+    dir: &tempfile::TempDir,
+    server: &MockServer,
+    password: &str,
+    num_cache: usize,
+) -> PrimenetOptions {
+    PrimenetOptions {
+        credentials: (PRIMENET_USER.to_string(), password.to_string()),
+        work_type: PrimenetWorkType::DoubleCheckLlTests,
+        fallback_work_type: None,
+        cores: 1,
+        proofs: ProofOptions::Archive,
+        general_options: general_options(dir, server, num_cache),
+    }
+}
+
+#[test]
+fn caches_assignments_and_submits_results() {
+    let server = MockServer::start();
+    server.add_primenet_work(&[DC_1, DC_2]);
+    let dir = example_workdir("mlucas-example-workdir");
+    write(dir.path().join("results.txt"), format!("{}\n", RESULT_1)).unwrap();
+
+    primenet_runtime(options(&dir, &server, PRIMENET_PASS, 2)).unwrap();
+
+    assert_eq!(lines(dir.path().join("worktodo.ini")), vec![DC_1, DC_2]);
+    assert_eq!(lines(dir.path().join("results.sent")), vec![RESULT_1]);
+    assert!(lines(dir.path().join("results.txt")).is_empty());
+    let requests = server.requests_to("GET", "/manual_assignment/");
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].query_param("num_to_get"), Some("2"));
+    assert_eq!(requests[0].query_param("pref"), Some("101"));
+    let submissions = server.requests_to("POST", "/manual_result/");
+    assert_eq!(submissions.len(), 1);
+    assert_eq!(submissions[0].form_param("data").as_deref(), Some(RESULT_1));
+    assert_unlocked(&dir);
+}
+
+#[test]
+fn only_requests_what_is_missing() {
+    let server = MockServer::start();
+    server.add_primenet_work(&[DC_2]);
+    let dir = example_workdir("mfakto-example-workdir");
+    write(
+        dir.path().join("worktodo.txt"),
+        format!("# cached earlier\n{}\n", DC_1),
+    )
+    .unwrap();
+
+    primenet_runtime(options(&dir, &server, PRIMENET_PASS, 2)).unwrap();
+
+    let requests = server.requests_to("GET", "/manual_assignment/");
+    assert_eq!(requests[0].query_param("num_to_get"), Some("1"));
+    assert_eq!(
+        lines(dir.path().join("worktodo.txt")),
+        vec!["# cached earlier", DC_1, DC_2]
+    );
+
+    // The cache is full now, so another pass doesn't ask for anything.
+    primenet_runtime(options(&dir, &server, PRIMENET_PASS, 2)).unwrap();
+    assert_eq!(server.requests_to("GET", "/manual_assignment/").len(), 1);
+    assert_unlocked(&dir);
+}
+
+#[test]
+fn rejected_results_are_quarantined() {
+    let server = MockServer::start();
+    server.reject_result(RESULT_2, "code 40: No assignment found for exponent");
+    let dir = example_workdir("mlucas-example-workdir");
+    write(
+        dir.path().join("results.txt"),
+        format!("{}\n{}\n", RESULT_1, RESULT_2),
+    )
+    .unwrap();
+
+    primenet_runtime(options(&dir, &server, PRIMENET_PASS, 0)).unwrap();
+
+    assert_eq!(lines(dir.path().join("results.sent")), vec![RESULT_1]);
+    assert!(lines(dir.path().join("results.txt")).is_empty());
+    assert!(
+        SubmissionQueue::load(&dir.path().join("results.queue.json"))
+            .unwrap()
+            .is_empty()
+    );
+    let quarantine = Quarantine::load(&dir.path().join("results.quarantine")).unwrap();
+    assert_eq!(quarantine.entries.len(), 1);
+    assert_eq!(quarantine.entries[0].0, RESULT_2);
+    assert!(quarantine.entries[0].1.contains("code 40"));
+
+    // It's never sent again.
+    primenet_runtime(options(&dir, &server, PRIMENET_PASS, 0)).unwrap();
+    assert_eq!(server.requests_to("POST", "/manual_result/").len(), 2);
+    assert_unlocked(&dir);
+}
+
+#[test]
+fn bad_credentials_are_an_authentication_error() {
+    let server = MockServer::start();
+    let dir = example_workdir("mlucas-example-workdir");
+
+    let result = primenet_runtime(options(&dir, &server, "wrong", 1));
+
+    assert!(matches!(result, Err(Error::Authentication(_))));
+    assert!(server.requests_to("GET", "/manual_assignment/").is_empty());
+    assert_unlocked(&dir);
+}
+
+#[test]
+fn server_error_releases_the_worktodo_lock() {
+    let server = MockServer::start();
+    server.script(
+        "GET",
+        "/manual_assignment/",
+        MockResponse::new(503, "Down for maintenance"),
+    );
+    let dir = example_workdir("mlucas-example-workdir");
+
+    let result = primenet_runtime(options(&dir, &server, PRIMENET_PASS, 1));
+
+    match result {
+        Err(Error::HttpStatus { status, .. }) => assert_eq!(status, 503),
+        other => panic!("Expected an HTTP status error, got {:?}", other),
+    }
+    assert!(lines(dir.path().join("worktodo.ini")).is_empty());
+    assert_unlocked(&dir);
+}
+
+#[test]
+fn response_without_assignments_writes_nothing() {
+    let server = MockServer::start();
+    let dir = example_workdir("mlucas-example-workdir");
+
+    primenet_runtime(options(&dir, &server, PRIMENET_PASS, 1)).unwrap();
+
+    assert!(lines(dir.path().join("worktodo.ini")).is_empty());
+    assert_unlocked(&dir);
+}
@@ -0,0 +1,164 @@
+use primenet_rs::primenet_pages::{
+    assignments, login_state, no_assignments_reason, submission_results, under_maintenance,
+    LoginState, NoAssignmentsReason, ResultMessage, ResultOutcome,
+};
+
+const RESULT_1: &str = "M51234559 is not prime. Res64: 1A2B3C4D5E6F7081. Wh8: 00000000,00000000";
+const RESULT_2: &str = "M51234563 is not prime. Res64: 8070605040302010. Wh8: 00000000,00000000";
+const RESULT_3: &str = "M51234571 is not prime. Res64: 0F0E0D0C0B0A0908. Wh8: 00000000,00000000";
+
+macro_rules! fixture {
+    ($name:literal) => {
+        include_str!(concat!("fixtures/primenet/", $name, ".html"))
+    };
+}
+
+#[test]
+fn login_state_comes_from_the_login_box() {
+    let logged_in = login_state(fixture!("front_page_logged_in"));
+    assert_eq!(logged_in, LoginState::LoggedIn("Test_User".to_string()));
+    assert!(logged_in.is_logged_in_as("test_user"));
+    assert!(!logged_in.is_logged_in_as("other_user"));
+
+    assert_eq!(
+        login_state(fixture!("front_page_logged_out")),
+        LoginState::LoggedOut
+    );
+    assert_eq!(login_state(fixture!("login_failed")), LoginState::LoggedOut);
+    assert_eq!(
+        login_state(fixture!("manual_assignment_logged_out")),
+        LoginState::LoggedOut
+    );
+    assert_eq!(
+        login_state("<html><body>Service Unavailable</body></html>"),
+        LoginState::Unknown
+    );
+}
+
+#[test]
+fn assignments_come_from_the_page_not_its_instructions() {
+    let lines = assignments(fixture!("manual_assignment"))
+        .into_iter()
+        .map(|assignment| assignment.line)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        lines,
+        vec![
+            "DoubleCheck=7A30B8B6C0FC79C534A271D9561F7DCC,51234577,73,1",
+            "DoubleCheck=BC914675C81023F252E92CF034BEFF6C,51234611,73,1",
+        ]
+    );
+    assert!(assignments(fixture!("manual_assignment_logged_out")).is_empty());
+}
+
+#[test]
+fn accepted_result_has_its_credit() {
+    assert_eq!(
+        submission_results(fixture!("manual_result_accepted")),
+        vec![ResultMessage {
+            result: RESULT_1.to_string(),
+            outcome: ResultOutcome::Accepted {
+                ghz_days: Some(71.462)
+            },
+        }]
+    );
+}
+
+#[test]
+fn rejected_result_has_the_error_message() {
+    assert_eq!(
+        submission_results(fixture!("manual_result_rejected")),
+        vec![ResultMessage {
+            result: RESULT_2.to_string(),
+            outcome: ResultOutcome::Rejected(
+                "Error code: 40, error text: No assignment found for exponent 51234563".to_string()
+            ),
+        }]
+    );
+}
+
+#[test]
+fn unclosed_error_is_still_read() {
+    assert_eq!(
+        submission_results(fixture!("manual_result_unclosed_error")),
+        vec![ResultMessage {
+            result: RESULT_2.to_string(),
+            outcome: ResultOutcome::Rejected(
+                "Error code: 41, error text: Result already submitted".to_string()
+            ),
+        }]
+    );
+}
+
+#[test]
+fn each_result_gets_its_own_outcome() {
+    let messages = submission_results(fixture!("manual_result_mixed"));
+
+    let outcomes = messages
+        .iter()
+        .map(|message| (message.result.as_str(), message.outcome.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        outcomes,
+        vec![
+            (
+                "no factor for M332194529 from 2^74 to 2^75 [mfaktc 0.21 barrett76_mul32_gs]",
+                ResultOutcome::Accepted {
+                    ghz_days: Some(12.8163)
+                }
+            ),
+            (
+                RESULT_2,
+                ResultOutcome::Rejected(
+                    "Error code: 40, error text: No assignment found for exponent 51234563"
+                        .to_string()
+                )
+            ),
+            (RESULT_3, ResultOutcome::Accepted { ghz_days: None }),
+        ]
+    );
+}
+
+#[test]
+fn page_without_results_says_nothing() {
+    assert!(submission_results(fixture!("front_page_logged_in")).is_empty());
+}
+
+#[test]
+fn no_assignments_reasons_are_told_apart() {
+    let cases = [
+        (
+            fixture!("manual_assignment_no_work"),
+            NoAssignmentsReason::NoWork,
+        ),
+        (
+            fixture!("manual_assignment_limit"),
+            NoAssignmentsReason::LimitReached,
+        ),
+        (
+            fixture!("manual_assignment_range_empty"),
+            NoAssignmentsReason::RangeEmpty,
+        ),
+        (
+            fixture!("manual_assignment_maintenance"),
+            NoAssignmentsReason::Maintenance,
+        ),
+        (
+            fixture!("manual_assignment_logged_out"),
+            NoAssignmentsReason::LoggedOut,
+        ),
+        (fixture!("manual_assignment"), NoAssignmentsReason::Unknown),
+        // Saying "in the range" on its own isn't about the requested range.
+        (
+            "<p>No assignments available. Exponents in the range 60M to 70M are being tested \
+            first.</p>",
+            NoAssignmentsReason::NoWork,
+        ),
+    ];
+    for (page, reason) in &cases {
+        assert_eq!(no_assignments_reason(page), *reason);
+    }
+    assert!(under_maintenance(fixture!("manual_assignment_maintenance")));
+    assert!(!under_maintenance(fixture!("manual_result_accepted")));
+}
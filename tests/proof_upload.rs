@@ -0,0 +1,225 @@
+mod common;
+
+use common::*;
+use primenet_rs::{
+    client::Client,
+    error::Error,
+    options::{PrimenetOptions, ProofOptions},
+    p95_work::PrimenetWorkType,
+    primenet_runtime::primenet_runtime,
+    proof_upload::{
+        file_md5, matching_result, proof_exponent, proof_files, upload_proof, ProofFile,
+    },
+    transport::ReqwestTransport,
+};
+use std::fs::{create_dir_all, write};
+use std::path::Path;
+
+const PRP_1: &str = "PRP=BC914675C81023F252E92CF034BEFF6C,1,2,96364649,-1,77,0";
+
+// The text header every proof starts with, then some stand-in for the binary part.
+fn proof_contents(exponent: u64) -> Vec<u8> {
+    let mut contents = format!(
+        "PRP PROOF\nVERSION=2\nHASHSIZE=64\nPOWER=8\nNUMBER=M{}\n",
+        exponent
+    )
+    .into_bytes();
+    contents.extend((0..200u32).map(|i| (i * 7 % 256) as u8));
+    contents
+}
+
+fn prp_result(exponent: u64, md5: &str) -> String {
+    format!(
+        r#"{{"status":"C", "exponent":{}, "worktype":"PRP-3", "res64":"1A2B3C4D5E6F7081", "proof":{{"version":2, "power":8, "hashsize":64, "md5":"{}"}}, "aid":"BC914675C81023F252E92CF034BEFF6C"}}"#,
+        exponent, md5
+    )
+}
+
+fn options(dir: &tempfile::TempDir, server: &MockServer, proofs: ProofOptions) -> PrimenetOptions {
+    let mut general_options = general_options(dir, server, 0);
+    general_options.client = Some(Client::Gpuowl);
+    PrimenetOptions {
+        credentials: (PRIMENET_USER.to_string(), PRIMENET_PASS.to_string()),
+        work_type: PrimenetWorkType::DoubleCheckPrpTests,
+        fallback_work_type: None,
+        cores: 1,
+        proofs,
+        general_options,
+    }
+}
+
+fn write_proof(path: &Path, exponent: u64) -> String {
+    create_dir_all(path.parent().unwrap()).unwrap();
+    let contents = proof_contents(exponent);
+    write(path, &contents).unwrap();
+    md5_hex(&contents)
+}
+
+#[test]
+fn proofs_are_found_and_matched_to_their_results() {
+    let dir = tempfile::tempdir().unwrap();
+    let md5 = write_proof(
+        &dir.path().join("96364649/proof/96364649-8.proof"),
+        96364649,
+    );
+    write_proof(&dir.path().join("96364691.proof"), 96364691);
+    write_proof(
+        &dir.path().join("proofs-uploaded/96364651-8.proof"),
+        96364651,
+    );
+    write(dir.path().join("notes.proof"), "Not a proof\n").unwrap();
+
+    let proofs = proof_files(dir.path());
+
+    assert_eq!(
+        proofs,
+        vec![
+            ProofFile {
+                path: dir.path().join("96364649/proof/96364649-8.proof"),
+                exponent: 96364649,
+            },
+            ProofFile {
+                path: dir.path().join("96364691.proof"),
+                exponent: 96364691,
+            },
+        ]
+    );
+    assert_eq!(
+        proof_exponent("PRP PROOF\nVERSION=2\nNUMBER=M96364649/2833\n"),
+        Some(96364649)
+    );
+    let results = vec![
+        "M96364649 is not prime. Res64: 1A2B3C4D5E6F7081.".to_string(),
+        r#"{"status":"C", "exponent":96364649, "worktype":"PRP-3", "res64":"0000000000000000"}"#
+            .to_string(),
+        prp_result(96364649, &md5),
+    ];
+    assert_eq!(
+        matching_result(&results, &proofs[0]),
+        Some(prp_result(96364649, &md5))
+    );
+    assert_eq!(matching_result(&results, &proofs[1]), None);
+}
+
+#[test]
+fn proofs_are_uploaded_and_archived_after_their_results() {
+    let server = MockServer::start();
+    let dir = tempfile::tempdir().unwrap();
+    let proof_path = dir.path().join("96364649/proof/96364649-8.proof");
+    let md5 = write_proof(&proof_path, 96364649);
+    // No result yet, so this one has to wait.
+    write_proof(&dir.path().join("96364691.proof"), 96364691);
+    write(dir.path().join("worktodo.txt"), format!("{}\n", PRP_1)).unwrap();
+    write(
+        dir.path().join("results.txt"),
+        format!("{}\n", prp_result(96364649, &md5)),
+    )
+    .unwrap();
+
+    primenet_runtime(options(&dir, &server, ProofOptions::Archive)).unwrap();
+
+    assert_eq!(
+        lines(dir.path().join("results.sent")),
+        vec![prp_result(96364649, &md5)]
+    );
+    let proof = server.proof(&md5).unwrap();
+    assert_eq!(proof.exponent, 96364649);
+    assert_eq!(proof.data, proof_contents(96364649));
+    assert!(!proof_path.exists());
+    assert!(dir.path().join("proofs-uploaded/96364649-8.proof").exists());
+    assert!(dir.path().join("96364691.proof").exists());
+    let requests = server.requests_to("GET", "/proof_upload/");
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].query_param("UserID"), Some(PRIMENET_USER));
+    assert_eq!(requests[0].query_param("FileMD5"), Some(md5.as_str()));
+    assert_unlocked(&dir);
+
+    // Running again doesn't upload anything else.
+    primenet_runtime(options(&dir, &server, ProofOptions::Archive)).unwrap();
+    assert_eq!(server.requests_to("GET", "/proof_upload/").len(), 1);
+}
+
+#[test]
+fn uploads_resume_where_the_server_left_off() {
+    let server = MockServer::start();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("96364649.proof");
+    let md5 = write_proof(&path, 96364649);
+    let contents = proof_contents(96364649);
+    let proof = ProofFile {
+        path,
+        exponent: 96364649,
+    };
+    let transport = ReqwestTransport::new().unwrap();
+    let upload = |chunk_size| {
+        let (md5, size) = file_md5(&proof.path).unwrap();
+        upload_proof(
+            &transport,
+            &server.endpoints(),
+            PRIMENET_USER,
+            &proof,
+            &md5,
+            size,
+            chunk_size,
+        )
+    };
+
+    // The first piece never arrives.
+    server.script("POST", "/proof_upload/chunk/", MockResponse::new(500, ""));
+    assert!(matches!(
+        upload(100),
+        Err(Error::HttpStatus { status: 500, .. })
+    ));
+    assert!(server.proof(&md5).unwrap().data.is_empty());
+
+    // A previous run got the first 100 bytes through.
+    server.seed_proof(&md5, 96364649, contents.len(), &contents[..100]);
+    upload(100).unwrap();
+
+    assert_eq!(server.proof(&md5).unwrap().data, contents);
+    let offsets = server
+        .requests_to("POST", "/proof_upload/chunk/")
+        .iter()
+        .map(|request| request.query_param("DataOffset").unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(offsets, vec!["0", "100", "200"]);
+    // The server already has it all.
+    upload(100).unwrap();
+    assert_eq!(server.requests_to("POST", "/proof_upload/chunk/").len(), 3);
+}
+
+#[test]
+fn proofs_that_dont_match_their_result_are_not_uploaded() {
+    let server = MockServer::start();
+    let dir = tempfile::tempdir().unwrap();
+    let mismatched = dir.path().join("96364649.proof");
+    write_proof(&mismatched, 96364649);
+    let deleted = dir.path().join("96364691.proof");
+    let md5 = write_proof(&deleted, 96364691);
+    write(dir.path().join("worktodo.txt"), "").unwrap();
+    write(dir.path().join("results.txt"), "").unwrap();
+    write(
+        dir.path().join("results.sent"),
+        format!(
+            "{}\n{}\n",
+            prp_result(96364649, "0123456789abcdef0123456789abcdef"),
+            prp_result(96364691, &md5)
+        ),
+    )
+    .unwrap();
+
+    primenet_runtime(options(&dir, &server, ProofOptions::Delete)).unwrap();
+
+    assert!(mismatched.exists());
+    assert!(!deleted.exists());
+    assert!(!dir.path().join("proofs-uploaded").exists());
+    let requests = server.requests_to("GET", "/proof_upload/");
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].query_param("Exponent"), Some("96364691"));
+
+    // Nothing is uploaded with uploads turned off.
+    write_proof(&deleted, 96364691);
+    primenet_runtime(options(&dir, &server, ProofOptions::NoUpload)).unwrap();
+    assert!(deleted.exists());
+    assert_eq!(server.requests_to("GET", "/proof_upload/").len(), 1);
+}
@@ -0,0 +1,169 @@
+mod common;
+
+use chrono::{Duration, TimeZone, Utc};
+use common::{example_workdir, lines};
+use primenet_rs::{
+    endpoints::Endpoints,
+    primenet_runtime::primenet_submit,
+    submission_queue::{Quarantine, SubmissionQueue, QUEUE_RETRY_DELAY, QUEUE_RETRY_MAX_DELAY},
+    transport::ScriptedTransport,
+    util::append_quarantine,
+    work_directory::WorkDirectory,
+};
+use std::fs::write;
+
+const RESULT_1: &str = "M51234559 is not prime. Res64: 1A2B3C4D5E6F7081. Wh8: 00000000,00000000";
+const RESULT_2: &str = "M51234563 is not prime. Res64: 8070605040302010. Wh8: 00000000,00000000";
+
+#[test]
+fn missing_queue_file_is_an_empty_queue() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let queue = SubmissionQueue::load(&dir.path().join("results.queue.json")).unwrap();
+
+    assert!(queue.is_empty());
+    assert_eq!(
+        queue.to_string(),
+        "No results waiting to be submitted to Primenet."
+    );
+}
+
+#[test]
+fn queue_survives_a_round_trip_through_the_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("results.queue.json");
+    let now = Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap();
+    let mut queue = SubmissionQueue::default();
+    assert!(queue.push(RESULT_1.to_string(), now));
+    assert!(queue.push(RESULT_2.to_string(), now));
+    assert!(!queue.push(RESULT_1.to_string(), now));
+    queue.record_failure(RESULT_1, "Server unavailable".to_string(), now);
+
+    queue.save(&path).unwrap();
+
+    assert_eq!(SubmissionQueue::load(&path).unwrap(), queue);
+    assert!(!dir.path().join("results.queue.json.tmp").exists());
+}
+
+#[test]
+fn failed_attempts_back_off() {
+    let now = Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap();
+    let mut queue = SubmissionQueue::default();
+    queue.push(RESULT_1.to_string(), now);
+    assert_eq!(queue.due(now), vec![RESULT_1]);
+
+    queue.record_failure(RESULT_1, "Server unavailable".to_string(), now);
+    let entry = &queue.entries[0];
+    assert_eq!(entry.attempts, 1);
+    assert_eq!(entry.last_attempt, Some(now));
+    let wait = entry.next_attempt - now;
+    assert!(wait >= Duration::seconds(QUEUE_RETRY_DELAY as i64 / 2));
+    assert!(wait <= Duration::seconds(QUEUE_RETRY_DELAY as i64));
+    assert!(queue.due(now).is_empty());
+    assert_eq!(queue.due(entry.next_attempt), vec![RESULT_1]);
+
+    for _ in 0..20 {
+        queue.record_failure(RESULT_1, "Server unavailable".to_string(), now);
+    }
+    assert!(queue.entries[0].next_attempt - now <= Duration::seconds(QUEUE_RETRY_MAX_DELAY as i64));
+}
+
+#[test]
+fn quarantine_reads_back_what_was_appended() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("results.quarantine");
+    assert!(Quarantine::load(&path).unwrap().entries.is_empty());
+    let now = Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap();
+    append_quarantine(
+        &path,
+        &[(
+            RESULT_1.to_string(),
+            "Rejected by Primenet: code 40".to_string(),
+        )],
+        now,
+    )
+    .unwrap();
+    append_quarantine(
+        &path,
+        &[(
+            RESULT_2.to_string(),
+            "Rejected by Primenet: code 41".to_string(),
+        )],
+        now,
+    )
+    .unwrap();
+
+    let quarantine = Quarantine::load(&path).unwrap();
+
+    assert_eq!(
+        quarantine.entries,
+        vec![
+            (
+                RESULT_1.to_string(),
+                "2026-01-01 12:00:00 UTC: Rejected by Primenet: code 40".to_string()
+            ),
+            (
+                RESULT_2.to_string(),
+                "2026-01-01 12:00:00 UTC: Rejected by Primenet: code 41".to_string()
+            ),
+        ]
+    );
+    assert!(quarantine
+        .to_string()
+        .starts_with("2 result(s) quarantined and not being submitted:"));
+}
+
+#[test]
+fn queue_drains_once_the_server_is_back() {
+    let dir = example_workdir("mlucas-example-workdir");
+    let work_directory = WorkDirectory::new(dir.path());
+    write(
+        &work_directory.results,
+        format!("{}\n{}\n", RESULT_1, RESULT_2),
+    )
+    .unwrap();
+    let down = ScriptedTransport::new();
+    down.fail("connection refused");
+    assert!(primenet_submit(&down, &Endpoints::default(), &work_directory).is_err());
+    assert_eq!(
+        SubmissionQueue::load(&work_directory.queue).unwrap().len(),
+        2
+    );
+
+    // Pretend the backoff has passed, as it would have by the next update.
+    let mut queue = SubmissionQueue::load(&work_directory.queue).unwrap();
+    for entry in &mut queue.entries {
+        entry.next_attempt = Utc::now() - Duration::seconds(1);
+    }
+    queue.save(&work_directory.queue).unwrap();
+    let up = ScriptedTransport::new();
+    up.respond(200, "Accepted").respond(200, "Accepted");
+
+    primenet_submit(&up, &Endpoints::default(), &work_directory).unwrap();
+
+    assert_eq!(
+        lines(work_directory.results_sent.clone()),
+        vec![RESULT_1, RESULT_2]
+    );
+    assert!(SubmissionQueue::load(&work_directory.queue)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn results_already_sent_are_taken_off_the_queue() {
+    let dir = example_workdir("mlucas-example-workdir");
+    let work_directory = WorkDirectory::new(dir.path());
+    let mut queue = SubmissionQueue::default();
+    queue.push(RESULT_1.to_string(), Utc::now());
+    queue.save(&work_directory.queue).unwrap();
+    write(&work_directory.results_sent, format!("{}\n", RESULT_1)).unwrap();
+    let transport = ScriptedTransport::new();
+
+    primenet_submit(&transport, &Endpoints::default(), &work_directory).unwrap();
+
+    assert!(transport.requests().is_empty());
+    assert!(SubmissionQueue::load(&work_directory.queue)
+        .unwrap()
+        .is_empty());
+}
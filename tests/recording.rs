@@ -0,0 +1,93 @@
+mod common;
+
+use common::*;
+use primenet_rs::{
+    error::Error,
+    options::{PrimenetOptions, ProofOptions},
+    p95_work::PrimenetWorkType,
+    primenet_runtime::primenet_runtime_with_transport,
+    recording::{RecordingTransport, ReplayTransport, REDACTED},
+    transport::ReqwestTransport,
+};
+use std::fs::{read_dir, read_to_string, write};
+
+const DC_1: &str = "DoubleCheck=7A30B8B6C0FC79C534A271D9561F7DCC,51234577,73,1";
+const RESULT_1: &str = "M51234559 is not prime. Res64: 1A2B3C4D5E6F7081. Wh8: 00000000,00000000";
+
+fn options(dir: &tempfile::TempDir, server: &MockServer) -> PrimenetOptions {
+    PrimenetOptions {
+        credentials: (PRIMENET_USER.to_string(), PRIMENET_PASS.to_string()),
+        work_type: PrimenetWorkType::DoubleCheckLlTests,
+        fallback_work_type: None,
+        cores: 1,
+        proofs: ProofOptions::Archive,
+        general_options: general_options(dir, server, 1),
+    }
+}
+
+#[test]
+fn recorded_session_replays_without_the_server() {
+    let recording = tempfile::tempdir().unwrap();
+    let primenet_options = {
+        let server = MockServer::start();
+        server.add_primenet_work(&[DC_1]);
+        let dir = example_workdir("mlucas-example-workdir");
+        write(dir.path().join("results.txt"), format!("{}\n", RESULT_1)).unwrap();
+        let transport =
+            RecordingTransport::new(ReqwestTransport::new().unwrap(), recording.path()).unwrap();
+        primenet_runtime_with_transport(&transport, options(&dir, &server)).unwrap();
+        options(&dir, &server)
+    };
+
+    let mut files = read_dir(recording.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    files.sort();
+    assert_eq!(
+        files,
+        vec![
+            "0001-GET-root.json",
+            "0002-POST-root.json",
+            "0003-GET-manual_assignment.json",
+            "0004-POST-manual_result.json",
+        ]
+    );
+    let login = read_to_string(recording.path().join(&files[1])).unwrap();
+    assert!(!login.contains(PRIMENET_PASS));
+    assert!(login.contains(REDACTED));
+
+    // Same conversation again in a fresh work directory, with the server gone.
+    let dir = example_workdir("mlucas-example-workdir");
+    write(dir.path().join("results.txt"), format!("{}\n", RESULT_1)).unwrap();
+    let replay = ReplayTransport::new(recording.path()).unwrap();
+    let mut replay_options = primenet_options;
+    replay_options.general_options.work_directory = dir.path().display().to_string();
+    primenet_runtime_with_transport(&replay, replay_options).unwrap();
+
+    assert_eq!(replay.remaining(), 0);
+    assert_eq!(lines(dir.path().join("worktodo.ini")), vec![DC_1]);
+    assert_eq!(lines(dir.path().join("results.sent")), vec![RESULT_1]);
+    assert_unlocked(&dir);
+}
+
+#[test]
+fn replay_rejects_requests_that_go_off_script() {
+    let recording = tempfile::tempdir().unwrap();
+    let server = MockServer::start();
+    {
+        let dir = example_workdir("mlucas-example-workdir");
+        let transport =
+            RecordingTransport::new(ReqwestTransport::new().unwrap(), recording.path()).unwrap();
+        primenet_runtime_with_transport(&transport, options(&dir, &server)).unwrap();
+    }
+
+    // Pointing at a different server changes the login URL.
+    let other_server = MockServer::start();
+    let dir = example_workdir("mlucas-example-workdir");
+    let replay = ReplayTransport::new(recording.path()).unwrap();
+    let result = primenet_runtime_with_transport(&replay, options(&dir, &other_server));
+
+    assert!(matches!(result, Err(Error::Network { .. })));
+    assert!(other_server.requests().is_empty());
+}
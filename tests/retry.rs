@@ -0,0 +1,149 @@
+use primenet_rs::{
+    retry::{RetryPolicy, RetryStats, RetryingTransport},
+    transport::{Request, ScriptedTransport, Transport},
+};
+use std::time::Duration;
+
+fn no_wait(retries: u32) -> RetryPolicy {
+    RetryPolicy {
+        retries,
+        initial_delay: Duration::from_secs(0),
+        max_delay: Duration::from_secs(0),
+    }
+}
+
+fn request() -> Request {
+    Request::get("https://www.mersenne.org/manual_assignment/")
+}
+
+#[test]
+fn connect_failures_are_retried() {
+    let scripted = ScriptedTransport::new();
+    scripted
+        .fail("connection refused")
+        .fail("connection refused")
+        .respond(200, "ok");
+    let transport = RetryingTransport::new(&scripted, no_wait(3));
+
+    let response = transport.send(&request()).unwrap();
+
+    assert_eq!(response.body, "ok");
+    assert_eq!(scripted.requests().len(), 3);
+    assert_eq!(
+        transport.take_stats(),
+        Some(RetryStats {
+            requests: 1,
+            retries: 2,
+            failures: 0,
+        })
+    );
+    // Taking the stats starts a new cycle.
+    assert_eq!(transport.take_stats(), Some(RetryStats::default()));
+}
+
+#[test]
+fn server_errors_and_rate_limits_are_retried() {
+    let scripted = ScriptedTransport::new();
+    scripted
+        .respond(503, "maintenance")
+        .respond(429, "slow down")
+        .respond(200, "ok");
+    let transport = RetryingTransport::new(&scripted, no_wait(3));
+
+    assert_eq!(transport.send(&request()).unwrap().status, 200);
+    assert_eq!(scripted.requests().len(), 3);
+}
+
+#[test]
+fn gives_up_with_the_last_response_after_running_out_of_retries() {
+    let scripted = ScriptedTransport::new();
+    scripted
+        .respond(502, "one")
+        .respond(502, "two")
+        .respond(502, "three");
+    let transport = RetryingTransport::new(&scripted, no_wait(2));
+
+    let response = transport.send(&request()).unwrap();
+
+    assert_eq!((response.status, response.body.as_str()), (502, "three"));
+    assert_eq!(
+        transport.take_stats(),
+        Some(RetryStats {
+            requests: 1,
+            retries: 2,
+            failures: 1,
+        })
+    );
+}
+
+#[test]
+fn client_errors_are_not_retried() {
+    let scripted = ScriptedTransport::new();
+    scripted.respond(404, "not found").respond(200, "ok");
+    let transport = RetryingTransport::new(&scripted, no_wait(3));
+
+    assert_eq!(transport.send(&request()).unwrap().status, 404);
+    assert_eq!(scripted.remaining(), 1);
+}
+
+#[test]
+fn errors_that_would_repeat_are_not_retried() {
+    // An empty script fails every request the same way.
+    let scripted = ScriptedTransport::new();
+    let transport = RetryingTransport::new(&scripted, no_wait(3));
+
+    assert!(transport.send(&request()).is_err());
+    assert_eq!(scripted.requests().len(), 1);
+}
+
+#[test]
+fn posts_are_only_retried_if_they_never_got_through() {
+    let post = Request::post("https://www.mersenne.org/manual_result/");
+    let scripted = ScriptedTransport::new();
+    scripted.fail("connection refused").respond(200, "ok");
+    let transport = RetryingTransport::new(&scripted, no_wait(3));
+    assert_eq!(transport.send(&post).unwrap().status, 200);
+    assert_eq!(scripted.requests().len(), 2);
+
+    // Primenet may have taken the result before the answer timed out, or before a proxy gave up
+    // on it, so sending it again could submit it twice.
+    let scripted = ScriptedTransport::new();
+    scripted.time_out("timed out").respond(200, "ok");
+    let transport = RetryingTransport::new(&scripted, no_wait(3));
+    assert!(transport.send(&post).is_err());
+    assert_eq!(scripted.requests().len(), 1);
+
+    let scripted = ScriptedTransport::new();
+    scripted.respond(502, "bad gateway").respond(200, "ok");
+    let transport = RetryingTransport::new(&scripted, no_wait(3));
+    assert_eq!(transport.send(&post).unwrap().status, 502);
+    assert_eq!(scripted.requests().len(), 1);
+}
+
+#[test]
+fn timeouts_are_retried_for_gets() {
+    let scripted = ScriptedTransport::new();
+    scripted.time_out("timed out").respond(200, "ok");
+    let transport = RetryingTransport::new(&scripted, no_wait(3));
+
+    assert_eq!(transport.send(&request()).unwrap().status, 200);
+    assert_eq!(scripted.requests().len(), 2);
+}
+
+#[test]
+fn delays_back_off_exponentially_with_jitter_up_to_the_cap() {
+    let policy = RetryPolicy {
+        retries: 10,
+        initial_delay: Duration::from_secs(4),
+        max_delay: Duration::from_secs(60),
+    };
+    for _ in 0..20 {
+        let first = policy.delay(0);
+        assert!(first >= Duration::from_secs(2) && first <= Duration::from_secs(4));
+        let third = policy.delay(2);
+        assert!(third >= Duration::from_secs(8) && third <= Duration::from_secs(16));
+        let capped = policy.delay(9);
+        assert!(capped >= Duration::from_secs(30) && capped <= Duration::from_secs(60));
+    }
+    assert_eq!(RetryPolicy::none().retries, 0);
+}
@@ -0,0 +1,157 @@
+mod common;
+
+use chrono::{Duration, TimeZone, Utc};
+use common::*;
+use primenet_rs::{
+    assignment::ExponentRange,
+    cookie_jar::CookieJar,
+    endpoints::Endpoints,
+    options::{PrimenetOptions, ProofOptions},
+    p95_work::PrimenetWorkType,
+    primenet_runtime::{primenet_request, primenet_runtime_with_transport, with_primenet_session},
+    transport::{ReqwestTransport, ScriptedTransport},
+    work_directory::WorkDirectory,
+};
+use reqwest::Url;
+
+const DC_1: &str = "DoubleCheck=7A30B8B6C0FC79C534A271D9561F7DCC,51234577,73,1";
+const DC_2: &str = "DoubleCheck=BC914675C81023F252E92CF034BEFF6C,51234611,73,1";
+
+fn options(dir: &tempfile::TempDir, server: &MockServer, num_cache: usize) -> PrimenetOptions {
+    PrimenetOptions {
+        credentials: (PRIMENET_USER.to_string(), PRIMENET_PASS.to_string()),
+        work_type: PrimenetWorkType::DoubleCheckLlTests,
+        fallback_work_type: None,
+        cores: 1,
+        proofs: ProofOptions::Archive,
+        general_options: general_options(dir, server, num_cache),
+    }
+}
+
+#[test]
+fn saved_session_is_reused_by_the_next_run() {
+    let server = MockServer::start();
+    server.add_primenet_work(&[DC_1, DC_2]);
+    let dir = example_workdir("mlucas-example-workdir");
+    let jar = dir.path().join("cookies.json");
+
+    let transport = ReqwestTransport::with_cookie_jar(&jar).unwrap();
+    primenet_runtime_with_transport(&transport, options(&dir, &server, 1)).unwrap();
+    let transport = ReqwestTransport::with_cookie_jar(&jar).unwrap();
+    primenet_runtime_with_transport(&transport, options(&dir, &server, 2)).unwrap();
+
+    assert_eq!(server.logins(), 1);
+    assert_eq!(lines(dir.path().join("worktodo.ini")), vec![DC_1, DC_2]);
+    assert_eq!(CookieJar::load(&jar).unwrap().len(), 1);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&jar).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+    assert_unlocked(&dir);
+}
+
+#[test]
+fn expired_saved_session_logs_in_again() {
+    let server = MockServer::start();
+    server.add_primenet_work(&[DC_1]);
+    let dir = example_workdir("mlucas-example-workdir");
+    let jar = dir.path().join("cookies.json");
+    let transport = ReqwestTransport::with_cookie_jar(&jar).unwrap();
+    primenet_runtime_with_transport(&transport, options(&dir, &server, 0)).unwrap();
+
+    server.expire_sessions();
+    let transport = ReqwestTransport::with_cookie_jar(&jar).unwrap();
+    primenet_runtime_with_transport(&transport, options(&dir, &server, 1)).unwrap();
+
+    assert_eq!(server.logins(), 2);
+    assert_eq!(lines(dir.path().join("worktodo.ini")), vec![DC_1]);
+}
+
+#[test]
+fn session_expiring_part_way_logs_in_again_and_retries() {
+    let dir = example_workdir("mlucas-example-workdir");
+    let work_directory = WorkDirectory::new(dir.path());
+    let endpoints = Endpoints::default();
+    let transport = ScriptedTransport::new();
+    transport
+        .respond(200, "<input type=\"password\" name=\"user_password\">")
+        .respond(200, "user<br>logged in")
+        .respond(200, &format!("<pre>\n{}\n</pre>", DC_1));
+
+    with_primenet_session(&transport, &endpoints, "user", "pass", || {
+        primenet_request(
+            &transport,
+            &endpoints,
+            1,
+            &work_directory,
+            PrimenetWorkType::DoubleCheckLlTests,
+            1,
+            ExponentRange::default(),
+        )
+    })
+    .unwrap();
+
+    let requests = transport.requests();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[1].url, "https://www.mersenne.org/");
+    assert_eq!(lines(work_directory.worktodo.clone()), vec![DC_1]);
+    assert!(!work_directory.worktodo_lock.exists());
+}
+
+#[test]
+fn cookies_only_go_where_they_belong() {
+    let now = Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap();
+    let site = Url::parse("https://www.mersenne.org/").unwrap();
+    let mut jar = CookieJar::default();
+
+    assert!(jar.store(&site, "PHPSESSID=abc; path=/; HttpOnly", now));
+    assert!(jar.store(&site, "pref=1; Domain=.mersenne.org; Path=/account", now));
+    assert!(jar.store(&site, "token=x; Secure", now));
+    // Another site can't set cookies for mersenne.org.
+    assert!(!jar.store(&site, "evil=1; Domain=example.com", now));
+
+    let header = |url: &str| jar.header(&Url::parse(url).unwrap(), now);
+    assert_eq!(
+        header("https://www.mersenne.org/manual_result/").as_deref(),
+        Some("PHPSESSID=abc; token=x")
+    );
+    assert_eq!(
+        header("https://mersenne.org/account/settings").as_deref(),
+        Some("pref=1")
+    );
+    assert_eq!(
+        header("http://www.mersenne.org/accounts").as_deref(),
+        Some("PHPSESSID=abc")
+    );
+    assert_eq!(header("https://www.gpu72.com/"), None);
+}
+
+#[test]
+fn expired_cookies_are_dropped() {
+    let now = Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap();
+    let site = Url::parse("https://www.mersenne.org/").unwrap();
+    let mut jar = CookieJar::default();
+    jar.store(&site, "PHPSESSID=abc", now);
+    jar.store(&site, "short=1; Max-Age=60", now);
+    jar.store(&site, "dated=1; Expires=Thu, 01 Jan 2026 13:00:00 GMT", now);
+
+    assert_eq!(jar.len(), 3);
+    assert_eq!(
+        jar.header(&site, now + Duration::minutes(30)).as_deref(),
+        Some("PHPSESSID=abc; dated=1")
+    );
+    assert_eq!(
+        jar.header(&site, now + Duration::hours(2)).as_deref(),
+        Some("PHPSESSID=abc")
+    );
+
+    // Servers log people out by expiring the cookie.
+    assert!(jar.store(&site, "PHPSESSID=deleted; Max-Age=0", now));
+    assert_eq!(jar.len(), 2);
+    assert!(!jar
+        .cookies()
+        .iter()
+        .any(|cookie| cookie.name == "PHPSESSID"));
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use chrono::{Duration, TimeZone, Utc};
+use common::*;
+use primenet_rs::{
+    activity::WorkSource,
+    client::Client,
+    credit::iteration_credit,
+    gpuowl::Progress,
+    options::{PrimenetOptions, ProofOptions},
+    p95_work::PrimenetWorkType,
+    primenet_runtime::primenet_runtime,
+    status::{eta, Status},
+    work_directory::WorkDirectory,
+};
+use std::fs::write;
+
+const DC_1: &str = "DoubleCheck=7A30B8B6C0FC79C534A271D9561F7DCC,51234577,73,1";
+const DC_2: &str = "DoubleCheck=BC914675C81023F252E92CF034BEFF6C,51234611,73,1";
+const MANUAL: &str = "Test=N/A,89459323,76,1";
+const RESULT_1: &str = "M51234559 is not prime. Res64: 1A2B3C4D5E6F7081. Wh8: 00000000,00000000";
+const RESULT_2: &str = "M51234563 is not prime. Res64: 8070605040302010. Wh8: 00000000,00000000";
+const STAT_LINE: &str = "[2026-10-18 10:39:55] M89459323 Iter# = 44729661 [50.00% complete] \
+    clocks = 00:01:23.456 [  4.0000 msec/iter] Res64: 0123456789ABCDEF.";
+
+#[test]
+fn status_shows_cached_work_progress_and_activity() {
+    let server = MockServer::start();
+    server.add_primenet_work(&[DC_1, DC_2]);
+    let dir = tempfile::tempdir().unwrap();
+    let work_directory = WorkDirectory::for_client(dir.path(), Client::Mlucas);
+    write(&work_directory.worktodo, format!("{}\n", MANUAL)).unwrap();
+    write(&work_directory.results, format!("{}\n", RESULT_1)).unwrap();
+    write(
+        dir.path().join("p89459323.stat"),
+        format!("{}\n", STAT_LINE),
+    )
+    .unwrap();
+    let mut general_options = general_options(&dir, &server, 3);
+    general_options.client = Some(Client::Mlucas);
+
+    let before = Utc::now();
+    primenet_runtime(PrimenetOptions {
+        credentials: (PRIMENET_USER.to_string(), PRIMENET_PASS.to_string()),
+        work_type: PrimenetWorkType::DoubleCheckLlTests,
+        fallback_work_type: None,
+        cores: 1,
+        proofs: ProofOptions::Archive,
+        general_options,
+    })
+    .unwrap();
+    // Finished since, and not submitted yet.
+    write(&work_directory.results, format!("{}\n", RESULT_2)).unwrap();
+    let now = Utc::now();
+    let status = Status::read(&work_directory, now).unwrap();
+
+    let cached = status
+        .cached
+        .iter()
+        .map(|cached| (cached.assignment.exponent, cached.source))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        cached,
+        vec![
+            (89459323, None),
+            (51234577, Some(WorkSource::Primenet)),
+            (51234611, Some(WorkSource::Primenet)),
+        ]
+    );
+    let current = status.current.unwrap();
+    assert_eq!(current.progress.iteration, 44729661);
+    assert_eq!(status.progress_unavailable, None);
+    // The other half at Mlucas's 4 ms an iteration.
+    let eta = current.eta.unwrap();
+    assert!((eta - now - Duration::seconds(178918)).num_seconds().abs() <= 1);
+    assert_eq!(status.results_waiting, 1);
+    assert!(status.activity.last_fetch.unwrap() >= before);
+    assert!(status.activity.last_submit.unwrap() >= before);
+    assert!(status.locks.is_empty());
+    let shown = status.to_string();
+    assert!(shown.contains("7A30B8B6C0FC79C534A271D9561F7DCC"));
+    assert!(shown.contains("Primenet"));
+    assert!(shown.contains("M89459323 is 50.0% done"));
+    assert!(shown.contains("Locks held:  none"));
+}
+
+#[test]
+fn status_lists_held_locks_and_defaults_to_nothing() {
+    let dir = tempfile::tempdir().unwrap();
+    let work_directory = WorkDirectory::for_client(dir.path(), Client::Mfaktc);
+    write(&work_directory.worktodo_lock, "").unwrap();
+
+    let status = Status::read(&work_directory, Utc::now()).unwrap();
+
+    assert!(status.cached.is_empty());
+    assert_eq!(status.current, None);
+    assert_eq!(status.progress_unavailable, None);
+    assert_eq!(status.results_waiting, 0);
+    assert_eq!(status.activity.last_fetch, None);
+    assert_eq!(status.locks, vec![work_directory.worktodo_lock.clone()]);
+    let shown = status.to_string();
+    assert!(shown.contains("No assignments cached."));
+    assert!(shown.contains("Last fetch:  never"));
+    assert!(shown.contains("worktodo.txt.lck"));
+}
+
+#[test]
+fn status_says_when_the_client_progress_cannot_be_read() {
+    let dir = tempfile::tempdir().unwrap();
+    let work_directory = WorkDirectory::for_client(dir.path(), Client::Mprime);
+    write(&work_directory.worktodo, format!("{}\n", DC_1)).unwrap();
+
+    let status = Status::read(&work_directory, Utc::now()).unwrap();
+
+    assert_eq!(status.current, None);
+    assert_eq!(status.progress_unavailable, Some(Client::Mprime));
+    assert!(status
+        .to_string()
+        .contains("Progress isn't available for mprime."));
+}
+
+#[test]
+fn eta_falls_back_to_the_measured_throughput() {
+    let now = Utc.with_ymd_and_hms(2026, 10, 18, 0, 0, 0).unwrap();
+    let progress = Progress {
+        exponent: 96364649,
+        iteration: 48182324,
+        iterations: 96364649,
+    };
+    assert_eq!(eta(&progress, None, None, now), None);
+    // At the rate that gets through half the test in a day.
+    let rate = iteration_credit(96364649) * 48182325.0;
+    let eta = eta(&progress, None, Some(rate), now).unwrap();
+    assert!((eta - now - Duration::days(1)).num_seconds().abs() <= 1);
+}
@@ -0,0 +1,212 @@
+mod common;
+
+use chrono::{Duration, TimeZone, Utc};
+use common::*;
+use primenet_rs::{
+    client::Client,
+    credit::{iteration_credit, ll_credit, result_credit, tf_credit},
+    gpuowl::Progress,
+    mlucas::{stat_line_timing, stat_timing, StatTiming},
+    options::{PrimenetOptions, ProofOptions},
+    p95_work::PrimenetWorkType,
+    primenet_runtime::primenet_runtime,
+    throughput::{
+        measure_throughput, result_time, Throughput, ThroughputSource, THROUGHPUT_SAMPLES,
+    },
+    work_directory::WorkDirectory,
+    work_result::WorkResult,
+};
+use std::fs::{create_dir_all, write};
+
+const PRP_1: &str = "PRP=BC914675C81023F252E92CF034BEFF6C,1,2,96364649,-1,77,0";
+const PRPDC_1: &str = "PRPDC=7A30B8B6C0FC79C534A271D9561F7DCC,1,2,51234577,-1,73,0";
+const PRPDC_2: &str = "PRPDC=3F2A0C6BA2B55A2F8E1C9D7E6B5A4F30,1,2,51234587,-1,73,0";
+const STAT_LINE: &str = "[2021-04-18 10:39:55] M96364649 Iter# = 20000 [ 0.02% complete] clocks \
+    = 00:01:23.456 [  8.0000 msec/iter] Res64: 0123456789ABCDEF. AvgMaxErr = 0.123. MaxErr = 0.156.";
+
+fn prp_result(exponent: u64, timestamp: &str) -> String {
+    format!(
+        r#"{{"status":"C", "exponent":{}, "worktype":"PRP-3", "res64":"1A2B3C4D5E6F7081", "timestamp":"{}"}}"#,
+        exponent, timestamp
+    )
+}
+
+fn credit(line: &str) -> Option<f64> {
+    result_credit(&WorkResult::parse(line).unwrap())
+}
+
+#[test]
+fn results_are_timed_and_credited() {
+    let prp = prp_result(96364649, "2026-10-17 06:00:00");
+    assert_eq!(
+        result_time(&prp),
+        Some(Utc.with_ymd_and_hms(2026, 10, 17, 6, 0, 0).unwrap())
+    );
+    assert_eq!(credit(&prp), Some(ll_credit(96364649)));
+    let tf = "[Sun Oct 18 12:00:00 2026] no factor for M332194529 from 2^74 to 2^75 [mfaktc 0.21 \
+        barrett76_mul32_gs]";
+    assert!(result_time(tf).is_some());
+    assert_eq!(credit(tf), Some(tf_credit(332194529, 74, 75)));
+    assert_eq!(
+        credit("M332194679 has a factor: 701595162049 [TF:39:40*:mfaktc 0.21 barrett76_mul32_gs]"),
+        Some(tf_credit(332194679, 39, 40))
+    );
+    // Nothing to say how far an untimed factor was looked for.
+    assert_eq!(
+        result_time("M89459323 has a factor: 1193028769481932346719"),
+        None
+    );
+    assert_eq!(
+        credit("M89459323 has a factor: 1193028769481932346719"),
+        None
+    );
+}
+
+#[test]
+fn mlucas_stat_files_give_the_time_per_iteration() {
+    assert_eq!(
+        stat_line_timing(STAT_LINE),
+        Some(StatTiming {
+            exponent: 96364649,
+            iteration: 20000,
+            seconds_per_iteration: 0.008,
+        })
+    );
+    let dir = tempfile::tempdir().unwrap();
+    assert_eq!(stat_timing(dir.path(), 96364649), None);
+    write(
+        dir.path().join("p96364649.stat"),
+        format!(
+            "{}\n{}\nM96364649: using FFT length 5632K\n",
+            STAT_LINE
+                .replace("20000", "10000")
+                .replace("8.0000", "9.0000"),
+            STAT_LINE
+        ),
+    )
+    .unwrap();
+    assert_eq!(
+        stat_timing(dir.path(), 96364649).map(|timing| timing.iteration),
+        Some(20000)
+    );
+
+    let mut throughput = Throughput::default();
+    let now = Utc::now();
+    let timing = stat_line_timing(STAT_LINE).unwrap();
+    throughput.measure_stat(timing, now);
+    // The same line again isn't a new measurement.
+    throughput.measure_stat(timing, now);
+    assert_eq!(throughput.samples.len(), 1);
+    assert_eq!(throughput.samples[0].source, ThroughputSource::MlucasStat);
+    let expected = iteration_credit(96364649) * 86400.0 / 0.008;
+    assert!((throughput.ghz_days_per_day().unwrap() - expected).abs() < 1e-9);
+}
+
+#[test]
+fn results_and_checkpoints_make_a_rolling_average() {
+    let mut throughput = Throughput::default();
+    let mut results = vec![prp_result(96364649, "2026-10-01 00:00:00")];
+    // One result only marks where the clock starts.
+    throughput.measure_results(&results);
+    assert!(throughput.samples.is_empty());
+    results.push(prp_result(96364691, "2026-10-11 00:00:00"));
+    throughput.measure_results(&results);
+    throughput.measure_results(&results);
+    assert_eq!(throughput.samples.len(), 1);
+    let expected = ll_credit(96364691) / 10.0;
+    assert!((throughput.ghz_days_per_day().unwrap() - expected).abs() < 1e-9);
+
+    let start = Utc.with_ymd_and_hms(2026, 10, 11, 0, 0, 0).unwrap();
+    let progress = |iteration| Progress {
+        exponent: 96364649,
+        iteration,
+        iterations: 96364649,
+    };
+    throughput.measure_checkpoint(progress(1_000_000), start);
+    throughput.measure_checkpoint(progress(3_000_000), start + Duration::days(1));
+    assert_eq!(throughput.samples.len(), 2);
+    assert_eq!(throughput.samples[1].source, ThroughputSource::Checkpoint);
+    let per_day = iteration_credit(96364649) * 2_000_000.0;
+    assert!((throughput.samples[1].ghz_days_per_day - per_day).abs() < 1e-9);
+    // A different test starts over rather than counting backwards.
+    throughput.measure_checkpoint(
+        Progress {
+            exponent: 96364691,
+            iteration: 10,
+            iterations: 96364691,
+        },
+        start + Duration::days(2),
+    );
+    assert_eq!(throughput.samples.len(), 2);
+
+    // Only the newest measurements count.
+    for day in 3..20 {
+        throughput.measure_checkpoint(
+            Progress {
+                exponent: 96364691,
+                iteration: day * 1_000_000,
+                iterations: 96364691,
+            },
+            start + Duration::days(day as i64),
+        );
+    }
+    assert_eq!(throughput.samples.len(), THROUGHPUT_SAMPLES);
+    assert!(throughput
+        .samples
+        .iter()
+        .all(|sample| sample.source == ThroughputSource::Checkpoint));
+}
+
+#[test]
+fn measured_throughput_sizes_the_cache() {
+    let server = MockServer::start();
+    server.add_primenet_work(&[PRPDC_1, PRPDC_2]);
+    let dir = tempfile::tempdir().unwrap();
+    let work_directory = WorkDirectory::for_client(dir.path(), Client::Gpuowl);
+    write(&work_directory.worktodo, format!("{}\n", PRP_1)).unwrap();
+    write(&work_directory.results, "").unwrap();
+    create_dir_all(dir.path().join("96364649")).unwrap();
+    write(
+        dir.path().join("96364649/96364649.prp"),
+        "PRP 12 96364649 2500000 0 0\n",
+    )
+    .unwrap();
+
+    let throughput = measure_throughput(&work_directory).unwrap();
+    assert_eq!(throughput.ghz_days_per_day(), None);
+    assert_eq!(
+        throughput.last_checkpoint.map(|mark| mark.iteration),
+        Some(2500000)
+    );
+    assert!(work_directory.throughput.exists());
+
+    // About 345 GHz-days of PRP test cached, which is 13.8 days at 25 GHz-days a day.
+    let mut throughput = Throughput::load(&work_directory.throughput).unwrap();
+    throughput.measure_results(&[
+        prp_result(96364651, "2026-10-01 00:00:00"),
+        prp_result(96364691, "2026-10-14 19:00:29"),
+    ]);
+    let measured = throughput.ghz_days_per_day().unwrap();
+    assert!((measured - 25.0).abs() < 0.1, "{}", measured);
+    throughput.save(&work_directory.throughput).unwrap();
+    let mut general_options = general_options(&dir, &server, 1);
+    general_options.client = Some(Client::Gpuowl);
+    general_options.days_of_work = Some(15.0);
+    let options = PrimenetOptions {
+        credentials: (PRIMENET_USER.to_string(), PRIMENET_PASS.to_string()),
+        work_type: PrimenetWorkType::DoubleCheckPrpTests,
+        fallback_work_type: None,
+        cores: 1,
+        proofs: ProofOptions::Archive,
+        general_options,
+    };
+
+    primenet_runtime(options).unwrap();
+
+    assert_eq!(lines(work_directory.worktodo.clone()), vec![PRP_1, PRPDC_1]);
+    assert_eq!(
+        server.requests_to("GET", "/manual_assignment/")[0].query_param("num_to_get"),
+        Some("1")
+    );
+    assert_unlocked(&dir);
+}
@@ -0,0 +1,361 @@
+mod common;
+
+use common::{example_workdir, lines};
+use primenet_rs::{
+    assignment::ExponentRange,
+    endpoints::Endpoints,
+    error::Error,
+    gpu72_runtime::gpu72_check_login,
+    p95_work::PrimenetWorkType,
+    primenet_runtime::{
+        primenet_login, primenet_request, primenet_submit, request_with_fallback,
+        WorkRequestOutcome, LIMIT_BACKOFF, NO_WORK_BACKOFF,
+    },
+    submission_queue::SubmissionQueue,
+    transport::{Method, ScriptedTransport},
+    util::lock_file,
+    work_directory::WorkDirectory,
+};
+use std::fs::write;
+
+const DC_1: &str = "DoubleCheck=7A30B8B6C0FC79C534A271D9561F7DCC,51234577,73,1";
+const DC_HUGE: &str = "DoubleCheck=BC914675C81023F252E92CF034BEFF6C,332194529,81,1";
+const RESULT_1: &str = "M51234559 is not prime. Res64: 1A2B3C4D5E6F7081. Wh8: 00000000,00000000";
+const RESULT_2: &str = "M51234563 is not prime. Res64: 8070605040302010. Wh8: 00000000,00000000";
+const RESULT_3: &str = "M51234571 is not prime. Res64: 0F0E0D0C0B0A0908. Wh8: 00000000,00000000";
+
+#[test]
+fn request_caches_validated_assignments() {
+    let dir = example_workdir("mlucas-example-workdir");
+    let work_directory = WorkDirectory::new(dir.path());
+    let transport = ScriptedTransport::new();
+    transport.respond(
+        200,
+        &format!("<pre>\nDoubleCheck=garbage,1,2,3\n{}\n</pre>", DC_1),
+    );
+
+    primenet_request(
+        &transport,
+        &Endpoints::default(),
+        2,
+        &work_directory,
+        PrimenetWorkType::DoubleCheckLlTests,
+        1,
+        ExponentRange::default(),
+    )
+    .unwrap();
+
+    assert_eq!(lines(work_directory.worktodo.clone()), vec![DC_1]);
+    let requests = transport.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, Method::Get);
+    assert_eq!(
+        requests[0].url,
+        "https://www.mersenne.org/manual_assignment/"
+    );
+    assert!(requests[0]
+        .query
+        .contains(&("num_to_get".to_string(), "2".to_string())));
+    assert!(!work_directory.worktodo_lock.exists());
+}
+
+#[test]
+fn request_failure_releases_the_lock_and_writes_nothing() {
+    let dir = example_workdir("mlucas-example-workdir");
+    let work_directory = WorkDirectory::new(dir.path());
+    let transport = ScriptedTransport::new();
+    transport.fail("connection refused");
+
+    let result = primenet_request(
+        &transport,
+        &Endpoints::default(),
+        1,
+        &work_directory,
+        PrimenetWorkType::DoubleCheckLlTests,
+        1,
+        ExponentRange::default(),
+    );
+
+    assert!(matches!(result, Err(Error::Network { .. })));
+    assert!(lines(work_directory.worktodo.clone()).is_empty());
+    assert!(!work_directory.worktodo_lock.exists());
+}
+
+#[test]
+fn request_only_caches_assignments_in_range() {
+    let dir = example_workdir("mlucas-example-workdir");
+    let work_directory = WorkDirectory::new(dir.path());
+    let transport = ScriptedTransport::new();
+    transport.respond(200, &format!("<pre>\n{}\n{}\n</pre>", DC_HUGE, DC_1));
+
+    primenet_request(
+        &transport,
+        &Endpoints::default(),
+        2,
+        &work_directory,
+        PrimenetWorkType::DoubleCheckLlTests,
+        4,
+        ExponentRange {
+            min: Some(50_000_000),
+            max: Some(60_000_000),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(lines(work_directory.worktodo.clone()), vec![DC_1]);
+    let query = &transport.requests()[0].query;
+    for (key, value) in &[
+        ("cores", "4"),
+        ("exp_lo", "50000000"),
+        ("exp_hi", "60000000"),
+    ] {
+        assert!(query.contains(&(key.to_string(), value.to_string())));
+    }
+    assert!(!work_directory.worktodo_lock.exists());
+}
+
+#[test]
+fn request_reports_maintenance_as_unavailable() {
+    let dir = example_workdir("mlucas-example-workdir");
+    let work_directory = WorkDirectory::new(dir.path());
+    let transport = ScriptedTransport::new();
+    transport.respond(200, "<p>PrimeNet is down for maintenance.</p>");
+
+    let result = primenet_request(
+        &transport,
+        &Endpoints::default(),
+        1,
+        &work_directory,
+        PrimenetWorkType::DoubleCheckLlTests,
+        1,
+        ExponentRange::default(),
+    );
+
+    assert!(matches!(result, Err(Error::Unavailable(_))));
+    assert!(!work_directory.worktodo_lock.exists());
+}
+
+#[test]
+fn request_falls_back_when_there_is_no_work() {
+    let dir = example_workdir("mlucas-example-workdir");
+    let work_directory = WorkDirectory::new(dir.path());
+    let transport = ScriptedTransport::new();
+    transport
+        .respond(200, "<p>No assignments available.</p>")
+        .respond(200, &format!("<pre>\n{}\n</pre>", DC_1));
+
+    let backoff = request_with_fallback(
+        &transport,
+        &Endpoints::default(),
+        1,
+        &work_directory,
+        PrimenetWorkType::DoubleCheckPrpTests,
+        Some(PrimenetWorkType::DoubleCheckLlTests),
+        1,
+        ExponentRange::default(),
+    )
+    .unwrap();
+
+    assert_eq!(backoff, None);
+    assert_eq!(lines(work_directory.worktodo.clone()), vec![DC_1]);
+    let prefs = transport
+        .requests()
+        .iter()
+        .map(|request| {
+            request
+                .query
+                .iter()
+                .find(|(key, _)| key == "pref")
+                .map(|(_, value)| value.clone())
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(prefs, vec!["151", "101"]);
+}
+
+#[test]
+fn request_backs_off_when_primenet_turns_it_down() {
+    let dir = example_workdir("mlucas-example-workdir");
+    let work_directory = WorkDirectory::new(dir.path());
+    let transport = ScriptedTransport::new();
+    transport
+        .respond(200, "<p>No assignments available.</p>")
+        .respond(
+            200,
+            "<p>You have the maximum number of assignments allowed.</p>",
+        );
+    let request = || {
+        request_with_fallback(
+            &transport,
+            &Endpoints::default(),
+            1,
+            &work_directory,
+            PrimenetWorkType::DoubleCheckLlTests,
+            Some(PrimenetWorkType::DoubleCheckLlTests),
+            1,
+            ExponentRange::default(),
+        )
+    };
+
+    assert_eq!(request().unwrap(), Some(NO_WORK_BACKOFF));
+    assert_eq!(request().unwrap(), Some(LIMIT_BACKOFF));
+    assert_eq!(transport.requests().len(), 2);
+    assert!(lines(work_directory.worktodo.clone()).is_empty());
+    assert_eq!(
+        primenet_request(
+            &transport,
+            &Endpoints::default(),
+            0,
+            &work_directory,
+            PrimenetWorkType::DoubleCheckLlTests,
+            1,
+            ExponentRange::default(),
+        )
+        .unwrap(),
+        WorkRequestOutcome::AlreadyCached
+    );
+}
+
+#[test]
+fn submit_keeps_what_was_not_sent_queued_after_a_failure() {
+    let dir = example_workdir("mlucas-example-workdir");
+    let work_directory = WorkDirectory::new(dir.path());
+    write(
+        &work_directory.results,
+        format!("{}\n{}\n{}\n", RESULT_1, RESULT_2, RESULT_3),
+    )
+    .unwrap();
+    let transport = ScriptedTransport::new();
+    transport.respond(200, "Accepted").fail("connection reset");
+
+    let result = primenet_submit(&transport, &Endpoints::default(), &work_directory);
+
+    assert!(matches!(result, Err(Error::Network { .. })));
+    assert_eq!(transport.requests().len(), 2);
+    assert_eq!(lines(work_directory.results_sent.clone()), vec![RESULT_1]);
+    assert!(lines(work_directory.results.clone()).is_empty());
+    let queue = SubmissionQueue::load(&work_directory.queue).unwrap();
+    let queued = queue
+        .entries
+        .iter()
+        .map(|entry| (entry.result.as_str(), entry.attempts))
+        .collect::<Vec<_>>();
+    assert_eq!(queued, vec![(RESULT_2, 1), (RESULT_3, 0)]);
+    assert!(queue.entries[0]
+        .last_error
+        .as_ref()
+        .unwrap()
+        .contains("connection reset"));
+    for lock in &work_directory.lockfile_paths() {
+        assert!(!lock.exists());
+    }
+}
+
+#[test]
+fn submit_skips_results_already_sent() {
+    let dir = example_workdir("mlucas-example-workdir");
+    let work_directory = WorkDirectory::new(dir.path());
+    write(
+        &work_directory.results,
+        format!("{}\n{}\n", RESULT_1, RESULT_2),
+    )
+    .unwrap();
+    write(&work_directory.results_sent, format!("{}\n", RESULT_1)).unwrap();
+    let transport = ScriptedTransport::new();
+    transport.respond(200, "Accepted");
+
+    primenet_submit(&transport, &Endpoints::default(), &work_directory).unwrap();
+
+    let requests = transport.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(
+        requests[0].form,
+        vec![("data".to_string(), RESULT_2.to_string())]
+    );
+    assert_eq!(
+        lines(work_directory.results_sent.clone()),
+        vec![RESULT_1, RESULT_2]
+    );
+    assert!(lines(work_directory.results.clone()).is_empty());
+}
+
+#[test]
+fn submit_takes_an_answer_over_a_busy_notice() {
+    let dir = example_workdir("mlucas-example-workdir");
+    let work_directory = WorkDirectory::new(dir.path());
+    write(&work_directory.results, format!("{}\n", RESULT_1)).unwrap();
+    let transport = ScriptedTransport::new();
+    transport.respond(
+        200,
+        &format!(
+            "<p>The server is busy, so pages may be slow.</p>\n\
+            <p>processing: {}<br>\nAccepted<br>\nCPU credit is 71.4620 GHz-days.</p>",
+            RESULT_1
+        ),
+    );
+
+    primenet_submit(&transport, &Endpoints::default(), &work_directory).unwrap();
+
+    assert_eq!(lines(work_directory.results_sent.clone()), vec![RESULT_1]);
+    assert!(SubmissionQueue::load(&work_directory.queue)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn submit_leaves_other_processes_locks_alone() {
+    let dir = example_workdir("mlucas-example-workdir");
+    let work_directory = WorkDirectory::new(dir.path());
+    write(&work_directory.results, format!("{}\n", RESULT_1)).unwrap();
+    lock_file(&work_directory.results_lock).unwrap();
+    let transport = ScriptedTransport::new();
+
+    let result = primenet_submit(&transport, &Endpoints::default(), &work_directory);
+
+    assert!(matches!(result, Err(Error::LockContention(_))));
+    assert!(transport.requests().is_empty());
+    assert!(!work_directory.worktodo_lock.exists());
+    assert!(work_directory.results_lock.exists());
+    assert_eq!(lines(work_directory.results.clone()), vec![RESULT_1]);
+}
+
+#[test]
+fn login_without_logged_in_marker_is_rejected() {
+    let transport = ScriptedTransport::new();
+    transport.respond(200, "<html>Login failed</html>");
+
+    let result = primenet_login(&transport, &Endpoints::default(), "user", "pass");
+
+    assert!(matches!(result, Err(Error::Authentication(_))));
+    assert_eq!(
+        transport.requests()[0].form,
+        vec![
+            ("user_login".to_string(), "user".to_string()),
+            ("user_password".to_string(), "pass".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn gpu72_login_check_maps_statuses() {
+    let transport = ScriptedTransport::new();
+    transport
+        .respond(200, "")
+        .respond(403, "Forbidden")
+        .respond(502, "Bad Gateway");
+    let endpoints = Endpoints::default();
+
+    assert!(gpu72_check_login(&transport, &endpoints, "user", "pass").is_ok());
+    assert!(matches!(
+        gpu72_check_login(&transport, &endpoints, "user", "pass"),
+        Err(Error::Authentication(_))
+    ));
+    assert!(matches!(
+        gpu72_check_login(&transport, &endpoints, "user", "pass"),
+        Err(Error::HttpStatus { status: 502, .. })
+    ));
+    assert_eq!(
+        transport.requests()[0].basic_auth,
+        Some(("user".to_string(), "pass".to_string()))
+    );
+}